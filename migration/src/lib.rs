@@ -3,6 +3,22 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table;
 mod m20250315_033605_create_blacklist_table;
 mod m20250403_163323_create_cached_embeddings;
+mod m20250412_090000_add_owner_to_cached_embeddings;
+mod m20250420_120000_add_pgvector_embeddings;
+mod m20250501_100000_create_documents_table;
+mod m20250510_090000_add_chunk_search_vector;
+mod m20250515_100000_add_chunking_settings;
+mod m20250520_090000_add_chunk_provenance;
+mod m20250525_090000_add_embedding_model;
+mod m20250601_090000_create_reindex_jobs;
+mod m20250610_090000_create_generation_jobs;
+mod m20250615_090000_create_generations;
+mod m20250620_090000_create_llm_usage;
+mod m20250625_090000_create_plans;
+mod m20250701_090000_add_generation_history;
+mod m20250705_090000_single_running_reindex_job;
+mod m20250710_090000_add_job_ids;
+mod m20250715_090000_add_generation_job_run_after;
 
 pub struct Migrator;
 
@@ -13,6 +29,22 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20250315_033605_create_blacklist_table::Migration),
             Box::new(m20250403_163323_create_cached_embeddings::Migration),
+            Box::new(m20250412_090000_add_owner_to_cached_embeddings::Migration),
+            Box::new(m20250420_120000_add_pgvector_embeddings::Migration),
+            Box::new(m20250501_100000_create_documents_table::Migration),
+            Box::new(m20250510_090000_add_chunk_search_vector::Migration),
+            Box::new(m20250515_100000_add_chunking_settings::Migration),
+            Box::new(m20250520_090000_add_chunk_provenance::Migration),
+            Box::new(m20250525_090000_add_embedding_model::Migration),
+            Box::new(m20250601_090000_create_reindex_jobs::Migration),
+            Box::new(m20250610_090000_create_generation_jobs::Migration),
+            Box::new(m20250615_090000_create_generations::Migration),
+            Box::new(m20250620_090000_create_llm_usage::Migration),
+            Box::new(m20250625_090000_create_plans::Migration),
+            Box::new(m20250701_090000_add_generation_history::Migration),
+            Box::new(m20250705_090000_single_running_reindex_job::Migration),
+            Box::new(m20250710_090000_add_job_ids::Migration),
+            Box::new(m20250715_090000_add_generation_job_run_after::Migration),
         ]
     }
 }
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .add_column(ColumnDef::new(CachedEmbeddings::OwnerId).uuid().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_cached_embeddings_owner_file_hash")
+                    .table(CachedEmbeddings::Table)
+                    .col(CachedEmbeddings::OwnerId)
+                    .col(CachedEmbeddings::FileHash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_cached_embeddings_owner_file_hash")
+                    .table(CachedEmbeddings::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .drop_column(CachedEmbeddings::OwnerId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CachedEmbeddings {
+    Table,
+    OwnerId,
+    FileHash,
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // pgvector is optional: servers without it keep using the usearch store
+        let available = db
+            .query_one(Statement::from_string(
+                manager.get_database_backend(),
+                "SELECT 1 FROM pg_available_extensions WHERE name = 'vector'",
+            ))
+            .await?
+            .is_some();
+
+        if !available {
+            println!("pgvector extension is not available, skipping embedding_vec column");
+            return Ok(());
+        }
+
+        db.execute_unprepared("CREATE EXTENSION IF NOT EXISTS vector")
+            .await?;
+
+        db.execute_unprepared(
+            "ALTER TABLE cached_embeddings ADD COLUMN IF NOT EXISTS embedding_vec vector \
+             GENERATED ALWAYS AS (embedding::vector) STORED",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE INDEX IF NOT EXISTS idx_cached_embeddings_embedding_vec_384 \
+             ON cached_embeddings USING hnsw ((embedding_vec::vector(384)) vector_cosine_ops) \
+             WHERE vector_dims(embedding_vec) = 384",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_cached_embeddings_embedding_vec_384")
+            .await?;
+        db.execute_unprepared("ALTER TABLE cached_embeddings DROP COLUMN IF EXISTS embedding_vec")
+            .await?;
+
+        Ok(())
+    }
+}
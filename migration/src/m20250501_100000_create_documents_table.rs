@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Documents::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Documents::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Documents::OwnerId).uuid().not_null())
+                    .col(ColumnDef::new(Documents::Name).string().not_null())
+                    .col(ColumnDef::new(Documents::FileHash).string().not_null())
+                    .col(ColumnDef::new(Documents::ExtractedText).text().not_null())
+                    .col(ColumnDef::new(Documents::ChunkCount).integer().not_null().default(0))
+                    .col(ColumnDef::new(Documents::Status).string_len(20).not_null())
+                    .col(ColumnDef::new(Documents::Error).text().null())
+                    .col(ColumnDef::new(Documents::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Documents::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_documents_owner_id")
+                    .table(Documents::Table)
+                    .col(Documents::OwnerId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Documents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Documents {
+    Table,
+    Id,
+    OwnerId,
+    Name,
+    FileHash,
+    ExtractedText,
+    ChunkCount,
+    Status,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+}
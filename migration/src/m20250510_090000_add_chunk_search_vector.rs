@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // full-text index backing the lexical half of hybrid retrieval
+        db.execute_unprepared(
+            "ALTER TABLE cached_embeddings ADD COLUMN IF NOT EXISTS chunk_tsv tsvector \
+             GENERATED ALWAYS AS (to_tsvector('english', chunk)) STORED",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE INDEX IF NOT EXISTS idx_cached_embeddings_chunk_tsv \
+             ON cached_embeddings USING gin (chunk_tsv)",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_cached_embeddings_chunk_tsv")
+            .await?;
+        db.execute_unprepared("ALTER TABLE cached_embeddings DROP COLUMN IF EXISTS chunk_tsv")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // rows written before this migration have no signature and are
+        // re-chunked the next time their document is used
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .add_column(ColumnDef::new(CachedEmbeddings::Chunking).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Documents::Table)
+                    .add_column(ColumnDef::new(Documents::ChunkingOverrides).json_binary().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Documents::Table)
+                    .drop_column(Documents::ChunkingOverrides)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .drop_column(CachedEmbeddings::Chunking)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CachedEmbeddings {
+    Table,
+    Chunking,
+}
+
+#[derive(Iden)]
+enum Documents {
+    Table,
+    ChunkingOverrides,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .add_column(ColumnDef::new(CachedEmbeddings::Page).integer().null())
+                    .add_column(ColumnDef::new(CachedEmbeddings::Heading).text().null())
+                    .add_column(ColumnDef::new(CachedEmbeddings::StartOffset).big_integer().null())
+                    .add_column(ColumnDef::new(CachedEmbeddings::EndOffset).big_integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .drop_column(CachedEmbeddings::Page)
+                    .drop_column(CachedEmbeddings::Heading)
+                    .drop_column(CachedEmbeddings::StartOffset)
+                    .drop_column(CachedEmbeddings::EndOffset)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CachedEmbeddings {
+    Table,
+    Page,
+    Heading,
+    StartOffset,
+    EndOffset,
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // rows without a model predate this column and are re-embedded on use
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .add_column(ColumnDef::new(CachedEmbeddings::EmbeddingModel).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CachedEmbeddings::Table)
+                    .drop_column(CachedEmbeddings::EmbeddingModel)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CachedEmbeddings {
+    Table,
+    EmbeddingModel,
+}
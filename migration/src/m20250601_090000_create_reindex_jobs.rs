@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReindexJobs::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ReindexJobs::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ReindexJobs::Status).string_len(20).not_null())
+                    .col(ColumnDef::new(ReindexJobs::Chunking).string().not_null())
+                    .col(ColumnDef::new(ReindexJobs::EmbeddingModel).string().not_null())
+                    .col(ColumnDef::new(ReindexJobs::Force).boolean().not_null().default(false))
+                    .col(ColumnDef::new(ReindexJobs::TotalDocuments).integer().not_null().default(0))
+                    .col(ColumnDef::new(ReindexJobs::ProcessedDocuments).integer().not_null().default(0))
+                    .col(ColumnDef::new(ReindexJobs::FailedDocuments).integer().not_null().default(0))
+                    .col(ColumnDef::new(ReindexJobs::Cursor).uuid().null())
+                    .col(ColumnDef::new(ReindexJobs::Error).text().null())
+                    .col(ColumnDef::new(ReindexJobs::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ReindexJobs::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ReindexJobs::CompletedAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // rebuilt chunks wait here until the job swaps them in all at once
+        manager
+            .create_table(
+                Table::create()
+                    .table(StagedEmbeddings::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(StagedEmbeddings::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(StagedEmbeddings::JobId).uuid().not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::OwnerId).uuid().not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::FileHash).string().not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::Chunk).text().not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::Embedding).array(ColumnType::Float).not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::Chunking).string().not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::EmbeddingModel).string().not_null())
+                    .col(ColumnDef::new(StagedEmbeddings::Page).integer().null())
+                    .col(ColumnDef::new(StagedEmbeddings::Heading).text().null())
+                    .col(ColumnDef::new(StagedEmbeddings::StartOffset).big_integer().null())
+                    .col(ColumnDef::new(StagedEmbeddings::EndOffset).big_integer().null())
+                    .col(ColumnDef::new(StagedEmbeddings::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_staged_embeddings_job_id")
+                            .from(StagedEmbeddings::Table, StagedEmbeddings::JobId)
+                            .to(ReindexJobs::Table, ReindexJobs::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_staged_embeddings_job_document")
+                    .table(StagedEmbeddings::Table)
+                    .col(StagedEmbeddings::JobId)
+                    .col(StagedEmbeddings::OwnerId)
+                    .col(StagedEmbeddings::FileHash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StagedEmbeddings::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ReindexJobs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ReindexJobs {
+    Table,
+    Id,
+    Status,
+    Chunking,
+    EmbeddingModel,
+    Force,
+    TotalDocuments,
+    ProcessedDocuments,
+    FailedDocuments,
+    Cursor,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+    CompletedAt,
+}
+
+#[derive(Iden)]
+enum StagedEmbeddings {
+    Table,
+    Id,
+    JobId,
+    OwnerId,
+    FileHash,
+    Chunk,
+    Embedding,
+    Chunking,
+    EmbeddingModel,
+    Page,
+    Heading,
+    StartOffset,
+    EndOffset,
+    CreatedAt,
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(GenerationJobs::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(GenerationJobs::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(GenerationJobs::OwnerId).uuid().not_null())
+                    .col(ColumnDef::new(GenerationJobs::Kind).string_len(32).not_null())
+                    .col(ColumnDef::new(GenerationJobs::Status).string_len(20).not_null())
+                    .col(ColumnDef::new(GenerationJobs::Progress).integer().not_null().default(0))
+                    .col(ColumnDef::new(GenerationJobs::Request).json_binary().not_null())
+                    .col(ColumnDef::new(GenerationJobs::Result).json_binary().null())
+                    .col(ColumnDef::new(GenerationJobs::Error).text().null())
+                    .col(ColumnDef::new(GenerationJobs::Attempts).integer().not_null().default(0))
+                    .col(ColumnDef::new(GenerationJobs::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(GenerationJobs::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(GenerationJobs::StartedAt).timestamp_with_time_zone().null())
+                    .col(ColumnDef::new(GenerationJobs::CompletedAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // workers poll for the oldest queued job
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_generation_jobs_status_created_at")
+                    .table(GenerationJobs::Table)
+                    .col(GenerationJobs::Status)
+                    .col(GenerationJobs::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GenerationJobs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum GenerationJobs {
+    Table,
+    Id,
+    OwnerId,
+    Kind,
+    Status,
+    Progress,
+    Request,
+    Result,
+    Error,
+    Attempts,
+    CreatedAt,
+    UpdatedAt,
+    StartedAt,
+    CompletedAt,
+}
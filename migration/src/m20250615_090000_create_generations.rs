@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Generations::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Generations::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Generations::OwnerId).uuid().not_null())
+                    .col(ColumnDef::new(Generations::Kind).string_len(32).not_null())
+                    .col(ColumnDef::new(Generations::Model).string().not_null())
+                    .col(ColumnDef::new(Generations::Content).text().not_null())
+                    .col(ColumnDef::new(Generations::Citations).json_binary().not_null())
+                    .col(ColumnDef::new(Generations::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_generations_owner_id_created_at")
+                    .table(Generations::Table)
+                    .col(Generations::OwnerId)
+                    .col(Generations::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Generations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Generations {
+    Table,
+    Id,
+    OwnerId,
+    Kind,
+    Model,
+    Content,
+    Citations,
+    CreatedAt,
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LlmUsage::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(LlmUsage::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(LlmUsage::OwnerId).uuid().not_null())
+                    .col(ColumnDef::new(LlmUsage::Kind).string_len(32).not_null())
+                    .col(ColumnDef::new(LlmUsage::Model).string().not_null())
+                    .col(ColumnDef::new(LlmUsage::PromptTokens).integer().not_null())
+                    .col(ColumnDef::new(LlmUsage::CompletionTokens).integer().not_null())
+                    .col(ColumnDef::new(LlmUsage::EstimatedCost).double().null())
+                    .col(ColumnDef::new(LlmUsage::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_llm_usage_owner_id_created_at")
+                    .table(LlmUsage::Table)
+                    .col(LlmUsage::OwnerId)
+                    .col(LlmUsage::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_llm_usage_created_at")
+                    .table(LlmUsage::Table)
+                    .col(LlmUsage::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LlmUsage::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum LlmUsage {
+    Table,
+    Id,
+    OwnerId,
+    Kind,
+    Model,
+    PromptTokens,
+    CompletionTokens,
+    EstimatedCost,
+    CreatedAt,
+}
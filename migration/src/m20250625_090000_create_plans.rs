@@ -0,0 +1,132 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Plans::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Plans::Name).string_len(32).not_null().primary_key())
+                    .col(ColumnDef::new(Plans::GenerationsPerDay).integer().null())
+                    .col(ColumnDef::new(Plans::TokensPerMonth).big_integer().null())
+                    .col(ColumnDef::new(Plans::PresentationCreditsPerMonth).integer().null())
+                    .col(ColumnDef::new(Plans::MaxUploadBytes).big_integer().null())
+                    .col(ColumnDef::new(Plans::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Plans::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // every user starts on the free plan
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Plans::Table)
+                    .columns([
+                        Plans::Name,
+                        Plans::GenerationsPerDay,
+                        Plans::TokensPerMonth,
+                        Plans::PresentationCreditsPerMonth,
+                        Plans::MaxUploadBytes,
+                        Plans::CreatedAt,
+                        Plans::UpdatedAt,
+                    ])
+                    .values_panic([
+                        "free".into(),
+                        50.into(),
+                        1_000_000i64.into(),
+                        10.into(),
+                        (10i64 * 1024 * 1024).into(),
+                        Expr::current_timestamp().into(),
+                        Expr::current_timestamp().into(),
+                    ])
+                    .on_conflict(OnConflict::column(Plans::Name).do_nothing().to_owned())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::Plan).string_len(32).not_null().default("free"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(GenerationRequests::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(GenerationRequests::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(GenerationRequests::OwnerId).uuid().not_null())
+                    .col(ColumnDef::new(GenerationRequests::Kind).string_len(32).not_null())
+                    .col(ColumnDef::new(GenerationRequests::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_generation_requests_owner_id_created_at")
+                    .table(GenerationRequests::Table)
+                    .col(GenerationRequests::OwnerId)
+                    .col(GenerationRequests::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GenerationRequests::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Plan)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Plans::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Plans {
+    Table,
+    Name,
+    GenerationsPerDay,
+    TokensPerMonth,
+    PresentationCreditsPerMonth,
+    MaxUploadBytes,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Plan,
+}
+
+#[derive(Iden)]
+enum GenerationRequests {
+    Table,
+    Id,
+    OwnerId,
+    Kind,
+    CreatedAt,
+}
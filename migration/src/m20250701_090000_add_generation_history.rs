@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // generations stored before this migration have no request and cannot
+        // be regenerated
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Generations::Table)
+                    .add_column(ColumnDef::new(Generations::Request).json_binary().not_null().default(Expr::cust("'{}'::jsonb")))
+                    .add_column(ColumnDef::new(Generations::PromptVersion).string_len(64).not_null().default(""))
+                    .add_column(ColumnDef::new(Generations::UpdatedAt).timestamp_with_time_zone().not_null().default(Expr::current_timestamp()))
+                    .add_column(ColumnDef::new(Generations::DeletedAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_generations_owner_id_kind_created_at")
+                    .table(Generations::Table)
+                    .col(Generations::OwnerId)
+                    .col(Generations::Kind)
+                    .col(Generations::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_generations_owner_id_kind_created_at")
+                    .table(Generations::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Generations::Table)
+                    .drop_column(Generations::DeletedAt)
+                    .drop_column(Generations::UpdatedAt)
+                    .drop_column(Generations::PromptVersion)
+                    .drop_column(Generations::Request)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Generations {
+    Table,
+    OwnerId,
+    Kind,
+    Request,
+    PromptVersion,
+    UpdatedAt,
+    DeletedAt,
+    CreatedAt,
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // keep the newest of any jobs that raced each other into `running`
+        db.execute_unprepared(
+            "UPDATE reindex_jobs SET status = 'failed', error = 'Superseded by a concurrent reindex job', updated_at = now() \
+             WHERE status = 'running' AND id <> ( \
+                 SELECT id FROM reindex_jobs WHERE status = 'running' ORDER BY created_at DESC LIMIT 1 \
+             )",
+        )
+        .await?;
+
+        // at most one reindex job may run at a time, across all replicas
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_reindex_jobs_single_running \
+             ON reindex_jobs (status) WHERE status = 'running'",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_reindex_jobs_single_running")
+            .await?;
+
+        Ok(())
+    }
+}
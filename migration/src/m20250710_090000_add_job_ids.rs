@@ -0,0 +1,106 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // a retried generation job overwrites what its earlier attempts
+        // stored instead of adding to it; rows of synchronous requests have
+        // no job and never conflict
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Generations::Table)
+                    .add_column(ColumnDef::new(Generations::JobId).uuid().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_generations_job_id")
+                    .table(Generations::Table)
+                    .col(Generations::JobId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LlmUsage::Table)
+                    .add_column(ColumnDef::new(LlmUsage::JobId).uuid().null())
+                    .add_column(ColumnDef::new(LlmUsage::CallIndex).integer().not_null().default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_llm_usage_job_id_call_index")
+                    .table(LlmUsage::Table)
+                    .col(LlmUsage::JobId)
+                    .col(LlmUsage::CallIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_llm_usage_job_id_call_index")
+                    .table(LlmUsage::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LlmUsage::Table)
+                    .drop_column(LlmUsage::CallIndex)
+                    .drop_column(LlmUsage::JobId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_generations_job_id")
+                    .table(Generations::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Generations::Table)
+                    .drop_column(Generations::JobId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Generations {
+    Table,
+    JobId,
+}
+
+#[derive(Iden)]
+enum LlmUsage {
+    Table,
+    JobId,
+    CallIndex,
+}
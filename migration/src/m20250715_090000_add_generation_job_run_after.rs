@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // a job re-queued after a transient failure waits until then before
+        // a worker claims it again; new jobs run straight away
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(GenerationJobs::Table)
+                    .add_column(ColumnDef::new(GenerationJobs::RunAfter).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(GenerationJobs::Table)
+                    .drop_column(GenerationJobs::RunAfter)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum GenerationJobs {
+    Table,
+    RunAfter,
+}
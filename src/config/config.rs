@@ -10,6 +10,33 @@ pub struct Configuration {
     pub server_port: String,
     pub magic_slide_access_id: String,
     pub magic_slide_base_url: String,
+    pub magic_slide_email: Option<String>,
+    pub vector_store: String,
+    pub rag_index_path: String,
+    pub rag_snapshot_interval: u64,
+    pub embedding_model: String,
+    pub enable_reranker: bool,
+    pub chunking_strategy: String,
+    pub chunk_target_size: usize,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub chunk_min_size: usize,
+    pub generation_workers: usize,
+    pub llm_provider: String,
+    pub llm_base_url: Option<String>,
+    pub llm_api_key: Option<String>,
+    pub allow_mock_provider: bool,
+    pub content_model: String,
+    pub mcq_model: String,
+    pub allowed_models: Vec<String>,
+    pub fallback_models: Vec<String>,
+    pub llm_prices: String,
+    pub llm_timeout: u64,
+    pub magic_slides_timeout: u64,
+    pub outbound_max_attempts: u32,
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown: u64,
+    pub max_upload_bytes: usize,
 }
 
 impl Configuration {
@@ -30,6 +57,79 @@ impl Configuration {
             server_port: env::var("SERVER_PORT").unwrap_or_else(|_| "8080".to_string()),
             magic_slide_access_id: env::var("Magic_Slide_Access_ID").unwrap(),
             magic_slide_base_url: env::var("Magic_Slide_Base_URL").unwrap(),
+            magic_slide_email: env::var("Magic_Slide_Email").ok(),
+            vector_store: env::var("VECTOR_STORE").unwrap_or_else(|_| "usearch".to_string()),
+            rag_index_path: env::var("RAG_INDEX_PATH")
+                .unwrap_or_else(|_| "data/rag_index".to_string()),
+            rag_snapshot_interval: env::var("RAG_SNAPSHOT_INTERVAL")
+                .unwrap_or_else(|_| "300".to_string()) // 5 minutes
+                .parse()
+                .expect("RAG_SNAPSHOT_INTERVAL must be a number"),
+            embedding_model: env::var("EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "AllMiniLML12V2Q".to_string()),
+            enable_reranker: env::var("ENABLE_RERANKER")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("ENABLE_RERANKER must be true or false"),
+            chunking_strategy: env::var("CHUNKING_STRATEGY")
+                .unwrap_or_else(|_| "markdown".to_string()),
+            chunk_target_size: env::var("CHUNK_TARGET_SIZE")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .expect("CHUNK_TARGET_SIZE must be a number"),
+            chunk_size: env::var("CHUNK_SIZE")
+                .unwrap_or_else(|_| "2000".to_string())
+                .parse()
+                .expect("CHUNK_SIZE must be a number"),
+            chunk_overlap: env::var("CHUNK_OVERLAP")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .expect("CHUNK_OVERLAP must be a number"),
+            chunk_min_size: env::var("CHUNK_MIN_SIZE")
+                .unwrap_or_else(|_| "51".to_string())
+                .parse()
+                .expect("CHUNK_MIN_SIZE must be a number"),
+            generation_workers: env::var("GENERATION_WORKERS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .expect("GENERATION_WORKERS must be a number"),
+            llm_provider: env::var("LLM_PROVIDER").unwrap_or_else(|_| "openrouter".to_string()),
+            llm_base_url: env::var("LLM_BASE_URL").ok(),
+            llm_api_key: env::var("LLM_API_KEY").ok(),
+            allow_mock_provider: env::var("ALLOW_MOCK_PROVIDER")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .expect("ALLOW_MOCK_PROVIDER must be true or false"),
+            content_model: env::var("CONTENT_MODEL")
+                .unwrap_or_else(|_| "qwen/qwq-32b:free".to_string()),
+            mcq_model: env::var("MCQ_MODEL").unwrap_or_else(|_| "qwen/qwq-32b:free".to_string()),
+            allowed_models: list_var("ALLOWED_MODELS"),
+            fallback_models: list_var("FALLBACK_MODELS"),
+            llm_prices: env::var("LLM_PRICES").unwrap_or_default(),
+            llm_timeout: env::var("LLM_TIMEOUT")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .expect("LLM_TIMEOUT must be a number"),
+            magic_slides_timeout: env::var("MAGIC_SLIDES_TIMEOUT")
+                .unwrap_or_else(|_| "120".to_string())
+                .parse()
+                .expect("MAGIC_SLIDES_TIMEOUT must be a number"),
+            outbound_max_attempts: env::var("OUTBOUND_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .expect("OUTBOUND_MAX_ATTEMPTS must be a number"),
+            circuit_breaker_threshold: env::var("CIRCUIT_BREAKER_THRESHOLD")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("CIRCUIT_BREAKER_THRESHOLD must be a number"),
+            circuit_breaker_cooldown: env::var("CIRCUIT_BREAKER_COOLDOWN")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("CIRCUIT_BREAKER_COOLDOWN must be a number"),
+            max_upload_bytes: env::var("MAX_UPLOAD_BYTES")
+                .unwrap_or_else(|_| "52428800".to_string()) // 50 MiB
+                .parse()
+                .expect("MAX_UPLOAD_BYTES must be a number"),
         }
     }
 
@@ -47,3 +147,14 @@ impl Configuration {
         Ok(conn)
     }
 }
+
+/// Reads a comma-separated list, empty when unset.
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
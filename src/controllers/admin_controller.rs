@@ -0,0 +1,121 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        llm_usage::UsageQuery,
+        plans::{PlanLimits, SetUserPlanRequest},
+        reindex_jobs::StartReindexRequest,
+    },
+    router::AppState,
+    services::{
+        quota_service::{list_plans_service, set_user_plan_service, upsert_plan_service},
+        reindex_service::{
+            get_reindex_job_service, list_reindex_jobs_service, start_reindex_service,
+        },
+        usage_service::usage_report_service,
+    },
+    utils::{errors::AppError, response::success_response},
+};
+
+pub async fn start_reindex(
+    State(state): State<AppState>,
+    Json(request): Json<StartReindexRequest>,
+) -> Result<Json<Value>, AppError> {
+    let job = start_reindex_service(
+        &state.db,
+        request,
+        &state.chunking,
+        state.embedding_model,
+        state.rag_store,
+    )
+    .await?;
+
+    let rd = serde_json::to_value(job)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn list_reindex_jobs(State(state): State<AppState>) -> Result<Json<Value>, AppError> {
+    let jobs = list_reindex_jobs_service(&state.db).await?;
+
+    let rd = serde_json::to_value(jobs)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn get_reindex_job(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    let job = get_reindex_job_service(&state.db, id).await?;
+
+    let rd = serde_json::to_value(job)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+/// Usage of every user, or of `user_id` when given.
+pub async fn get_usage_report(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<Value>, AppError> {
+    let report = usage_report_service(
+        &state.db,
+        query.user_id,
+        query.group_by,
+        query.from,
+        query.to,
+    )
+    .await?;
+
+    let rd = serde_json::to_value(report)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn list_plans(State(state): State<AppState>) -> Result<Json<Value>, AppError> {
+    let plans = list_plans_service(&state.db).await?;
+
+    let rd = serde_json::to_value(plans)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+/// Creates a plan or replaces its limits. Users on the plan get the new
+/// limits on their next request.
+pub async fn put_plan(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<PlanLimits>,
+) -> Result<Json<Value>, AppError> {
+    let plan = upsert_plan_service(&state.db, name, payload).await?;
+
+    let rd = serde_json::to_value(plan)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn set_user_plan(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<SetUserPlanRequest>,
+) -> Result<Json<Value>, AppError> {
+    let plan = payload.plan.clone();
+    set_user_plan_service(&state.db, user_id, payload).await?;
+
+    Ok(Json(success_response(json!({
+        "user_id": user_id,
+        "plan": plan,
+    }))))
+}
@@ -1,28 +1,136 @@
 use axum::{
-    extract::{Multipart, State},
+    extract::{Extension, Multipart, State},
+    response::sse::{Event, KeepAlive, Sse},
     Json,
 };
 
-use serde_json::Value;
-
-use tempfile::NamedTempFile;
-use tokio::fs;
+use futures::Stream;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use tokio::sync::mpsc;
 
 use crate::{
-    models::features::AcademicContentRequest,
+    controllers::job_controller::queued_job,
+    middleware::auth::AuthenticatedUser,
+    models::{
+        documents::UploadedDocument,
+        features::{AcademicContentRequest, RetrievalStrategy},
+        generation_jobs::{GenerationKind, GenerationMode},
+    },
     router::AppState,
-    services::{content_service::content_service, extract::extract_from_file},
-    utils::errors::AppError,
+    services::{
+        content_service::{content_service, prepare_content_prompt, stream_content_service},
+        document_service::parse_document_ids,
+        extract::extract_from_upload,
+        generation_job_service::enqueue_generation_job,
+        retrieval::RetrievalOptions,
+    },
+    utils::{errors::AppError, response::success_response},
 };
 
-use std::path::Path;
-
 #[axum::debug_handler]
 pub async fn generate_academic_content(
     State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
     // Json(request): Json<AcademicContentRequest>,
     mut multipart: Multipart,
 ) -> Result<Json<Value>, AppError> {
+    let (request, mode) = read_academic_content_request(&mut multipart).await?;
+
+    if mode == GenerationMode::Job {
+        RetrievalOptions::new(request.retrieval_strategy, state.reranker.clone())?;
+        state
+            .models
+            .chain(&state.models.content_model, request.model.as_deref())?;
+
+        let job = enqueue_generation_job(
+            &state.db,
+            &state.job_notify,
+            user.user_id,
+            GenerationKind::AcademicContent,
+            &request,
+        )
+        .await?;
+
+        return Ok(Json(success_response(queued_job(job)?)));
+    }
+
+    let response =
+        content_service(&state.generation_context(), user.user_id, request, None).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "data": response
+    })))
+}
+
+/// Streams the generated content as server-sent events: `delta` events carry
+/// pieces of content as the LLM produces them, then a final `done` event
+/// carries the stored generation's id and citations, or `error` its failure.
+pub async fn stream_academic_content(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    mut multipart: Multipart,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let (request, _) = read_academic_content_request(&mut multipart).await?;
+
+    // retrieval errors are still returned as a normal error response
+    let ctx = state.generation_context();
+    let prepared = prepare_content_prompt(&ctx, user.user_id, request).await?;
+
+    let (events, receiver) = mpsc::channel::<Event>(32);
+
+    tokio::spawn(async move {
+        let (deltas, mut delta_receiver) = mpsc::channel::<String>(32);
+
+        let delta_events = events.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(delta) = delta_receiver.recv().await {
+                let _ = delta_events
+                    .send(sse_event("delta", json!({ "content": delta })))
+                    .await;
+            }
+        });
+
+        let result = stream_content_service(&ctx, user.user_id, prepared, deltas).await;
+        let _ = forward.await;
+
+        let event = match result {
+            Ok(generation) => sse_event(
+                "done",
+                json!({
+                    "generation_id": generation.id,
+                    "model": generation.model,
+                    "generated_at": generation.created_at,
+                    "content": generation.content,
+                    "citations": generation.citations,
+                }),
+            ),
+            Err(e) => {
+                eprintln!("Streaming generation failed: {:?}", e);
+                sse_event("error", json!({ "message": e.into_message() }))
+            }
+        };
+        let _ = events.send(event).await;
+    });
+
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (Ok(event), receiver))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn sse_event(name: &str, data: Value) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_default()
+}
+
+async fn read_academic_content_request(
+    multipart: &mut Multipart,
+) -> Result<(AcademicContentRequest, GenerationMode), AppError> {
     let mut request = AcademicContentRequest {
         grade_level: String::new(),
         content_type: String::new(),
@@ -30,9 +138,14 @@ pub async fn generate_academic_content(
         topic: String::new(),
         standard_objective: String::new(),
         additional_criteria: None,
-        uploaded_content: None,
+        uploaded_content: Vec::new(),
+        document_ids: Vec::new(),
+        retrieval_strategy: RetrievalStrategy::default(),
+        model: None,
     };
 
+    let mut mode = GenerationMode::default();
+
     while let Some(field) = multipart
         .next_field()
         .await
@@ -78,67 +191,55 @@ pub async fn generate_academic_content(
                         .map_err(|e| AppError::BadRequest(e.to_string()))?,
                 )
             }
+            "document_ids" => {
+                let ids = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+                request.document_ids.extend(parse_document_ids(&ids)?);
+            }
+            "retrieval_strategy" => {
+                request.retrieval_strategy = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?
+                    .parse()?
+            }
+            "model" => {
+                request.model = Some(
+                    field
+                        .text()
+                        .await
+                        .map_err(|e| AppError::BadRequest(e.to_string()))?,
+                )
+            }
+            "mode" => {
+                mode = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?
+                    .parse()?
+            }
             "uploaded_content" => {
                 let file_name = field.file_name().map(|name| name.to_string());
 
-                if file_name.clone().unwrap() == "" {
-                    AppError::InternalServerError("Empty file".to_string());
-                }
-
-                let data = &field
+                let data = field
                     .bytes()
                     .await
                     .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
-                let file_extension = file_name
-                    .as_deref()
-                    .and_then(|name| Path::new(name).extension())
-                    .and_then(|ext| ext.to_str())
-                    .unwrap_or("tmp");
-
-                let temp_file = NamedTempFile::new().map_err(|e| {
-                    AppError::InternalServerError(format!("Failed to create temp file: {}", e))
-                })?;
-
-                let temp_file_path = temp_file.path().with_extension(file_extension);
-
-                std::fs::rename(temp_file.path(), &temp_file_path).map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to rename temp file to include extension: {}",
-                        e
-                    ))
-                })?;
-
-                fs::write(&temp_file_path, &data).await.map_err(|e| {
-                    AppError::InternalServerError(format!("Failed to write temp file: {}", e))
-                })?;
-
-                let extracted_text =
-                    extract_from_file(temp_file_path.to_str().ok_or_else(|| {
-                        AppError::InternalServerError(
-                            "Failed to convert temp file path to string".to_string(),
-                        )
-                    })?);
-
-                request.uploaded_content = Some(extracted_text.unwrap());
+                let extracted_text = extract_from_upload(file_name.as_deref(), &data)
+                    .await
+                    .map_err(AppError::BadRequest)?;
+
+                request.uploaded_content.push(UploadedDocument {
+                    name: file_name.unwrap_or_else(|| "Uploaded file".to_string()),
+                    content: extracted_text,
+                });
             }
             _ => {}
         }
     }
 
-    
-
-    let response = content_service(
-        &state.db,
-        request,
-        state.embedding_model.as_ref(),
-        state.rag_store,
-        state.client,
-    )
-    .await?;
-
-    Ok(Json(serde_json::json!({
-        "status": "success",
-        "data": response
-    })))
+    Ok((request, mode))
 }
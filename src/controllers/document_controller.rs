@@ -0,0 +1,171 @@
+use axum::{
+    extract::{multipart::Field, Extension, Multipart, Path, State},
+    Json,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    models::documents::{DocumentSummary, RenameDocumentRequest, UploadedDocument},
+    router::AppState,
+    services::{
+        document_service::{
+            delete_document_service, get_document_service, list_documents_service,
+            rename_document_service, upload_document_service,
+        },
+        extract::extract_from_upload,
+        rag_generate::ChunkingOverrides,
+    },
+    utils::{errors::AppError, response::success_response},
+};
+
+pub async fn upload_document(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, AppError> {
+    let mut name = None;
+    let mut upload = None;
+    let mut overrides = ChunkingOverrides::default();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?
+    {
+        let field_name = field.name().unwrap_or("").to_string();
+        match field_name.as_str() {
+            "name" => {
+                name = Some(
+                    field
+                        .text()
+                        .await
+                        .map_err(|e| AppError::BadRequest(e.to_string()))?,
+                )
+            }
+            "chunking_strategy" => {
+                overrides.strategy = Some(
+                    field
+                        .text()
+                        .await
+                        .map_err(|e| AppError::BadRequest(e.to_string()))?
+                        .parse()
+                        .map_err(AppError::BadRequest)?,
+                )
+            }
+            "target_chunk_size" => {
+                overrides.target_chunk_size = Some(parse_usize_field(field).await?)
+            }
+            "chunk_size" => overrides.chunk_size = Some(parse_usize_field(field).await?),
+            "chunk_overlap" => overrides.chunk_overlap = Some(parse_usize_field(field).await?),
+            "min_chunk_size" => overrides.min_chunk_size = Some(parse_usize_field(field).await?),
+            "uploaded_content" => {
+                let file_name = field.file_name().map(|name| name.to_string());
+
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+                let extracted_text = extract_from_upload(file_name.as_deref(), &data)
+                    .await
+                    .map_err(AppError::BadRequest)?;
+
+                upload = Some((file_name, extracted_text));
+            }
+            _ => {}
+        }
+    }
+
+    let (file_name, extracted_text) =
+        upload.ok_or_else(|| AppError::BadRequest("uploaded_content is required".to_string()))?;
+
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .or(file_name)
+        .unwrap_or_else(|| "Untitled document".to_string());
+
+    let document = upload_document_service(
+        &state.db,
+        user.user_id,
+        UploadedDocument {
+            name,
+            content: extracted_text,
+        },
+        overrides,
+        &state.chunking,
+        state.embedding_model,
+        state.rag_store,
+    )
+    .await?;
+
+    let rd = serde_json::to_value(DocumentSummary::from(document))
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn list_documents(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<Value>, AppError> {
+    let documents = list_documents_service(&state.db, user.user_id).await?;
+
+    let rd = serde_json::to_value(documents)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn get_document(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    let document = get_document_service(&state.db, user.user_id, id).await?;
+
+    let rd = serde_json::to_value(document)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn rename_document(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<RenameDocumentRequest>,
+) -> Result<Json<Value>, AppError> {
+    let document = rename_document_service(&state.db, user.user_id, id, request).await?;
+
+    let rd = serde_json::to_value(document)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn delete_document(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    delete_document_service(&state.db, user.user_id, id, state.rag_store).await?;
+
+    let response = json!({
+        "message": "Document deleted"
+    });
+
+    Ok(Json(success_response(response)))
+}
+
+async fn parse_usize_field(field: Field<'_>) -> Result<usize, AppError> {
+    let name = field.name().unwrap_or("").to_string();
+    field
+        .text()
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?
+        .trim()
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("{} must be a positive number", name)))
+}
@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    controllers::job_controller::queued_job,
+    middleware::auth::AuthenticatedUser,
+    models::generations::{ExportQuery, GenerationQuery},
+    router::AppState,
+    services::{
+        generation_service::{
+            delete_generation_service, export_generation_service, get_generation_service,
+            list_generations_service, regenerate_generation_service,
+        },
+        quota_service::{load_user_plan, release_generation_quota, reserve_generation_quota},
+    },
+    utils::{errors::AppError, response::success_response},
+};
+
+pub async fn list_generations(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<GenerationQuery>,
+) -> Result<Json<Value>, AppError> {
+    let page = list_generations_service(&state.db, user.user_id, query).await?;
+
+    let rd = serde_json::to_value(page)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+pub async fn get_generation(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    let generation = get_generation_service(&state.db, user.user_id, id).await?;
+
+    let rd = serde_json::to_value(generation)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+/// Runs the generation again as a job. The optional JSON body replaces
+/// fields of the stored request, e.g. `{"grade_level": "Grade 5"}`. Counts
+/// against the plan like any other generation.
+pub async fn regenerate_generation(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    overrides: Option<Json<Value>>,
+) -> Result<Response, AppError> {
+    let generation = get_generation_service(&state.db, user.user_id, id).await?;
+
+    let plan = load_user_plan(&state.db, user.user_id).await?;
+    let reservation =
+        match reserve_generation_quota(&state.db, &plan, user.user_id, generation.kind).await? {
+            Ok(reservation) => reservation,
+            Err(exceeded) => return Ok(exceeded.into_response()),
+        };
+
+    let job = regenerate_generation_service(
+        &state.db,
+        &state.job_notify,
+        &state.models,
+        state.reranker.clone(),
+        user.user_id,
+        id,
+        overrides
+            .map(|Json(overrides)| overrides)
+            .unwrap_or_default(),
+    )
+    .await;
+    let job = match job.and_then(queued_job) {
+        Ok(job) => job,
+        Err(e) => {
+            release_generation_quota(&state.db, reservation).await;
+            return Err(e);
+        }
+    };
+
+    let mut response = Json(success_response(job)).into_response();
+    if let Some(usage) = &reservation.tightest {
+        usage.insert_headers(response.headers_mut());
+    }
+
+    Ok(response)
+}
+
+pub async fn delete_generation(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    delete_generation_service(&state.db, user.user_id, id).await?;
+
+    let response = json!({
+        "message": "Generation deleted"
+    });
+
+    Ok(Json(success_response(response)))
+}
+
+/// Downloads the generation rendered in `format`.
+pub async fn export_generation(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, AppError> {
+    let file = export_generation_service(&state.db, user.user_id, id, &query).await?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, file.content_type.to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file.file_name),
+            ),
+        ],
+        file.bytes,
+    )
+        .into_response())
+}
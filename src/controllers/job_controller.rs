@@ -0,0 +1,33 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    models::generation_jobs::{JobResponse, Model as GenerationJobModel},
+    router::AppState,
+    services::generation_job_service::get_generation_job_service,
+    utils::{errors::AppError, response::success_response},
+};
+
+pub async fn get_job(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Value>, AppError> {
+    let job = get_generation_job_service(&state.db, user.user_id, id).await?;
+
+    let rd = serde_json::to_value(JobResponse::from(job))
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
+
+/// Body returned by generation endpoints in job mode.
+pub fn queued_job(job: GenerationJobModel) -> Result<Value, AppError> {
+    serde_json::to_value(JobResponse::from(job))
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))
+}
@@ -1,24 +1,30 @@
 use axum::{
-    extract::{Multipart, State},
+    extract::{Extension, Multipart, State},
     Json,
 };
 
 use serde_json::Value;
 
-use tempfile::NamedTempFile;
-use tokio::fs;
-
 use crate::{
-    models::features::McqGeneratorRequest,
+    controllers::job_controller::queued_job,
+    middleware::auth::AuthenticatedUser,
+    models::{
+        documents::UploadedDocument,
+        features::{McqGeneratorRequest, RetrievalStrategy},
+        generation_jobs::{GenerationKind, GenerationMode},
+    },
     router::AppState,
-    services::{extract::extract_from_file, mcq_service::mcq_service},
-    utils::errors::AppError,
+    services::{
+        document_service::parse_document_ids, extract::extract_from_upload,
+        generation_job_service::enqueue_generation_job, mcq_service::mcq_service,
+        retrieval::RetrievalOptions,
+    },
+    utils::{errors::AppError, response::success_response},
 };
 
-use std::path::Path;
-
 pub async fn generate_mcq_content(
     State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
     mut multipart: Multipart,
 ) -> Result<Json<Value>, AppError> {
     let mut request = McqGeneratorRequest {
@@ -27,9 +33,14 @@ pub async fn generate_mcq_content(
         number_of_questions: 0,
         standard_objective: String::new(),
         additional_criteria: None,
-        uploaded_content: None,
+        uploaded_content: Vec::new(),
+        document_ids: Vec::new(),
+        retrieval_strategy: RetrievalStrategy::default(),
+        model: None,
     };
 
+    let mut mode = GenerationMode::default();
+
     while let Some(field) = multipart
         .next_field()
         .await
@@ -71,62 +82,75 @@ pub async fn generate_mcq_content(
                         .map_err(|e| AppError::BadRequest(e.to_string()))?,
                 )
             }
+            "document_ids" => {
+                let ids = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+                request.document_ids.extend(parse_document_ids(&ids)?);
+            }
+            "retrieval_strategy" => {
+                request.retrieval_strategy = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?
+                    .parse()?
+            }
+            "model" => {
+                request.model = Some(
+                    field
+                        .text()
+                        .await
+                        .map_err(|e| AppError::BadRequest(e.to_string()))?,
+                )
+            }
+            "mode" => {
+                mode = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(e.to_string()))?
+                    .parse()?
+            }
             "uploaded_content" => {
                 let file_name = field.file_name().map(|name| name.to_string());
 
-                if file_name.clone().unwrap() == "" {
-                    AppError::InternalServerError("Empty file".to_string());
-                }
-
-                let data = &field
+                let data = field
                     .bytes()
                     .await
                     .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
-                let file_extension = file_name
-                    .as_deref()
-                    .and_then(|name| Path::new(name).extension())
-                    .and_then(|ext| ext.to_str())
-                    .unwrap_or("tmp");
-
-                let temp_file = NamedTempFile::new().map_err(|e| {
-                    AppError::InternalServerError(format!("Failed to create temp file: {}", e))
-                })?;
-
-                let temp_file_path = temp_file.path().with_extension(file_extension);
-
-                std::fs::rename(temp_file.path(), &temp_file_path).map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to rename temp file to include extension: {}",
-                        e
-                    ))
-                })?;
-
-                fs::write(&temp_file_path, &data).await.map_err(|e| {
-                    AppError::InternalServerError(format!("Failed to write temp file: {}", e))
-                })?;
-
-                let extracted_text =
-                    extract_from_file(temp_file_path.to_str().ok_or_else(|| {
-                        AppError::InternalServerError(
-                            "Failed to convert temp file path to string".to_string(),
-                        )
-                    })?);
+                let extracted_text = extract_from_upload(file_name.as_deref(), &data)
+                    .await
+                    .map_err(AppError::BadRequest)?;
 
-                request.uploaded_content = Some(extracted_text.unwrap());
+                request.uploaded_content.push(UploadedDocument {
+                    name: file_name.unwrap_or_else(|| "Uploaded file".to_string()),
+                    content: extracted_text,
+                });
             }
             _ => {}
         }
     }
 
-    let response = mcq_service(
-        &state.db,
-        request,
-        state.embedding_model.as_ref(),
-        state.rag_store,
-        state.client,
-    )
-    .await?;
+    if mode == GenerationMode::Job {
+        RetrievalOptions::new(request.retrieval_strategy, state.reranker.clone())?;
+        state
+            .models
+            .chain(&state.models.mcq_model, request.model.as_deref())?;
+
+        let job = enqueue_generation_job(
+            &state.db,
+            &state.job_notify,
+            user.user_id,
+            GenerationKind::Mcq,
+            &request,
+        )
+        .await?;
+
+        return Ok(Json(success_response(queued_job(job)?)));
+    }
+
+    let response = mcq_service(&state.generation_context(), user.user_id, request, None).await?;
 
     Ok(Json(serde_json::json!({
         "status": "success",
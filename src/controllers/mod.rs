@@ -1,5 +1,10 @@
+pub mod admin_controller;
 pub mod auth_controller;
 pub mod content_controller;
+pub mod document_controller;
+pub mod generation_controller;
+pub mod job_controller;
 pub mod mcq_controller;
 pub mod presentation_controller;
 pub mod test_controller;
+pub mod usage_controller;
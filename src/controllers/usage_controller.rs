@@ -0,0 +1,33 @@
+use axum::{
+    extract::{Extension, Query, State},
+    Json,
+};
+use serde_json::Value;
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    models::llm_usage::UsageQuery,
+    router::AppState,
+    services::usage_service::usage_report_service,
+    utils::{errors::AppError, response::success_response},
+};
+
+pub async fn get_usage(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<Value>, AppError> {
+    let report = usage_report_service(
+        &state.db,
+        Some(user.user_id),
+        query.group_by,
+        query.from,
+        query.to,
+    )
+    .await?;
+
+    let rd = serde_json::to_value(report)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    Ok(Json(success_response(rd)))
+}
@@ -8,14 +8,25 @@ mod utils;
 
 use config::jwt::JwtConfig;
 use migration::{Migrator, MigratorTrait};
+use services::document_service::resume_document_ingestion;
+use services::generation_job_service::GenerationWorker;
+use services::generation_service::GenerationContext;
+use services::llm_provider::{LlmProvider, MockLlmProvider, OpenAiCompatibleProvider};
+use services::llm_service::{parse_prices, ModelConfig};
+use services::pgvector_store::PgVectorStore;
+use services::presentation_service::MagicSlidesClient;
+use services::rag_generate::{ChunkingConfig, EmbeddingModelConfig};
 use services::rag_store::RagStore;
-use utils::errors::AppError;
+use services::reindex_service::spawn_reindex_resumer;
+use services::resilience::{ResilientClient, RetryPolicy};
+use services::vector_store::VectorStore;
 
 use crate::config::config::Configuration;
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
-use openrouter_api::OpenRouterClient;
+use fastembed::{InitOptions, RerankInitOptions, RerankerModel, TextEmbedding, TextRerank};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Notify;
 
 #[tokio::main]
 async fn main() {
@@ -57,37 +68,204 @@ async fn main() {
         configuration.jwt_expiration,
     );
 
+    //chunking defaults, documents may override them individually
+    let chunking = ChunkingConfig {
+        strategy: match configuration.chunking_strategy.parse() {
+            Ok(strategy) => strategy,
+            Err(e) => {
+                eprintln!("Invalid CHUNKING_STRATEGY: {}", e);
+                return;
+            }
+        },
+        target_chunk_size: configuration.chunk_target_size,
+        chunk_size: configuration.chunk_size,
+        chunk_overlap: configuration.chunk_overlap,
+        min_chunk_size: configuration.chunk_min_size,
+    };
+
+    if let Err(e) = chunking.validate() {
+        eprintln!("Invalid chunking configuration: {}", e);
+        return;
+    }
+
+    //the index dimension follows the configured embedding model
+    let embedding: EmbeddingModelConfig = match configuration.embedding_model.parse() {
+        Ok(embedding) => embedding,
+        Err(e) => {
+            eprintln!("Invalid EMBEDDING_MODEL: {}", e);
+            return;
+        }
+    };
+
     //initialize the rag generator
-    let rag_store = RagStore::new(db.clone(), 384)
-        .await
-        .expect("Failed to create RagStore");
+    let rag_store: Arc<dyn VectorStore> = match configuration.vector_store.as_str() {
+        "pgvector" => Arc::new(
+            PgVectorStore::new(db.clone(), &embedding)
+                .await
+                .expect("Failed to create PgVectorStore"),
+        ),
+        "usearch" => Arc::new(
+            RagStore::new(
+                db.clone(),
+                &embedding,
+                PathBuf::from(&configuration.rag_index_path),
+            )
+            .await
+            .expect("Failed to create RagStore"),
+        ),
+        other => {
+            eprintln!(
+                "Unknown VECTOR_STORE '{}', expected 'usearch' or 'pgvector'",
+                other
+            );
+            return;
+        }
+    };
+
+    //periodically snapshot the index so restarts don't rebuild it from postgres
+    let snapshot_store = rag_store.clone();
+    let snapshot_interval = Duration::from_secs(configuration.rag_snapshot_interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(snapshot_interval);
+        ticker.tick().await; // the first tick completes immediately
+        loop {
+            ticker.tick().await;
+            if let Err(e) = snapshot_store.persist().await {
+                eprintln!("Failed to snapshot vector store: {:?}", e);
+            }
+        }
+    });
 
     // Initialize the embedding model
     let embedding_model = Arc::new(
         TextEmbedding::try_new(
-            InitOptions::new(EmbeddingModel::AllMiniLML12V2Q).with_show_download_progress(true),
+            InitOptions::new(embedding.model.clone()).with_show_download_progress(true),
         )
         .expect("Failed to initialize TextEmbedding"),
     );
 
-    let api_key = std::env::var("OPENROUTER_API_KEY").expect("OPENROUTER_API_KEY must be set");
-
-    let client = OpenRouterClient::new()
-        .with_base_url("https://openrouter.ai/api/v1/")
-        .map_err(|e| AppError::InternalServerError(e.to_string()))
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to create OpenRouterClient: {:?}", e);
-            std::process::exit(1);
-        })
-        .with_timeout(Duration::from_secs(500))
-        .with_api_key(api_key)
-        .map_err(|e| AppError::InternalServerError(e.to_string()))
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to configure OpenRouterClient: {:?}", e);
-            std::process::exit(1);
-        });
-
-    let app = router::create_router(db, jwt_config, embedding_model, rag_store, Arc::new(client)); // share db connection with all handlers
+    // the cross-encoder is only needed for the hybrid_rerank retrieval strategy
+    let reranker = if configuration.enable_reranker {
+        Some(Arc::new(
+            TextRerank::try_new(
+                RerankInitOptions::new(RerankerModel::BGERerankerBase)
+                    .with_show_download_progress(true),
+            )
+            .expect("Failed to initialize TextRerank"),
+        ))
+    } else {
+        None
+    };
+
+    //finish reindex jobs whose worker died, here or on another replica
+    spawn_reindex_resumer(
+        db.clone(),
+        chunking.clone(),
+        embedding_model.clone(),
+        rag_store.clone(),
+    );
+
+    //re-queue documents whose ingestion was cut short by the last shutdown
+    if let Err(e) = resume_document_ingestion(
+        db.clone(),
+        chunking.clone(),
+        embedding_model.clone(),
+        rag_store.clone(),
+    )
+    .await
+    {
+        eprintln!("Failed to resume document ingestion: {:?}", e);
+    }
+
+    //default models per feature, the models requests may pick and the fallbacks
+    let models = ModelConfig {
+        content_model: configuration.content_model.clone(),
+        mcq_model: configuration.mcq_model.clone(),
+        allowed_models: configuration.allowed_models.clone(),
+        fallback_models: configuration.fallback_models.clone(),
+        prices: match parse_prices(&configuration.llm_prices) {
+            Ok(prices) => prices,
+            Err(e) => {
+                eprintln!("Invalid LLM_PRICES: {}", e);
+                return;
+            }
+        },
+    };
+
+    //retries and circuit breaking for calls to the LLM and MagicSlides
+    let retry_policy = |timeout| RetryPolicy {
+        max_attempts: configuration.outbound_max_attempts,
+        deadline: Duration::from_secs(timeout),
+        breaker_threshold: configuration.circuit_breaker_threshold,
+        breaker_cooldown: Duration::from_secs(configuration.circuit_breaker_cooldown),
+    };
+
+    //the LLM backend every generation service goes through
+    let llm: Arc<dyn LlmProvider> = match configuration.llm_provider.as_str() {
+        "openrouter" => Arc::new(OpenAiCompatibleProvider::openrouter(
+            std::env::var("OPENROUTER_API_KEY").expect("OPENROUTER_API_KEY must be set"),
+            retry_policy(configuration.llm_timeout),
+        )),
+        "openai_compatible" => Arc::new(OpenAiCompatibleProvider::new(
+            configuration
+                .llm_base_url
+                .as_deref()
+                .expect("LLM_BASE_URL must be set for the openai_compatible provider"),
+            configuration.llm_api_key.clone(),
+            retry_policy(configuration.llm_timeout),
+        )),
+        //the mock answers every prompt with canned text, never serve it by accident
+        "mock" if cfg!(debug_assertions) || configuration.allow_mock_provider => {
+            Arc::new(MockLlmProvider::new())
+        }
+        "mock" => {
+            eprintln!("LLM_PROVIDER 'mock' needs a debug build or ALLOW_MOCK_PROVIDER=true");
+            return;
+        }
+        other => {
+            eprintln!(
+                "Unknown LLM_PROVIDER '{}', expected 'openrouter', 'openai_compatible' or 'mock'",
+                other
+            );
+            return;
+        }
+    };
+
+    let magic_slides = Arc::new(MagicSlidesClient {
+        client: ResilientClient::new(
+            "MagicSlides",
+            retry_policy(configuration.magic_slides_timeout),
+        ),
+        base_url: configuration.magic_slide_base_url.clone(),
+        access_id: configuration.magic_slide_access_id.clone(),
+        email: configuration.magic_slide_email.clone(),
+    });
+
+    let generation = GenerationContext {
+        db,
+        embedding_model,
+        rag_store: rag_store.clone(),
+        reranker,
+        chunking,
+        models,
+        llm,
+        magic_slides,
+    };
+
+    //background workers run generation requests submitted in job mode
+    let job_notify = Arc::new(Notify::new());
+    GenerationWorker {
+        ctx: generation.clone(),
+        notify: job_notify.clone(),
+    }
+    .spawn(configuration.generation_workers);
+
+    let app = router::create_router(
+        jwt_config,
+        generation,
+        job_notify,
+        configuration.max_upload_bytes,
+    ); // share db connection with all handlers
 
     let port = configuration.server_port;
     let addr = format!("0.0.0.0:{}", port);
@@ -96,6 +274,18 @@ async fn main() {
     println!("Server listening on http://{}", addr);
 
     axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
+
+    if let Err(e) = rag_store.persist().await {
+        eprintln!("Failed to snapshot vector store on shutdown: {:?}", e);
+    }
+}
+
+async fn shutdown_signal() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to install shutdown signal handler");
+    println!("Shutting down, saving vector store snapshot...");
 }
@@ -66,3 +66,20 @@ pub async fn auth_middleware(
     // Proceed to the next handler
     Ok(next.run(request).await)
 }
+
+/// Rejects users without the admin flag. Must run after `auth_middleware`.
+pub async fn admin_middleware(
+    request: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let is_admin = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .is_some_and(|user| user.is_admin);
+
+    if !is_admin {
+        return Err(AppError::Unauthorized("Admin access required".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,90 @@
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    models::{generation_jobs::GenerationKind, plans::Model as PlanModel},
+    router::AppState,
+    services::quota_service::{load_user_plan, release_generation_quota, reserve_generation_quota},
+    utils::errors::AppError,
+};
+use axum::{
+    body::Body,
+    extract::{Extension, State},
+    http::{header::CONTENT_LENGTH, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::Limited;
+
+/// Enforces the user's plan on a generation route. Requests over a limit are
+/// answered with 429 and never reach the handler. Other requests are counted
+/// against the plan before they run, and given back if they fail; successful
+/// responses carry the closest limit in `X-RateLimit-*` headers. Must run
+/// after `auth_middleware`.
+pub async fn quota_middleware(
+    State((state, kind)): State<(AppState, GenerationKind)>,
+    Extension(user): Extension<AuthenticatedUser>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let plan = load_user_plan(&state.db, user.user_id).await?;
+    let request = limit_upload(&plan, state.max_upload_bytes, request)?;
+
+    let reservation = match reserve_generation_quota(&state.db, &plan, user.user_id, kind).await? {
+        Ok(reservation) => reservation,
+        Err(exceeded) => return Ok(exceeded.into_response()),
+    };
+
+    let mut response = next.run(request).await;
+    if response.status().is_success() {
+        if let Some(usage) = &reservation.tightest {
+            usage.insert_headers(response.headers_mut());
+        }
+    } else {
+        release_generation_quota(&state.db, reservation).await;
+    }
+
+    Ok(response)
+}
+
+/// Applies the plan's upload size limit to a route that stores documents.
+/// Must run after `auth_middleware`.
+pub async fn upload_limit_middleware(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let plan = load_user_plan(&state.db, user.user_id).await?;
+    let request = limit_upload(&plan, state.max_upload_bytes, request)?;
+
+    Ok(next.run(request).await)
+}
+
+/// Rejects bodies declared larger than the plan allows up front. Chunked
+/// bodies carry no length, so the body is also capped while it is read. A
+/// plan can only lower the server's `hard_limit`, which `DefaultBodyLimit`
+/// already enforces on its own.
+fn limit_upload(
+    plan: &PlanModel,
+    hard_limit: usize,
+    request: Request<Body>,
+) -> Result<Request<Body>, AppError> {
+    let Some(max_bytes) = plan.max_upload_bytes else {
+        return Ok(request);
+    };
+    let max_bytes = (max_bytes.max(0) as usize).min(hard_limit);
+
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    if declared.is_some_and(|length| length > max_bytes as u64) {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Uploads on the {} plan are limited to {} bytes",
+            plan.name, max_bytes
+        )));
+    }
+
+    Ok(request.map(|body| Body::new(Limited::new(body, max_bytes))))
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStatus {
+    #[sea_orm(string_value = "processing")]
+    Processing,
+    #[sea_orm(string_value = "ready")]
+    Ready,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "documents")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub file_hash: String,
+    #[sea_orm(column_type = "Text")]
+    pub extracted_text: String,
+    pub chunk_count: i32,
+    pub status: DocumentStatus,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub chunking_overrides: Option<Json>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct RenameDocumentRequest {
+    #[validate(length(min = 1, max = 255, message = "Name must be 1 to 255 characters"))]
+    pub name: String,
+}
+
+/// A file uploaded with a generation request instead of being stored in the
+/// library first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadedDocument {
+    pub name: String,
+    pub content: String,
+}
+
+/// A document without its extracted text, as returned by list endpoints.
+#[derive(Debug, Serialize)]
+pub struct DocumentSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub file_hash: String,
+    pub chunk_count: i32,
+    pub status: DocumentStatus,
+    pub error: Option<String>,
+    pub chunking_overrides: Option<Json>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Model> for DocumentSummary {
+    fn from(document: Model) -> Self {
+        Self {
+            id: document.id,
+            name: document.name,
+            file_hash: document.file_hash,
+            chunk_count: document.chunk_count,
+            status: document.status,
+            error: document.error,
+            chunking_overrides: document.chunking_overrides,
+            created_at: document.created_at,
+            updated_at: document.updated_at,
+        }
+    }
+}
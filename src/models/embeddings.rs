@@ -6,9 +6,17 @@ use serde::{Deserialize, Serialize};
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
     pub id: Uuid,
+    pub owner_id: Option<Uuid>,
     pub file_hash: String,
     pub chunk: String,
     pub embedding: Vec<f32>,
+    pub chunking: Option<String>,
+    pub embedding_model: Option<String>,
+    pub page: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub heading: Option<String>,
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
     pub created_at: DateTimeUtc,
 }
 
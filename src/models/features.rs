@@ -1,7 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
 use validator::Validate;
 
+use super::{documents::UploadedDocument, mcq::McqQuestion};
+use crate::utils::errors::AppError;
+
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct AcademicContentRequest {
     pub grade_level: String,
@@ -10,20 +15,96 @@ pub struct AcademicContentRequest {
     pub topic: String,
     pub standard_objective: String,
     pub additional_criteria: Option<String>,
-    pub uploaded_content: Option<String>,
+    pub uploaded_content: Vec<UploadedDocument>,
+    pub document_ids: Vec<Uuid>,
+    pub retrieval_strategy: RetrievalStrategy,
+    /// Overrides the default model; must be on the allowlist.
+    pub model: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McqGeneratorRequest {
     pub grade_level: String,
     pub number_of_questions: i32,
     pub topic: String,
     pub standard_objective: String,
     pub additional_criteria: Option<String>,
-    pub uploaded_content: Option<String>,
+    pub uploaded_content: Vec<UploadedDocument>,
+    pub document_ids: Vec<Uuid>,
+    pub retrieval_strategy: RetrievalStrategy,
+    /// Overrides the default model; must be on the allowlist.
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct GeneratedResponse {
     pub content: String,
+    /// The model that produced the content, after any fallbacks.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Tokens used by the LLM call, when the provider reports them.
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
     pub generated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub citations: Vec<Citation>,
+    /// Id of the stored generation, for the history endpoints.
+    #[serde(default)]
+    pub generation_id: Option<Uuid>,
+    /// The parsed questions of an MCQ generation, whose `content` holds the
+    /// same set as JSON.
+    #[serde(default)]
+    pub questions: Option<Vec<McqQuestion>>,
+}
+
+/// Token counts as reported in the `usage` object of an OpenAI-style
+/// chat completion.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+}
+
+/// Where a piece of context given to the model came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    /// Label the chunk had in the prompt, e.g. `S3`.
+    pub id: String,
+    pub source: String,
+    pub page: Option<i32>,
+    pub heading: Option<String>,
+    /// Byte range within the document's extracted text.
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+    /// Cosine similarity to the query; absent for full-text only matches.
+    pub similarity: Option<f32>,
+}
+
+/// How context chunks are selected for a generation request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetrievalStrategy {
+    /// Cosine similarity over chunk embeddings only.
+    Vector,
+    /// Vector and full-text results merged by reciprocal rank fusion.
+    #[default]
+    Hybrid,
+    /// Hybrid candidates reordered by a cross-encoder.
+    HybridRerank,
+}
+
+impl FromStr for RetrievalStrategy {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "vector" => Ok(Self::Vector),
+            "hybrid" => Ok(Self::Hybrid),
+            "hybrid_rerank" => Ok(Self::HybridRerank),
+            other => Err(AppError::BadRequest(format!(
+                "Unknown retrieval_strategy '{}', expected 'vector', 'hybrid' or 'hybrid_rerank'",
+                other
+            ))),
+        }
+    }
 }
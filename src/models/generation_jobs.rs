@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::utils::errors::AppError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationKind {
+    #[sea_orm(string_value = "academic_content")]
+    AcademicContent,
+    #[sea_orm(string_value = "mcq")]
+    Mcq,
+    #[sea_orm(string_value = "presentation")]
+    Presentation,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    #[sea_orm(string_value = "queued")]
+    Queued,
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "generation_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub kind: GenerationKind,
+    pub status: JobStatus,
+    /// Percentage, 0 to 100.
+    pub progress: i32,
+    /// The generation request, replayed by whichever worker claims the job.
+    #[sea_orm(column_type = "JsonBinary")]
+    pub request: Json,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub result: Option<Json>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
+    pub attempts: i32,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub started_at: Option<DateTime<Utc>>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub completed_at: Option<DateTime<Utc>>,
+
+    /// A retried job is not claimed again before this time.
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub run_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Whether a generation endpoint answers with the result or with a job id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GenerationMode {
+    #[default]
+    Sync,
+    Job,
+}
+
+impl FromStr for GenerationMode {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "" | "sync" => Ok(Self::Sync),
+            "job" => Ok(Self::Job),
+            other => Err(AppError::BadRequest(format!(
+                "Unknown mode '{}', expected 'sync' or 'job'",
+                other
+            ))),
+        }
+    }
+}
+
+/// A job as returned to its owner, without the stored request.
+#[derive(Debug, Serialize)]
+pub struct JobResponse {
+    pub id: Uuid,
+    pub kind: GenerationKind,
+    pub status: JobStatus,
+    pub progress: i32,
+    pub result: Option<Json>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<Model> for JobResponse {
+    fn from(job: Model) -> Self {
+        Self {
+            id: job.id,
+            kind: job.kind,
+            status: job.status,
+            progress: job.progress,
+            result: job.result,
+            error: job.error,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+            completed_at: job.completed_at,
+        }
+    }
+}
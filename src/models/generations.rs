@@ -0,0 +1,184 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::generation_jobs::GenerationKind;
+
+/// A finished generation, kept so clients can refer back to it.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "generations")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub kind: GenerationKind,
+    /// The request the generation was made with, as the feature's request
+    /// type serializes it.
+    #[sea_orm(column_type = "JsonBinary")]
+    pub request: Json,
+    /// The system prompt used, see `prompt_version`.
+    pub prompt_version: String,
+    /// The LLM that produced the content.
+    pub model: String,
+    #[sea_orm(column_type = "Text")]
+    pub content: String,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub citations: Json,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+    /// Set when the owner deletes the generation; it is then hidden.
+    #[serde(skip_serializing)]
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// The generation job that produced it, unique so a retried job
+    /// replaces its earlier result.
+    #[serde(skip_serializing)]
+    pub job_id: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Query string of `GET /generations`. `from` and `to` are inclusive UTC
+/// dates; pages start at 1.
+#[derive(Debug, Deserialize)]
+pub struct GenerationQuery {
+    pub kind: Option<GenerationKind>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+/// A generation in the history list, without its request and content.
+#[derive(Debug, Serialize)]
+pub struct GenerationSummary {
+    pub id: Uuid,
+    pub kind: GenerationKind,
+    pub topic: Option<String>,
+    pub model: String,
+    pub prompt_version: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Model> for GenerationSummary {
+    fn from(generation: Model) -> Self {
+        Self {
+            id: generation.id,
+            kind: generation.kind,
+            topic: generation
+                .request
+                .get("topic")
+                .and_then(Json::as_str)
+                .map(str::to_string),
+            model: generation.model,
+            prompt_version: generation.prompt_version,
+            created_at: generation.created_at,
+            updated_at: generation.updated_at,
+        }
+    }
+}
+
+/// Query string of `GET /generations/{id}/export`. The other fields fill
+/// the header of exam papers and are ignored by the other formats; the title
+/// defaults to the topic.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+    /// The exam paper to print, `a` by default.
+    pub variant: Option<ExamVariant>,
+    pub school: Option<String>,
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    #[serde(rename = "class")]
+    pub class_name: Option<String>,
+    pub duration: Option<String>,
+    pub date: Option<String>,
+    pub instructions: Option<String>,
+    /// Marks per correct answer in the marking scheme, 1 by default.
+    pub marks_per_question: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Moodle XML quiz import.
+    MoodleXml,
+    /// Moodle's GIFT text format.
+    Gift,
+    /// IMS QTI 2.1 content package, a zip of one item per question.
+    Qti,
+    /// One row per question with its options and answer.
+    Csv,
+    /// Printable exam paper, see `ExamVariant`.
+    ExamDocx,
+    ExamPdf,
+    /// Answers of every variant and the marking scheme, to keep apart from
+    /// the papers.
+    AnswerKeyDocx,
+    AnswerKeyPdf,
+    /// Academic content as an editable Word document.
+    Docx,
+    /// Academic content as a standalone web page.
+    Html,
+    /// Academic content as normalised markdown.
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MoodleXml => "moodle_xml",
+            Self::Gift => "gift",
+            Self::Qti => "qti",
+            Self::Csv => "csv",
+            Self::ExamDocx => "exam_docx",
+            Self::ExamPdf => "exam_pdf",
+            Self::AnswerKeyDocx => "answer_key_docx",
+            Self::AnswerKeyPdf => "answer_key_pdf",
+            Self::Docx => "docx",
+            Self::Html => "html",
+            Self::Markdown => "markdown",
+        }
+    }
+}
+
+/// Versions of an exam paper with the same questions in the same order,
+/// each with the options of every question shuffled differently, so
+/// neighbours cannot copy answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExamVariant {
+    #[serde(alias = "A")]
+    A,
+    #[serde(alias = "B")]
+    B,
+    #[serde(alias = "C")]
+    C,
+}
+
+impl ExamVariant {
+    pub const ALL: [Self; 3] = [Self::A, Self::B, Self::C];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GenerationPage {
+    pub items: Vec<GenerationSummary>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::{entity::prelude::*, FromQueryResult};
+use serde::{Deserialize, Serialize};
+
+use super::generation_jobs::GenerationKind;
+
+/// Tokens used by one LLM call, charged to the user who made the request.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "llm_usage")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub kind: GenerationKind,
+    pub model: String,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    /// In US dollars, from the configured price of the model; null when the
+    /// model has no configured price.
+    #[sea_orm(column_type = "Double", nullable)]
+    pub estimated_cost: Option<f64>,
+    /// The generation job the call was made for. With `call_index`, the
+    /// call's position within the job, it is unique, so a retried job is
+    /// charged once.
+    pub job_id: Option<Uuid>,
+    pub call_index: i32,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGrouping {
+    #[default]
+    Day,
+    Feature,
+    Model,
+}
+
+/// Query string of the usage endpoints. `from` and `to` are inclusive UTC
+/// dates; `user_id` is only honoured for admins.
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    #[serde(default)]
+    pub group_by: UsageGrouping,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub user_id: Option<Uuid>,
+}
+
+/// Usage summed over one day, feature or model.
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct UsageRow {
+    pub key: String,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub estimated_cost: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub group_by: UsageGrouping,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub rows: Vec<UsageRow>,
+}
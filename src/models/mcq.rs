@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, str::FromStr};
+
+use crate::services::citations::strip_citations;
+
+/// Fewest and most answer options a question may have.
+pub const MIN_OPTIONS: usize = 2;
+pub const MAX_OPTIONS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Medium => "medium",
+            Self::Hard => "hard",
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "easy" => Ok(Self::Easy),
+            "medium" => Ok(Self::Medium),
+            "hard" => Ok(Self::Hard),
+            other => Err(format!(
+                "Unknown difficulty '{}', expected 'easy', 'medium' or 'hard'",
+                other
+            )),
+        }
+    }
+}
+
+/// A multiple-choice question with exactly one correct option.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McqQuestion {
+    pub stem: String,
+    pub options: Vec<String>,
+    /// Index of the correct option in `options`, starting at 0.
+    pub correct_index: usize,
+    #[serde(default)]
+    pub explanation: String,
+    pub difficulty: Difficulty,
+    pub topic: String,
+    /// Ids of the context chunks the stem and explanation cite, e.g. `S1`,
+    /// taken out of the text so exports show clean questions.
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+/// The questions of an MCQ generation, as the LLM is asked to return them
+/// and as they are stored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McqSet {
+    pub questions: Vec<McqQuestion>,
+}
+
+impl McqQuestion {
+    /// Moves the `[S#]` tags of the stem and explanation into `sources`.
+    fn take_sources(&mut self) {
+        for text in [&mut self.stem, &mut self.explanation] {
+            let (stripped, cited) = strip_citations(text);
+            *text = stripped;
+            for id in cited {
+                if !self.sources.contains(&id) {
+                    self.sources.push(id);
+                }
+            }
+        }
+    }
+
+    /// Checks what the type cannot express: a stem, a sensible number of
+    /// distinct options and a correct index pointing at one of them.
+    pub fn check(&self) -> Result<(), String> {
+        if self.stem.trim().is_empty() {
+            return Err("the stem is empty".to_string());
+        }
+
+        if !(MIN_OPTIONS..=MAX_OPTIONS).contains(&self.options.len()) {
+            return Err(format!(
+                "it has {} options, expected {} to {}",
+                self.options.len(),
+                MIN_OPTIONS,
+                MAX_OPTIONS
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for option in &self.options {
+            let normalized = option.trim().to_lowercase();
+            if normalized.is_empty() {
+                return Err("an option is empty".to_string());
+            }
+            if !seen.insert(normalized) {
+                return Err(format!("the option '{}' appears twice", option.trim()));
+            }
+        }
+
+        if self.correct_index >= self.options.len() {
+            return Err(format!(
+                "correct_index {} does not name one of its {} options",
+                self.correct_index,
+                self.options.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl McqSet {
+    /// Parses the LLM's answer, tolerating a markdown code fence around the
+    /// JSON, moves citation tags into each question's `sources` and checks
+    /// every question. `expected` is the number of questions asked for, if
+    /// any.
+    pub fn parse(output: &str, expected: Option<usize>) -> Result<Self, String> {
+        let json = strip_code_fence(output);
+        let mut set: McqSet = serde_json::from_str(json)
+            .map_err(|e| format!("the output is not valid JSON: {}", e))?;
+
+        for question in &mut set.questions {
+            question.take_sources();
+        }
+
+        if set.questions.is_empty() {
+            return Err("it contains no questions".to_string());
+        }
+        if let Some(expected) = expected.filter(|&expected| expected != set.questions.len()) {
+            return Err(format!(
+                "it contains {} questions, {} were asked for",
+                set.questions.len(),
+                expected
+            ));
+        }
+
+        for (index, question) in set.questions.iter().enumerate() {
+            question
+                .check()
+                .map_err(|e| format!("question {} is invalid: {}", index + 1, e))?;
+        }
+
+        Ok(set)
+    }
+}
+
+fn strip_code_fence(output: &str) -> &str {
+    let trimmed = output.trim();
+    let Some(body) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+
+    // drop the info string, e.g. ```json
+    let body = body.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_moves_citation_tags_into_sources() {
+        let output = r#"{"questions": [{
+            "stem": "What do the kidneys remove [S2]?",
+            "options": ["Waste", "Oxygen"],
+            "correct_index": 0,
+            "explanation": "They filter waste from the blood [S1, S2]. See [note].",
+            "difficulty": "easy",
+            "topic": "Organs"
+        }]}"#;
+
+        let set = McqSet::parse(output, Some(1)).unwrap();
+        let question = &set.questions[0];
+
+        assert_eq!(question.stem, "What do the kidneys remove?");
+        assert_eq!(
+            question.explanation,
+            "They filter waste from the blood. See [note]."
+        );
+        assert_eq!(question.sources, vec!["S2", "S1"]);
+
+        // stored sets keep their sources when parsed again for export
+        let stored = serde_json::to_string(&set).unwrap();
+        assert_eq!(McqSet::parse(&stored, None).unwrap(), set);
+    }
+}
@@ -3,7 +3,16 @@
 pub mod prelude;
 
 pub mod blacklist;
+pub mod documents;
 pub mod embeddings;
 pub mod features;
+pub mod generation_jobs;
+pub mod generation_requests;
+pub mod generations;
+pub mod llm_usage;
+pub mod mcq;
+pub mod plans;
 pub mod presentation;
+pub mod reindex_jobs;
+pub mod staged_embeddings;
 pub mod user;
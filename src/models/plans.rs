@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Plan every new user is put on.
+pub const DEFAULT_PLAN: &str = "free";
+
+/// Limits shared by the users on a plan. A null limit is unlimited; a user
+/// needing their own limits gets a plan of their own.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "plans")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    /// Generation requests per UTC day, over all features.
+    pub generations_per_day: Option<i32>,
+    /// LLM prompt and completion tokens per calendar month.
+    pub tokens_per_month: Option<i64>,
+    /// Presentations per calendar month.
+    pub presentation_credits_per_month: Option<i32>,
+    /// Largest request body accepted with uploads, in bytes.
+    pub max_upload_bytes: Option<i64>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Body of `PUT /admin/plans/{name}`; omitted limits are unlimited.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PlanLimits {
+    #[validate(range(min = 0))]
+    pub generations_per_day: Option<i32>,
+    #[validate(range(min = 0))]
+    pub tokens_per_month: Option<i64>,
+    #[validate(range(min = 0))]
+    pub presentation_credits_per_month: Option<i32>,
+    #[validate(range(min = 0))]
+    pub max_upload_bytes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetUserPlanRequest {
+    pub plan: String,
+}
@@ -1,15 +1,20 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Debug, Serialize, Clone)]
+use super::{documents::UploadedDocument, features::RetrievalStrategy};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PresentationGeneratorRequest {
     pub grade_level: String,
     pub number_of_slides: i32,
     pub topic: String,
     pub standard_objective: String,
     pub additional_criteria: Option<String>,
-    pub uploaded_content: Option<String>,
+    pub uploaded_content: Vec<UploadedDocument>,
+    pub document_ids: Vec<Uuid>,
+    pub retrieval_strategy: RetrievalStrategy,
 }
 #[derive(Debug, Serialize)]
 pub struct PresentationGeneratorResponse {
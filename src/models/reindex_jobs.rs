@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(20))")]
+#[serde(rename_all = "snake_case")]
+pub enum ReindexStatus {
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "reindex_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
+    pub id: Uuid,
+    pub status: ReindexStatus,
+    /// Chunking signature and embedding model the job rebuilds with.
+    pub chunking: String,
+    pub embedding_model: String,
+    /// Rebuild documents whose embeddings are already current as well.
+    pub force: bool,
+    pub total_documents: i32,
+    pub processed_documents: i32,
+    pub failed_documents: i32,
+    /// Id of the last document processed; documents are walked in id order.
+    pub cursor: Option<Uuid>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StartReindexRequest {
+    #[serde(default)]
+    pub force: bool,
+}
@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Chunks rebuilt by a reindex job, invisible to retrieval until the job
+/// swaps them into `cached_embeddings`.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "staged_embeddings")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Uuid")]
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub owner_id: Uuid,
+    pub file_hash: String,
+    #[sea_orm(column_type = "Text")]
+    pub chunk: String,
+    pub embedding: Vec<f32>,
+    pub chunking: String,
+    pub embedding_model: String,
+    pub page: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub heading: Option<String>,
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
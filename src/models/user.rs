@@ -26,6 +26,9 @@ pub struct Model {
 
     #[sea_orm(default_value = "false")]
     pub is_admin: bool,
+
+    /// Name of the plan whose limits apply to this user.
+    pub plan: String,
 }
 
 #[derive(Clone, Debug, EnumIter, DeriveRelation)]
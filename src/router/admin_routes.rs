@@ -0,0 +1,34 @@
+use crate::{
+    controllers::admin_controller::{
+        get_reindex_job, get_usage_report, list_plans, list_reindex_jobs, put_plan, set_user_plan,
+        start_reindex,
+    },
+    middleware::auth::{admin_middleware, auth_middleware},
+};
+
+use super::AppState;
+use axum::{
+    middleware,
+    routing::{get, post, put},
+    Router,
+};
+
+pub fn routes(state: AppState) -> Router<AppState> {
+    let admin_routes = Router::new()
+        .route("/admin/reindex", post(start_reindex).get(list_reindex_jobs))
+        .route("/admin/reindex/{id}", get(get_reindex_job))
+        .route("/admin/usage", get(get_usage_report))
+        .route("/admin/plans", get(list_plans))
+        .route("/admin/plans/{name}", put(put_plan))
+        .route("/admin/users/{id}/plan", put(set_user_plan))
+        //middleware, the last layer added runs first
+        .route_layer(middleware::from_fn(admin_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest(state.version.as_str(), admin_routes)
+        .with_state(state)
+}
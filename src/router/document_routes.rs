@@ -0,0 +1,44 @@
+use crate::{
+    controllers::document_controller::{
+        delete_document, get_document, list_documents, rename_document, upload_document,
+    },
+    middleware::{auth::auth_middleware, quota::upload_limit_middleware},
+};
+
+use super::AppState;
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+pub fn routes(state: AppState) -> Router<AppState> {
+    let protected_routes = Router::new()
+        .route("/documents", get(list_documents))
+        .route(
+            "/documents",
+            post(upload_document)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    upload_limit_middleware,
+                ))
+                // plans may lower this limit, see upload_limit_middleware
+                .layer(DefaultBodyLimit::max(state.max_upload_bytes)),
+        )
+        .route(
+            "/documents/{id}",
+            get(get_document)
+                .patch(rename_document)
+                .delete(delete_document),
+        )
+        //middleware
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest(state.version.as_str(), protected_routes)
+        .with_state(state)
+}
@@ -1,20 +1,48 @@
 use crate::{
     controllers::{
-        content_controller::generate_academic_content, mcq_controller::generate_mcq_content,
+        content_controller::{generate_academic_content, stream_academic_content},
+        mcq_controller::generate_mcq_content,
         presentation_controller::generate_presentation_content,
     },
-    middleware::auth::auth_middleware,
+    middleware::{auth::auth_middleware, quota::quota_middleware},
+    models::generation_jobs::GenerationKind,
 };
 
 use super::AppState;
-use axum::{middleware, routing::post, Router};
+use axum::{extract::DefaultBodyLimit, middleware, routing::post, Router};
 
 pub fn routes(state: AppState) -> Router<AppState> {
     let protected_routes = Router::new()
-        .route("/academic-content-gen", post(generate_academic_content))
-        .route("/mcq-gen", post(generate_mcq_content))
-        .route("/presentation-gen", post(generate_presentation_content))
-        //middleware
+        .route(
+            "/academic-content-gen",
+            post(generate_academic_content).route_layer(middleware::from_fn_with_state(
+                (state.clone(), GenerationKind::AcademicContent),
+                quota_middleware,
+            )),
+        )
+        .route(
+            "/academic-content-gen/stream",
+            post(stream_academic_content).route_layer(middleware::from_fn_with_state(
+                (state.clone(), GenerationKind::AcademicContent),
+                quota_middleware,
+            )),
+        )
+        .route(
+            "/mcq-gen",
+            post(generate_mcq_content).route_layer(middleware::from_fn_with_state(
+                (state.clone(), GenerationKind::Mcq),
+                quota_middleware,
+            )),
+        )
+        .route(
+            "/presentation-gen",
+            post(generate_presentation_content).route_layer(middleware::from_fn_with_state(
+                (state.clone(), GenerationKind::Presentation),
+                quota_middleware,
+            )),
+        )
+        //middleware, plans may lower the upload limit in quota_middleware
+        .layer(DefaultBodyLimit::max(state.max_upload_bytes))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
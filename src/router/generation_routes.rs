@@ -0,0 +1,34 @@
+use crate::{
+    controllers::generation_controller::{
+        delete_generation, export_generation, get_generation, list_generations,
+        regenerate_generation,
+    },
+    middleware::auth::auth_middleware,
+};
+
+use super::AppState;
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+pub fn routes(state: AppState) -> Router<AppState> {
+    let protected_routes = Router::new()
+        .route("/generations", get(list_generations))
+        .route(
+            "/generations/{id}",
+            get(get_generation).delete(delete_generation),
+        )
+        .route("/generations/{id}/regenerate", post(regenerate_generation))
+        .route("/generations/{id}/export", get(export_generation))
+        //middleware
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest(state.version.as_str(), protected_routes)
+        .with_state(state)
+}
@@ -0,0 +1,18 @@
+use crate::{controllers::job_controller::get_job, middleware::auth::auth_middleware};
+
+use super::AppState;
+use axum::{middleware, routing::get, Router};
+
+pub fn routes(state: AppState) -> Router<AppState> {
+    let protected_routes = Router::new()
+        .route("/jobs/{id}", get(get_job))
+        //middleware
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest(state.version.as_str(), protected_routes)
+        .with_state(state)
+}
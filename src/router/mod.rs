@@ -1,17 +1,27 @@
+pub mod admin_routes;
 pub mod auth_routes;
+pub mod document_routes;
 pub mod feature_routes;
+pub mod generation_routes;
+pub mod job_routes;
 pub mod test_routes;
+pub mod usage_routes;
 
-use openrouter_api::OpenRouterClient;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::Notify;
 
 use crate::{
-    config::jwt::JwtConfig, models::embeddings, services::rag_store::RagStore,
+    config::jwt::JwtConfig,
+    models::embeddings,
+    services::{
+        generation_service::GenerationContext, llm_provider::LlmProvider, llm_service::ModelConfig,
+        presentation_service::MagicSlidesClient, rag_generate::ChunkingConfig,
+        vector_store::VectorStore,
+    },
     utils::errors::AppError,
 };
 use axum::{response::IntoResponse, Router};
-use fastembed::TextEmbedding;
+use fastembed::{TextEmbedding, TextRerank};
 use sea_orm::DatabaseConnection;
 
 #[derive(Clone)]
@@ -20,30 +30,64 @@ pub struct AppState {
     pub embedding_model: Arc<TextEmbedding>,
     pub jwt_config: JwtConfig,
     pub version: String,
-    pub rag_store: Arc<Mutex<RagStore>>,
-    pub client: Arc<OpenRouterClient<openrouter_api::Ready>>,
+    pub rag_store: Arc<dyn VectorStore>,
+    pub reranker: Option<Arc<TextRerank>>,
+    pub chunking: ChunkingConfig,
+    pub models: ModelConfig,
+    pub llm: Arc<dyn LlmProvider>,
+    pub magic_slides: Arc<MagicSlidesClient>,
+    /// Wakes a generation worker when a job is queued.
+    pub job_notify: Arc<Notify>,
+    /// Largest body accepted on upload routes, whatever the user's plan.
+    pub max_upload_bytes: usize,
+}
+
+impl AppState {
+    /// The state the generation services run with.
+    pub fn generation_context(&self) -> GenerationContext {
+        GenerationContext {
+            db: self.db.clone(),
+            embedding_model: self.embedding_model.clone(),
+            rag_store: self.rag_store.clone(),
+            reranker: self.reranker.clone(),
+            chunking: self.chunking.clone(),
+            models: self.models.clone(),
+            llm: self.llm.clone(),
+            magic_slides: self.magic_slides.clone(),
+        }
+    }
 }
 
 pub fn create_router(
-    db: DatabaseConnection,
     jwt_config: JwtConfig,
-    embedding_model: Arc<TextEmbedding>,
-    rag_store: Arc<Mutex<RagStore>>,
-    client: Arc<OpenRouterClient<openrouter_api::Ready>>,
+    generation: GenerationContext,
+    job_notify: Arc<Notify>,
+    max_upload_bytes: usize,
 ) -> Router {
     let state = AppState {
-        db: db.clone(),
+        db: generation.db,
         jwt_config: jwt_config.clone(),
         version: String::from("/api/v1"),
-        embedding_model,
-        rag_store,
-        client,
+        embedding_model: generation.embedding_model,
+        rag_store: generation.rag_store,
+        reranker: generation.reranker,
+        chunking: generation.chunking,
+        models: generation.models,
+        llm: generation.llm,
+        magic_slides: generation.magic_slides,
+        job_notify,
+        max_upload_bytes,
     };
 
     Router::new()
         .merge(test_routes::routes(state.clone()))
         .merge(auth_routes::routes(state.clone()))
         .merge(feature_routes::routes(state.clone()))
+        .merge(document_routes::routes(state.clone()))
+        .merge(admin_routes::routes(state.clone()))
+        .merge(job_routes::routes(state.clone()))
+        .merge(usage_routes::routes(state.clone()))
+        .merge(generation_routes::routes(state.clone()))
         .fallback(fallback_handler)
         .with_state(state)
 }
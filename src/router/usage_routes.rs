@@ -0,0 +1,18 @@
+use crate::{controllers::usage_controller::get_usage, middleware::auth::auth_middleware};
+
+use super::AppState;
+use axum::{middleware, routing::get, Router};
+
+pub fn routes(state: AppState) -> Router<AppState> {
+    let protected_routes = Router::new()
+        .route("/usage", get(get_usage))
+        //middleware
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    Router::new()
+        .nest(state.version.as_str(), protected_routes)
+        .with_state(state)
+}
@@ -6,6 +6,7 @@ use validator::Validate;
 
 use crate::config::jwt::JwtConfig;
 use crate::models::blacklist::{ActiveModel as BlacklistActiveModel, Column, Entity as Blacklist};
+use crate::models::plans::DEFAULT_PLAN;
 use crate::models::user::{
     ActiveModel as UserActiveModel, AuthResponse, Column::Email, Entity as User, RegisterRequest,
 };
@@ -123,6 +124,7 @@ pub async fn register_user_service(
         created_at: Set(Utc::now()),
         updated_at: Set(Utc::now()),
         is_admin: Set(false),
+        plan: Set(DEFAULT_PLAN.to_string()),
     };
     let user = user
         .insert(db)
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::{models::features::Citation, services::vector_store::SourceContext};
+
+/// Appended to prompts that include retrieved context.
+pub const CITATION_INSTRUCTIONS: &str = "When you use information from the context, cite it by \
+     writing the id of the chunk in square brackets right after the statement, e.g. [S1] or \
+     [S1, S3]. Only cite ids that appear in the context.";
+
+/// Id of the chunk at `index` in prompt order: `S1`, `S2`, ...
+pub fn citation_id(index: usize) -> String {
+    format!("S{}", index + 1)
+}
+
+fn is_citation_id(value: &str) -> bool {
+    value
+        .strip_prefix('S')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Every retrieved chunk as a citation, in prompt order.
+pub fn collect_citations(contexts: &[SourceContext]) -> Vec<Citation> {
+    let mut citations = Vec::new();
+    for context in contexts {
+        for retrieved in &context.chunks {
+            citations.push(Citation {
+                id: citation_id(citations.len()),
+                source: context.source.name.clone(),
+                page: retrieved.chunk.provenance.page,
+                heading: retrieved.chunk.provenance.heading.clone(),
+                start_offset: retrieved.chunk.provenance.start_offset,
+                end_offset: retrieved.chunk.provenance.end_offset,
+                similarity: retrieved.similarity,
+            });
+        }
+    }
+
+    citations
+}
+
+/// Takes the `[S#]` tags out of `text`, returning the text without them and
+/// the cited ids in order of first use.
+pub fn strip_citations(text: &str) -> (String, Vec<String>) {
+    let mut output = String::with_capacity(text.len());
+    let mut cited: Vec<String> = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        output.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+
+        let ids = rest[1..close].split(',').map(str::trim).collect::<Vec<_>>();
+        if !ids.iter().all(|id| is_citation_id(id)) {
+            output.push('[');
+            rest = &rest[1..];
+            continue;
+        }
+
+        for id in ids {
+            if !cited.iter().any(|c| c == id) {
+                cited.push(id.to_string());
+            }
+        }
+        let kept = output.trim_end().len();
+        output.truncate(kept);
+        rest = &rest[close + 1..];
+    }
+    output.push_str(rest);
+
+    (output.trim().to_string(), cited)
+}
+
+/// Checks the `[S#]` tags in generated content against the chunks that were
+/// retrieved. Tags naming a chunk that was never retrieved are removed from
+/// the content; the chunks that were cited are returned in order of first use.
+pub fn resolve_citations(content: &str, contexts: &[SourceContext]) -> (String, Vec<Citation>) {
+    let mut retrieved: HashMap<String, Citation> = collect_citations(contexts)
+        .into_iter()
+        .map(|citation| (citation.id.clone(), citation))
+        .collect();
+
+    let mut output = String::with_capacity(content.len());
+    let mut cited = Vec::new();
+    let mut rest = content;
+
+    while let Some(open) = rest.find('[') {
+        output.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+
+        let ids = rest[1..close].split(',').map(str::trim).collect::<Vec<_>>();
+        if !ids.iter().all(|id| is_citation_id(id)) {
+            // ordinary bracketed text, keep it and continue after the '['
+            output.push('[');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let mut valid = Vec::new();
+        for id in ids {
+            if let Some(citation) = retrieved.remove(id) {
+                cited.push(citation);
+                valid.push(id);
+            } else if cited.iter().any(|c: &Citation| c.id == id) {
+                valid.push(id);
+            } else {
+                eprintln!("Dropping citation of unknown source {}", id);
+            }
+        }
+
+        if !valid.is_empty() {
+            output.push_str(&format!("[{}]", valid.join(", ")));
+        } else if output.ends_with(' ') {
+            output.pop();
+        }
+        rest = &rest[close + 1..];
+    }
+    output.push_str(rest);
+
+    (output, cited)
+}
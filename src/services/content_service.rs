@@ -1,32 +1,98 @@
-use super::{extract::fetch_system_prompt, rag_generate::implement_rag};
+use super::extract::{fetch_system_prompt, prompt_version};
 use crate::{
     models::{
-        embeddings::{Column, Entity as Embedding},
         features::{AcademicContentRequest, GeneratedResponse},
+        generation_jobs::GenerationKind,
+        generations::Model as GenerationModel,
     },
     services::{
-        llm_service::run_prompt,
-        rag_store::{retrieve_relevant_chunks, RagStore},
+        citations::{resolve_citations, CITATION_INSTRUCTIONS},
+        document_service::resolve_context_documents,
+        generation_service::{request_value, save_generation, GenerationContext},
+        llm_provider::ResponseFormat,
+        llm_service::{run_prompt, stream_prompt},
+        retrieval::RetrievalOptions,
+        usage_service::record_llm_usage,
+        vector_store::{format_context, retrieve_relevant_chunks, SourceContext},
     },
     utils::errors::AppError,
 };
-use fastembed::TextEmbedding;
-use openrouter_api::client;
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
-use sha2::{Digest, Sha256};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// A prompt ready to send, with the context it was built from.
+pub struct ContentPrompt {
+    pub prompt: String,
+    pub retrieved: Vec<SourceContext>,
+    /// Models to try in order.
+    pub chain: Vec<String>,
+    /// The request, stored with the generation.
+    pub request: serde_json::Value,
+    pub prompt_version: String,
+}
 
 pub async fn content_service(
-    db: &DatabaseConnection,
+    ctx: &GenerationContext,
+    owner_id: Uuid,
     req: AcademicContentRequest,
-    model: &TextEmbedding,
-    rag_store: Arc<Mutex<RagStore>>,
-    client: Arc<client::OpenRouterClient<client::Ready>>,
+    job_id: Option<Uuid>,
 ) -> Result<GeneratedResponse, AppError> {
+    let prepared = prepare_content_prompt(ctx, owner_id, req).await?;
+
+    let mut response = run_prompt(
+        &prepared.prompt,
+        &prepared.chain,
+        ctx.llm.as_ref(),
+        ResponseFormat::Text,
+    )
+    .await?;
+    record_llm_usage(
+        &ctx.db,
+        &ctx.models,
+        owner_id,
+        GenerationKind::AcademicContent,
+        job_id,
+        0,
+        &response,
+    )
+    .await;
+
+    let (content, citations) = resolve_citations(&response.content, &prepared.retrieved);
+    response.content = content;
+    response.citations = citations;
+
+    let generation = save_generation(
+        &ctx.db,
+        owner_id,
+        GenerationKind::AcademicContent,
+        prepared.request,
+        &prepared.prompt_version,
+        &response,
+        job_id,
+    )
+    .await?;
+    response.generation_id = Some(generation.id);
+
+    Ok(response)
+}
+
+/// Retrieves context for the request and builds the prompt. Errors here are
+/// the client's to fix, so streaming callers run this before opening a stream.
+pub async fn prepare_content_prompt(
+    ctx: &GenerationContext,
+    owner_id: Uuid,
+    req: AcademicContentRequest,
+) -> Result<ContentPrompt, AppError> {
+    let retrieval = RetrievalOptions::new(req.retrieval_strategy, ctx.reranker.clone())?;
+    let chain = ctx
+        .models
+        .chain(&ctx.models.content_model, req.model.as_deref())?;
+    let request = request_value(&req)?;
+
     let sys_prompt = fetch_system_prompt("academic_content").await.map_err(|e| {
         AppError::InternalServerError(format!("Failed to fetch system prompt: {:?}", e))
     })?;
+    let prompt_version = prompt_version("academic_content", &sys_prompt);
 
     let base_prompt = format!(
         "{}\n\nGrade level: {}\nLength: {}\nTopic: {}\nStandard objective: {}\nAdditional criteria: {}",
@@ -38,82 +104,81 @@ pub async fn content_service(
         req.additional_criteria.unwrap_or("None".to_string())
     );
 
-    let ragged_prompt = if let Some(uploaded_content) = req.uploaded_content.as_deref() {
-        let mut hasher = Sha256::new();
-        hasher.update(uploaded_content);
-        let file_hash = format!("{:x}", hasher.finalize());
-
-        let cached = Embedding::find()
-            .filter(Column::FileHash.eq(file_hash.clone()))
-            .all(db)
-            .await
-            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
-
-        let mut store = rag_store.lock().await;
-
-        if cached.is_empty() {
-            println!("No cached embeddings found for file hash: {}", file_hash);
-
-            let (chunks, embeddings) = implement_rag(uploaded_content, model).map_err(|e| {
-                AppError::InternalServerError(format!("Failed to implement RAG: {}", e))
-            })?;
-
-            if chunks.is_empty() || embeddings.is_empty() {
-                return Err(AppError::BadRequest(
-                    "No chunks or embeddings generated.".to_string(),
-                ));
-            }
-
-            println!("Adding {} chunks and embeddings to the store", chunks.len());
-
-            store
-                .add_chunks_and_embeddings(db.clone(), &file_hash, chunks, embeddings)
-                .await
-                .map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to add chunks and embeddings: {:?}",
-                        e
-                    ))
-                })?;
-        } else {
-            println!("Using cached embeddings for file hash: {}", file_hash);
-
-            let chunks = cached.iter().map(|c| c.chunk.clone()).collect::<Vec<_>>();
-            let embeddings = cached
-                .iter()
-                .map(|c| c.embedding.clone())
-                .collect::<Vec<_>>();
-
-            for (chunk, embedding) in chunks.into_iter().zip(embeddings.into_iter()) {
-                store.add(chunk, embedding).map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to add cached embedding: {:?}",
-                        e
-                    ))
-                })?;
-            }
-        }
-
-        let relevant_chunks = retrieve_relevant_chunks(&req.topic, &store, model).map_err(|e| {
-            AppError::InternalServerError(format!("Failed to retrieve chunks: {:?}", e))
-        })?;
-        let context = relevant_chunks.join("\n");
-
-        context
+    let sources = resolve_context_documents(
+        &ctx.db,
+        owner_id,
+        &req.document_ids,
+        &req.uploaded_content,
+        ctx.embedding_model.clone(),
+        ctx.rag_store.clone(),
+        &ctx.chunking,
+    )
+    .await?;
+
+    let retrieved = retrieve_relevant_chunks(
+        &ctx.db,
+        &req.topic,
+        ctx.rag_store.as_ref(),
+        ctx.embedding_model.clone(),
+        owner_id,
+        &sources,
+        &retrieval,
+    )
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to retrieve chunks: {:?}", e)))?;
+    let ragged_prompt = format_context(&retrieved);
+
+    let prompt = if ragged_prompt.is_empty() {
+        base_prompt
     } else {
-        return Err(AppError::BadRequest(
-            "Uploaded content is required to create a RagStore.".to_string(),
-        ));
+        format!(
+            "{}\n\nRelevant context from the provided documents, grouped by source:\n{}\n\n{}",
+            base_prompt, ragged_prompt, CITATION_INSTRUCTIONS
+        )
     };
 
-    let prompt = format!(
-        "{}\n\nRelevant context from uploaded content:\n{}",
-        base_prompt, ragged_prompt
-    );
-
-    let response = run_prompt(&prompt, "qwen/qwq-32b:free", client)
-        .await
-        .map_err(|e| AppError::InternalServerError(format!("Failed to run prompt: {:?}", e)))?;
+    Ok(ContentPrompt {
+        prompt,
+        retrieved,
+        chain,
+        request,
+        prompt_version,
+    })
+}
 
-    Ok(response)
+/// Streams the completion of a prepared prompt through `deltas` and stores
+/// the finished generation.
+pub async fn stream_content_service(
+    ctx: &GenerationContext,
+    owner_id: Uuid,
+    prepared: ContentPrompt,
+    deltas: mpsc::Sender<String>,
+) -> Result<GenerationModel, AppError> {
+    let mut response =
+        stream_prompt(&prepared.prompt, &prepared.chain, ctx.llm.as_ref(), deltas).await?;
+    record_llm_usage(
+        &ctx.db,
+        &ctx.models,
+        owner_id,
+        GenerationKind::AcademicContent,
+        None,
+        0,
+        &response,
+    )
+    .await;
+
+    let (content, citations) = resolve_citations(&response.content, &prepared.retrieved);
+    response.content = content;
+    response.citations = citations;
+
+    save_generation(
+        &ctx.db,
+        owner_id,
+        GenerationKind::AcademicContent,
+        prepared.request,
+        &prepared.prompt_version,
+        &response,
+        None,
+    )
+    .await
 }
@@ -0,0 +1,374 @@
+use chrono::Utc;
+use fastembed::TextEmbedding;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, Set,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    models::{
+        documents::{
+            ActiveModel as DocumentActiveModel, Column, DocumentStatus, DocumentSummary,
+            Entity as Document, Model as DocumentModel, RenameDocumentRequest, UploadedDocument,
+        },
+        embeddings::{Column as EmbeddingColumn, Entity as Embedding},
+    },
+    services::{
+        rag_generate::{ChunkingConfig, ChunkingOverrides},
+        vector_store::{ensure_document_loaded, ContextSource, VectorStore},
+    },
+    utils::errors::AppError,
+};
+
+pub fn content_hash(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hash a document's chunks are cached and searched under. Documents with
+/// their own chunking overrides are keyed apart from the same content chunked
+/// with the server defaults, so the two never rebuild each other's chunks.
+fn document_hash(content: &str, chunking_overrides: Option<&serde_json::Value>) -> String {
+    match chunking_overrides {
+        Some(overrides) => {
+            let mut hasher = Sha256::new();
+            hasher.update(content);
+            hasher.update(overrides.to_string());
+            format!("{:x}", hasher.finalize())
+        }
+        None => content_hash(content),
+    }
+}
+
+/// Parses `document_ids` form values, accepting comma separated lists.
+pub fn parse_document_ids(value: &str) -> Result<Vec<Uuid>, AppError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            Uuid::parse_str(id)
+                .map_err(|e| AppError::BadRequest(format!("Invalid document id '{}': {}", id, e)))
+        })
+        .collect()
+}
+
+/// Resolves the chunking settings of a library document: the server defaults
+/// with the document's own overrides applied.
+pub fn document_chunking(chunking: &ChunkingConfig, document: &DocumentModel) -> ChunkingConfig {
+    let overrides = document
+        .chunking_overrides
+        .clone()
+        .and_then(|value| serde_json::from_value::<ChunkingOverrides>(value).ok())
+        .unwrap_or_default();
+
+    chunking.with_overrides(&overrides)
+}
+
+/// Stores an uploaded document and embeds it in the background. The returned
+/// document is still `processing`; clients poll it until it is `ready`.
+pub async fn upload_document_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    upload: UploadedDocument,
+    overrides: ChunkingOverrides,
+    chunking: &ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) -> Result<DocumentModel, AppError> {
+    if upload.content.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "No text could be extracted from the uploaded file".to_string(),
+        ));
+    }
+
+    chunking
+        .with_overrides(&overrides)
+        .validate()
+        .map_err(AppError::BadRequest)?;
+
+    let chunking_overrides = if overrides.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_value(&overrides).map_err(|e| {
+            AppError::InternalServerError(format!("JSON serialization error: {}", e))
+        })?)
+    };
+
+    let document = DocumentActiveModel {
+        id: Set(Uuid::new_v4()),
+        owner_id: Set(owner_id),
+        name: Set(upload.name),
+        file_hash: Set(document_hash(&upload.content, chunking_overrides.as_ref())),
+        extracted_text: Set(upload.content),
+        chunk_count: Set(0),
+        status: Set(DocumentStatus::Processing),
+        error: Set(None),
+        chunking_overrides: Set(chunking_overrides),
+        created_at: Set(Utc::now()),
+        updated_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    tokio::spawn(ingest_document(
+        db.clone(),
+        document.clone(),
+        document_chunking(chunking, &document),
+        model,
+        rag_store,
+    ));
+
+    Ok(document)
+}
+
+/// Restarts the ingestion of documents a previous process left `processing`.
+/// Embeddings are cached once per document hash, so a document another
+/// replica is still ingesting is not stored twice.
+pub async fn resume_document_ingestion(
+    db: DatabaseConnection,
+    chunking: ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) -> Result<(), AppError> {
+    let documents = Document::find()
+        .filter(Column::Status.eq(DocumentStatus::Processing))
+        .all(&db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    for document in documents {
+        let config = document_chunking(&chunking, &document);
+        tokio::spawn(ingest_document(
+            db.clone(),
+            document,
+            config,
+            model.clone(),
+            rag_store.clone(),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn ingest_document(
+    db: DatabaseConnection,
+    document: DocumentModel,
+    chunking: ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) {
+    let ingested = ingest_chunks(&db, &document, &chunking, model, rag_store).await;
+
+    let mut active = document.clone().into_active_model();
+    match ingested {
+        Ok(chunk_count) => {
+            active.chunk_count = Set(chunk_count);
+            active.status = Set(DocumentStatus::Ready);
+        }
+        Err(e) => {
+            eprintln!("Failed to ingest document {}: {:?}", document.id, e);
+            active.status = Set(DocumentStatus::Failed);
+            active.error = Set(Some(format!("{:?}", e)));
+        }
+    }
+    active.updated_at = Set(Utc::now());
+
+    if let Err(e) = active.update(&db).await {
+        eprintln!("Failed to update document {} status: {}", document.id, e);
+    }
+}
+
+async fn ingest_chunks(
+    db: &DatabaseConnection,
+    document: &DocumentModel,
+    chunking: &ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) -> Result<i32, AppError> {
+    ensure_document_loaded(
+        db,
+        rag_store.as_ref(),
+        model,
+        document.owner_id,
+        &document.file_hash,
+        &document.extracted_text,
+        chunking,
+    )
+    .await?;
+
+    let chunk_count = Embedding::find()
+        .filter(EmbeddingColumn::OwnerId.eq(document.owner_id))
+        .filter(EmbeddingColumn::FileHash.eq(document.file_hash.clone()))
+        .count(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(chunk_count as i32)
+}
+
+pub async fn list_documents_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+) -> Result<Vec<DocumentSummary>, AppError> {
+    let documents = Document::find()
+        .filter(Column::OwnerId.eq(owner_id))
+        .order_by_desc(Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(documents.into_iter().map(DocumentSummary::from).collect())
+}
+
+pub async fn get_document_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    document_id: Uuid,
+) -> Result<DocumentModel, AppError> {
+    Document::find_by_id(document_id)
+        .filter(Column::OwnerId.eq(owner_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound("Document not found".to_string()))
+}
+
+pub async fn rename_document_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    document_id: Uuid,
+    payload: RenameDocumentRequest,
+) -> Result<DocumentSummary, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let mut document = get_document_service(db, owner_id, document_id)
+        .await?
+        .into_active_model();
+    document.name = Set(payload.name);
+    document.updated_at = Set(Utc::now());
+
+    let document = document
+        .update(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(DocumentSummary::from(document))
+}
+
+/// Deletes a document. Its embeddings are dropped too unless another of the
+/// owner's documents has the same content and chunking overrides.
+pub async fn delete_document_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    document_id: Uuid,
+    rag_store: Arc<dyn VectorStore>,
+) -> Result<(), AppError> {
+    let document = get_document_service(db, owner_id, document_id).await?;
+
+    Document::delete_by_id(document.id)
+        .exec(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let still_referenced = Document::find()
+        .filter(Column::OwnerId.eq(owner_id))
+        .filter(Column::FileHash.eq(document.file_hash.clone()))
+        .count(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    if still_referenced == 0 {
+        rag_store
+            .delete_by_hash(owner_id, &document.file_hash)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Makes the requested library documents and any ad-hoc uploads searchable
+/// and returns the sources retrieval should draw from. Documents with the same
+/// content and chunking are only listed once.
+pub async fn resolve_context_documents(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    document_ids: &[Uuid],
+    uploads: &[UploadedDocument],
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+    chunking: &ChunkingConfig,
+) -> Result<Vec<ContextSource>, AppError> {
+    let mut sources: Vec<ContextSource> = Vec::new();
+
+    for document_id in document_ids {
+        let document = get_document_service(db, owner_id, *document_id).await?;
+
+        if document.status != DocumentStatus::Ready {
+            return Err(AppError::BadRequest(format!(
+                "Document '{}' is not ready for use",
+                document.name
+            )));
+        }
+
+        if sources.iter().any(|s| s.file_hash == document.file_hash) {
+            continue;
+        }
+
+        ensure_document_loaded(
+            db,
+            rag_store.as_ref(),
+            model.clone(),
+            owner_id,
+            &document.file_hash,
+            &document.extracted_text,
+            &document_chunking(chunking, &document),
+        )
+        .await?;
+
+        sources.push(ContextSource {
+            name: document.name,
+            file_hash: document.file_hash,
+        });
+    }
+
+    for upload in uploads {
+        let file_hash = content_hash(&upload.content);
+
+        if sources.iter().any(|s| s.file_hash == file_hash) {
+            continue;
+        }
+
+        ensure_document_loaded(
+            db,
+            rag_store.as_ref(),
+            model.clone(),
+            owner_id,
+            &file_hash,
+            &upload.content,
+            chunking,
+        )
+        .await?;
+
+        sources.push(ContextSource {
+            name: upload.name.clone(),
+            file_hash,
+        });
+    }
+
+    if sources.is_empty() {
+        return Err(AppError::BadRequest(
+            "Uploaded content or document_ids are required to build context.".to_string(),
+        ));
+    }
+
+    Ok(sources)
+}
@@ -0,0 +1,108 @@
+use super::option_letter;
+use crate::{models::mcq::McqSet, utils::errors::AppError};
+
+/// Renders the questions as CSV, one row per question: number, question,
+/// one column per option (`option_a`, `option_b`, ...), the letter of the
+/// answer, explanation, difficulty and topic.
+pub fn render(set: &McqSet) -> Result<Vec<u8>, AppError> {
+    let csv_error =
+        |e: csv::Error| AppError::InternalServerError(format!("Failed to write CSV: {}", e));
+
+    let option_columns = set
+        .questions
+        .iter()
+        .map(|question| question.options.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let mut header = vec!["number".to_string(), "question".to_string()];
+    header.extend(
+        (0..option_columns)
+            .map(|index| format!("option_{}", option_letter(index).to_ascii_lowercase())),
+    );
+    header.extend(["answer", "explanation", "difficulty", "topic"].map(str::to_string));
+    writer.write_record(&header).map_err(csv_error)?;
+
+    for (index, question) in set.questions.iter().enumerate() {
+        let mut row = vec![(index + 1).to_string(), question.stem.clone()];
+        row.extend(
+            (0..option_columns)
+                .map(|option| question.options.get(option).cloned().unwrap_or_default()),
+        );
+        row.push(option_letter(question.correct_index).to_string());
+        row.push(question.explanation.clone());
+        row.push(question.difficulty.as_str().to_string());
+        row.push(question.topic.clone());
+
+        writer.write_record(&row).map_err(csv_error)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to write CSV: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::{
+        models::mcq::{McqQuestion, McqSet},
+        services::export::test_support::sample_set,
+    };
+
+    /// Reads the sheet back by column name, as a spreadsheet import would.
+    fn read(sheet: &[u8]) -> McqSet {
+        let mut reader = csv::Reader::from_reader(sheet);
+        let header = reader.headers().unwrap().clone();
+        let column = |name: &str| header.iter().position(|h| h == name).unwrap();
+        let option_columns = header
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| name.starts_with("option_"))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let questions = reader
+            .records()
+            .map(|record| {
+                let record = record.unwrap();
+                let answer = record[column("answer")].chars().next().unwrap();
+
+                McqQuestion {
+                    stem: record[column("question")].to_string(),
+                    options: option_columns
+                        .iter()
+                        .map(|&index| record[index].to_string())
+                        .filter(|option| !option.is_empty())
+                        .collect(),
+                    correct_index: (answer as u8 - b'A') as usize,
+                    explanation: record[column("explanation")].to_string(),
+                    difficulty: record[column("difficulty")].parse().unwrap(),
+                    topic: record[column("topic")].to_string(),
+                    sources: Vec::new(),
+                }
+            })
+            .collect();
+
+        McqSet { questions }
+    }
+
+    #[test]
+    fn round_trips_the_sample_set() {
+        let set = sample_set();
+
+        assert_eq!(read(&render(&set).unwrap()), set);
+    }
+
+    #[test]
+    fn has_a_column_for_every_option_of_the_longest_question() {
+        let sheet = String::from_utf8(render(&sample_set()).unwrap()).unwrap();
+
+        assert_eq!(
+            sheet.lines().next().unwrap(),
+            "number,question,option_a,option_b,option_c,option_d,option_e,answer,explanation,difficulty,topic"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use super::{
+    document::{Block, Document, ListItem, Span},
+    markdown,
+};
+use crate::models::{features::Citation, generations::Model as GenerationModel};
+
+/// Academic content as a document: the generated markdown under a title,
+/// followed by the sources its `[S#]` tags refer to.
+pub fn content_document(generation: &GenerationModel, title: &str) -> Document {
+    let mut blocks = markdown::parse(&generation.content);
+
+    if !matches!(blocks.first(), Some(Block::Heading { .. })) {
+        blocks.insert(
+            0,
+            Block::Heading {
+                level: 1,
+                spans: vec![Span::plain(title)],
+            },
+        );
+    }
+
+    let citations: Vec<Citation> =
+        serde_json::from_value(generation.citations.clone()).unwrap_or_default();
+    if !citations.is_empty() {
+        blocks.push(Block::Heading {
+            level: 2,
+            spans: vec![Span::plain("Sources")],
+        });
+        blocks.push(Block::List(
+            citations
+                .iter()
+                .map(|citation| ListItem::bullet(vec![Span::plain(citation_label(citation))]))
+                .collect(),
+        ));
+    }
+
+    Document {
+        title: title.to_string(),
+        blocks,
+    }
+}
+
+/// E.g. `[S1] biology.pdf, page 4, The kidneys`.
+fn citation_label(citation: &Citation) -> String {
+    let mut label = format!("[{}] {}", citation.id, citation.source);
+    if let Some(page) = citation.page {
+        label.push_str(&format!(", page {}", page));
+    }
+    if let Some(heading) = citation.heading.as_deref().filter(|h| !h.is_empty()) {
+        label.push_str(&format!(", {}", heading));
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::content_document;
+    use crate::{
+        models::{generation_jobs::GenerationKind, generations::Model as GenerationModel},
+        services::export::document::{Block, Document, Span},
+    };
+
+    fn generation(content: &str, citations: serde_json::Value) -> GenerationModel {
+        GenerationModel {
+            id: Uuid::new_v4(),
+            owner_id: Uuid::new_v4(),
+            kind: GenerationKind::AcademicContent,
+            request: json!({ "topic": "The Water Cycle" }),
+            prompt_version: "v1".to_string(),
+            model: "test".to_string(),
+            content: content.to_string(),
+            citations,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            job_id: None,
+        }
+    }
+
+    fn text(spans: &[Span]) -> String {
+        spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// The level and text of every heading.
+    fn headings(document: &Document) -> Vec<(usize, String)> {
+        document
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Heading { level, spans } => Some((*level, text(spans))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn content_without_a_heading_gets_the_title() {
+        let document = content_document(
+            &generation("Water moves between the oceans and the land.", json!([])),
+            "The Water Cycle",
+        );
+
+        assert_eq!(document.title, "The Water Cycle");
+        assert_eq!(headings(&document), [(1, "The Water Cycle".to_string())]);
+        assert!(matches!(
+            document.blocks[0],
+            Block::Heading { level: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn content_keeps_its_own_heading() {
+        let document = content_document(
+            &generation("# Evaporation\n\nWater turns into vapour.", json!([])),
+            "The Water Cycle",
+        );
+
+        assert_eq!(headings(&document), [(1, "Evaporation".to_string())]);
+    }
+
+    #[test]
+    fn citations_are_listed_as_sources() {
+        let citations = json!([
+            {
+                "id": "S1",
+                "source": "biology.pdf",
+                "page": 4,
+                "heading": "The kidneys",
+                "start_offset": 10,
+                "end_offset": 200,
+                "similarity": 0.8
+            },
+            {
+                "id": "S2",
+                "source": "notes.md",
+                "page": null,
+                "heading": "",
+                "start_offset": null,
+                "end_offset": null,
+                "similarity": null
+            }
+        ]);
+        let document = content_document(
+            &generation("Water evaporates [S1] and condenses [S2].", citations),
+            "The Water Cycle",
+        );
+
+        assert_eq!(
+            headings(&document).last(),
+            Some(&(2, "Sources".to_string()))
+        );
+        let Some(Block::List(items)) = document.blocks.last() else {
+            panic!("the sources should end the document");
+        };
+        let labels: Vec<String> = items.iter().map(|item| text(&item.spans)).collect();
+        assert_eq!(
+            labels,
+            ["[S1] biology.pdf, page 4, The kidneys", "[S2] notes.md"]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+/// A document laid out the same way whichever format it is rendered to.
+pub struct Document {
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+pub enum Block {
+    /// Centered, large and bold, at the top of the first page.
+    Title(String),
+    /// A centered line under the title, e.g. the subject and class.
+    Subtitle(String),
+    /// Level 1 is the top section level.
+    Heading {
+        level: usize,
+        spans: Vec<Span>,
+    },
+    Paragraph(Vec<Span>),
+    /// Consecutive list items, nested by their level.
+    List(Vec<ListItem>),
+    Quote(Vec<Span>),
+    /// Preformatted text, printed in a monospace font.
+    Code(String),
+    /// A numbered question with lettered options.
+    Question {
+        number: usize,
+        stem: String,
+        options: Vec<String>,
+    },
+    Table {
+        header: Vec<Vec<Span>>,
+        rows: Vec<Vec<Vec<Span>>>,
+    },
+}
+
+pub struct ListItem {
+    /// 0 for top-level items, 1 for items nested in them, and so on.
+    pub level: usize,
+    pub ordered: bool,
+    pub spans: Vec<Span>,
+}
+
+impl ListItem {
+    pub fn bullet(spans: Vec<Span>) -> Self {
+        Self {
+            level: 0,
+            ordered: false,
+            spans,
+        }
+    }
+}
+
+/// The number of every item within its list, counting from 1. Nested lists
+/// start again under each item of their parent.
+pub fn list_numbers(items: &[ListItem]) -> Vec<usize> {
+    let mut counters: Vec<usize> = Vec::new();
+
+    items
+        .iter()
+        .map(|item| {
+            counters.resize(item.level + 1, 0);
+            counters[item.level] += 1;
+            counters[item.level]
+        })
+        .collect()
+}
+
+/// A run of text sharing one style.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    /// Inline code, printed in a monospace font.
+    pub code: bool,
+    pub link: Option<String>,
+}
+
+impl Span {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn bold(text: impl Into<String>) -> Self {
+        Self {
+            bold: true,
+            ..Self::plain(text)
+        }
+    }
+
+    /// Whether both spans can be printed as one.
+    pub fn same_style(&self, other: &Span) -> bool {
+        self.bold == other.bold
+            && self.italic == other.italic
+            && self.code == other.code
+            && self.link == other.link
+    }
+}
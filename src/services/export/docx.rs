@@ -0,0 +1,434 @@
+use docx_rs::{
+    AbstractNumbering, AlignmentType, BreakType, Docx, IndentLevel, Level, LevelJc, LevelText,
+    LineSpacing, NumberFormat, Numbering, NumberingId, Paragraph, Run, RunFonts, SpecialIndentType,
+    Start, Style, StyleType, Table, TableCell, TableRow,
+};
+use std::io::Cursor;
+
+use super::{
+    document::{Block, Document, ListItem, Span},
+    option_letter,
+};
+use crate::utils::errors::AppError;
+
+/// Font sizes in half-points.
+const TITLE_SIZE: usize = 32;
+const HEADING_SIZES: [usize; 3] = [28, 26, 24];
+const BODY_SIZE: usize = 22;
+const CODE_SIZE: usize = 20;
+
+/// Indent steps in twentieths of a point.
+const INDENT: i32 = 360;
+
+const MONOSPACE: &str = "Courier New";
+
+/// Word lists nest at most nine levels deep.
+const MAX_LEVEL: usize = 8;
+
+/// docx-rs always writes a default numbering with id 1, so ours start after.
+const FIRST_NUMBERING_ID: usize = 2;
+
+/// Renders `document` as a Word document. Headings use the built-in
+/// `Heading1` to `Heading3` styles and lists real numbering, so the file
+/// stays easy to edit in Word.
+pub fn render(document: &Document) -> Result<Vec<u8>, AppError> {
+    let mut docx = Docx::new().add_style(
+        Style::new("Title", StyleType::Paragraph)
+            .name("Title")
+            .size(TITLE_SIZE)
+            .bold(),
+    );
+    for (index, size) in HEADING_SIZES.into_iter().enumerate() {
+        docx = docx.add_style(
+            Style::new(format!("Heading{}", index + 1), StyleType::Paragraph)
+                .name(format!("Heading {}", index + 1))
+                .size(size)
+                .bold(),
+        );
+    }
+    let mut lists = FIRST_NUMBERING_ID - 1;
+
+    for block in &document.blocks {
+        docx = match block {
+            Block::Title(text) => docx.add_paragraph(
+                Paragraph::new()
+                    .style("Title")
+                    .align(AlignmentType::Center)
+                    .line_spacing(LineSpacing::new().after(120))
+                    .add_run(text_run(text).size(TITLE_SIZE).bold()),
+            ),
+            Block::Subtitle(text) => docx.add_paragraph(
+                Paragraph::new()
+                    .align(AlignmentType::Center)
+                    .add_run(text_run(text).size(BODY_SIZE)),
+            ),
+            Block::Heading { level, spans } => {
+                let level = (*level).clamp(1, HEADING_SIZES.len());
+                let size = HEADING_SIZES[level - 1];
+                docx.add_paragraph(
+                    spans.iter().fold(
+                        Paragraph::new()
+                            .style(&format!("Heading{}", level))
+                            .line_spacing(LineSpacing::new().before(240).after(120)),
+                        |paragraph, span| add_span(paragraph, span, size, true),
+                    ),
+                )
+            }
+            Block::Paragraph(spans) => docx.add_paragraph(paragraph(spans)),
+            Block::List(items) => {
+                // every list gets its own numbering so it starts again at 1
+                lists += 1;
+                let docx = docx
+                    .add_abstract_numbering(numbering(lists, items))
+                    .add_numbering(Numbering::new(lists, lists));
+
+                items.iter().fold(docx, |docx, item| {
+                    docx.add_paragraph(paragraph(&item.spans).numbering(
+                        NumberingId::new(lists),
+                        IndentLevel::new(item.level.min(MAX_LEVEL)),
+                    ))
+                })
+            }
+            Block::Quote(spans) => docx.add_paragraph(
+                spans
+                    .iter()
+                    .fold(
+                        Paragraph::new().line_spacing(LineSpacing::new().before(120)),
+                        |paragraph, span| {
+                            let span = Span {
+                                italic: true,
+                                ..span.clone()
+                            };
+                            add_span(paragraph, &span, BODY_SIZE, false)
+                        },
+                    )
+                    .indent(Some(2 * INDENT), None, None, None),
+            ),
+            Block::Code(code) => docx.add_paragraph(
+                Paragraph::new()
+                    .indent(Some(INDENT), None, None, None)
+                    .line_spacing(LineSpacing::new().before(120))
+                    .add_run(text_run(code).size(CODE_SIZE).fonts(monospace())),
+            ),
+            Block::Question {
+                number,
+                stem,
+                options,
+            } => {
+                let docx = docx.add_paragraph(
+                    Paragraph::new()
+                        .indent(
+                            Some(INDENT),
+                            Some(SpecialIndentType::Hanging(INDENT)),
+                            None,
+                            None,
+                        )
+                        .line_spacing(LineSpacing::new().before(240).after(60))
+                        .add_run(
+                            Run::new()
+                                .add_text(format!("{}.", number))
+                                .add_tab()
+                                .size(BODY_SIZE)
+                                .bold(),
+                        )
+                        .add_run(text_run(stem).size(BODY_SIZE)),
+                );
+
+                options
+                    .iter()
+                    .enumerate()
+                    .fold(docx, |docx, (index, option)| {
+                        docx.add_paragraph(
+                            Paragraph::new()
+                                .indent(
+                                    Some(3 * INDENT),
+                                    Some(SpecialIndentType::Hanging(INDENT)),
+                                    None,
+                                    None,
+                                )
+                                .add_run(
+                                    Run::new()
+                                        .add_text(option_letter(index).to_string() + ".")
+                                        .add_tab()
+                                        .size(BODY_SIZE),
+                                )
+                                .add_run(text_run(option).size(BODY_SIZE)),
+                        )
+                    })
+            }
+            Block::Table { header, rows } => docx.add_table(table(header, rows)),
+        };
+    }
+
+    let mut bytes = Cursor::new(Vec::new());
+    docx.build()
+        .pack(&mut bytes)
+        .map_err(|e| AppError::InternalServerError(format!("DOCX rendering error: {}", e)))?;
+
+    Ok(bytes.into_inner())
+}
+
+fn paragraph(spans: &[Span]) -> Paragraph {
+    spans.iter().fold(
+        Paragraph::new().line_spacing(LineSpacing::new().before(120)),
+        |paragraph, span| add_span(paragraph, span, BODY_SIZE, false),
+    )
+}
+
+/// The numbering of a list. Each nesting level is numbered or bulleted
+/// like its first item.
+fn numbering(id: usize, items: &[ListItem]) -> AbstractNumbering {
+    let depth = items
+        .iter()
+        .map(|item| item.level)
+        .max()
+        .unwrap_or(0)
+        .min(MAX_LEVEL);
+
+    (0..=depth).fold(AbstractNumbering::new(id), |numbering, level| {
+        let ordered = items
+            .iter()
+            .find(|item| item.level == level)
+            .is_some_and(|item| item.ordered);
+        let (format, text) = match (ordered, level % 2) {
+            (true, _) => ("decimal", format!("%{}.", level + 1)),
+            (false, 0) => ("bullet", "\u{2022}".to_string()),
+            (false, _) => ("bullet", "\u{2013}".to_string()),
+        };
+
+        numbering.add_level(
+            Level::new(
+                level,
+                Start::new(1),
+                NumberFormat::new(format),
+                LevelText::new(text),
+                LevelJc::new("left"),
+            )
+            .indent(
+                Some((level as i32 + 2) * INDENT),
+                Some(SpecialIndentType::Hanging(INDENT)),
+                None,
+                None,
+            ),
+        )
+    })
+}
+
+fn table(header: &[Vec<Span>], rows: &[Vec<Vec<Span>>]) -> Table {
+    let cell = |spans: &Vec<Span>, bold: bool| {
+        TableCell::new().add_paragraph(spans.iter().fold(Paragraph::new(), |paragraph, span| {
+            add_span(paragraph, span, BODY_SIZE, bold)
+        }))
+    };
+
+    let mut table_rows = Vec::new();
+    if !header.is_empty() {
+        table_rows.push(TableRow::new(
+            header.iter().map(|spans| cell(spans, true)).collect(),
+        ));
+    }
+    table_rows.extend(
+        rows.iter()
+            .map(|row| TableRow::new(row.iter().map(|spans| cell(spans, false)).collect())),
+    );
+
+    Table::new(table_rows)
+}
+
+/// Adds a span to a paragraph. Links are written as their text followed by
+/// the address.
+fn add_span(paragraph: Paragraph, span: &Span, size: usize, bold: bool) -> Paragraph {
+    let mut run = text_run(&span.text).size(size);
+    if bold || span.bold {
+        run = run.bold();
+    }
+    if span.italic {
+        run = run.italic();
+    }
+    if span.code {
+        run = run.fonts(monospace());
+    }
+    let paragraph = paragraph.add_run(run);
+
+    match span.link.as_ref().filter(|url| **url != span.text) {
+        Some(url) => paragraph.add_run(text_run(&format!(" ({})", url)).size(size)),
+        None => paragraph,
+    }
+}
+
+fn monospace() -> RunFonts {
+    RunFonts::new().ascii(MONOSPACE).hi_ansi(MONOSPACE)
+}
+
+/// A run of text, with line breaks where the text has newlines.
+fn text_run(text: &str) -> Run {
+    text.split('\n')
+        .enumerate()
+        .fold(Run::new(), |run, (index, line)| match index {
+            0 => run.add_text(line),
+            _ => run.add_break(BreakType::TextWrapping).add_text(line),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    use super::render;
+    use crate::services::export::{
+        document::{Block, Document, ListItem, Span},
+        test_support::{parse_xml, Element},
+    };
+
+    fn part(document: &Document, name: &str) -> Element {
+        let bytes = render(document).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(bytes.as_slice())).expect("a zip");
+        let mut xml = String::new();
+        archive
+            .by_name(name)
+            .expect("part in package")
+            .read_to_string(&mut xml)
+            .unwrap();
+
+        parse_xml(&xml)
+    }
+
+    /// The body of the package's main document part.
+    fn body(document: &Document) -> Element {
+        part(document, "word/document.xml")
+            .children
+            .into_iter()
+            .find(|element| element.name == "body")
+            .expect("a body")
+    }
+
+    fn text(paragraph: &Element) -> String {
+        paragraph
+            .children("r")
+            .map(|run| run.text_at(&["t"]))
+            .collect()
+    }
+
+    fn heading(level: usize, text: &str) -> Block {
+        Block::Heading {
+            level,
+            spans: vec![Span::plain(text)],
+        }
+    }
+
+    fn item(level: usize, ordered: bool, text: &str) -> ListItem {
+        ListItem {
+            level,
+            ordered,
+            spans: vec![Span::plain(text)],
+        }
+    }
+
+    fn sample() -> Document {
+        Document {
+            title: "The Water Cycle".to_string(),
+            blocks: vec![
+                heading(1, "The Water Cycle"),
+                heading(2, "Stages"),
+                heading(3, "Evaporation"),
+                heading(5, "Too deep"),
+                Block::List(vec![
+                    item(0, true, "Heat"),
+                    item(1, false, "From the sun"),
+                    item(0, true, "Wind"),
+                ]),
+                Block::Table {
+                    header: vec![vec![Span::plain("Term")], vec![Span::plain("Meaning")]],
+                    rows: vec![vec![
+                        vec![Span::plain("Runoff")],
+                        vec![Span::plain("Water over land")],
+                    ]],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn headings_use_the_built_in_styles() {
+        let body = body(&sample());
+        let styles: Vec<(String, String)> = body
+            .children("p")
+            .filter_map(|paragraph| {
+                let style = paragraph.find(&["pPr", "pStyle"])?.attribute("val")?;
+                Some((style.to_string(), text(paragraph)))
+            })
+            .collect();
+
+        assert_eq!(
+            styles,
+            [
+                ("Heading1", "The Water Cycle"),
+                ("Heading2", "Stages"),
+                ("Heading3", "Evaporation"),
+                ("Heading3", "Too deep"),
+            ]
+            .map(|(style, text)| (style.to_string(), text.to_string()))
+        );
+    }
+
+    #[test]
+    fn list_items_are_numbered_at_their_level() {
+        let body = body(&sample());
+        let items: Vec<(String, String, String)> = body
+            .children("p")
+            .filter_map(|paragraph| {
+                let numbering = paragraph.find(&["pPr", "numPr"])?;
+                Some((
+                    text(paragraph),
+                    numbering.find(&["numId"])?.attribute("val")?.to_string(),
+                    numbering.find(&["ilvl"])?.attribute("val")?.to_string(),
+                ))
+            })
+            .collect();
+
+        assert_eq!(
+            items,
+            [
+                ("Heat", "2", "0"),
+                ("From the sun", "2", "1"),
+                ("Wind", "2", "0")
+            ]
+            .map(|(text, id, level)| (
+                text.to_string(),
+                id.to_string(),
+                level.to_string()
+            ))
+        );
+
+        let numbering = part(&sample(), "word/numbering.xml");
+        let formats: Vec<&str> = numbering
+            .children("abstractNum")
+            .find(|numbering| numbering.attribute("abstractNumId") == Some("2"))
+            .expect("the list's numbering")
+            .children("lvl")
+            .filter_map(|level| level.find(&["numFmt"])?.attribute("val"))
+            .collect();
+        assert_eq!(formats, ["decimal", "bullet"]);
+    }
+
+    #[test]
+    fn tables_are_word_tables() {
+        let body = body(&sample());
+        let table = body.find(&["tbl"]).expect("a table");
+        let cells: Vec<Vec<String>> = table
+            .children("tr")
+            .map(|row| {
+                row.children("tc")
+                    .map(|cell| text(cell.find(&["p"]).unwrap()))
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            cells,
+            [["Term", "Meaning"], ["Runoff", "Water over land"]]
+                .map(|row| row.map(str::to_string).to_vec())
+        );
+    }
+}
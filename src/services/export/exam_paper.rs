@@ -0,0 +1,485 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::{
+    document::{Block, Document, ListItem, Span},
+    option_letter,
+};
+use crate::{
+    models::{
+        generations::{ExamVariant, ExportQuery},
+        mcq::{McqQuestion, McqSet},
+    },
+    utils::errors::AppError,
+};
+
+const DEFAULT_INSTRUCTIONS: &str =
+    "Answer all questions. Circle the letter of the one correct option for each question.";
+
+const MAX_MARKS_PER_QUESTION: u32 = 100;
+
+/// Shuffles tried before a variant falls back to rotating paper A's order.
+const MAX_DRAWS: usize = 8;
+
+/// Printed at the top of the papers and the answer key.
+pub struct ExamHeader {
+    pub school: Option<String>,
+    pub title: String,
+    pub subject: Option<String>,
+    pub class_name: Option<String>,
+    pub duration: Option<String>,
+    pub date: Option<String>,
+    pub instructions: String,
+    pub marks_per_question: u32,
+}
+
+impl ExamHeader {
+    /// The header fields of the export query, leaving out blank ones.
+    pub fn new(query: &ExportQuery, topic: &str) -> Result<Self, AppError> {
+        let field = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let marks_per_question = query.marks_per_question.unwrap_or(1);
+        if !(1..=MAX_MARKS_PER_QUESTION).contains(&marks_per_question) {
+            return Err(AppError::BadRequest(format!(
+                "marks_per_question must be between 1 and {}",
+                MAX_MARKS_PER_QUESTION
+            )));
+        }
+
+        Ok(Self {
+            school: field(&query.school),
+            title: field(&query.title).unwrap_or_else(|| topic.to_string()),
+            subject: field(&query.subject),
+            class_name: field(&query.class_name),
+            duration: field(&query.duration),
+            date: field(&query.date),
+            instructions: field(&query.instructions)
+                .unwrap_or_else(|| DEFAULT_INSTRUCTIONS.to_string()),
+            marks_per_question,
+        })
+    }
+
+    /// Marks for the whole paper.
+    fn total(&self, set: &McqSet) -> u32 {
+        u32::try_from(set.questions.len())
+            .unwrap_or(u32::MAX)
+            .saturating_mul(self.marks_per_question)
+    }
+}
+
+/// The question paper of one variant. `seed` fixes the option order, so
+/// downloading a variant again gives the same paper.
+pub fn exam_paper(set: &McqSet, header: &ExamHeader, variant: ExamVariant, seed: u64) -> Document {
+    let total = header.total(set);
+
+    let mut blocks = header_blocks(header);
+    blocks.push(Block::Subtitle(format!(
+        "Paper {} \u{b7} Total: {}",
+        variant.as_str(),
+        marks(total)
+    )));
+    blocks.push(Block::Paragraph(vec![
+        Span::bold("Name: "),
+        Span::plain("______________________________________ "),
+        Span::bold("Class: "),
+        Span::plain("________________"),
+    ]));
+    blocks.push(Block::Paragraph(vec![
+        Span::bold("Instructions: "),
+        Span::plain(header.instructions.as_str()),
+    ]));
+
+    let orders = &option_orders(set, seed)[variant as usize];
+    for (index, (question, order)) in set.questions.iter().zip(orders).enumerate() {
+        blocks.push(Block::Question {
+            number: index + 1,
+            stem: question.stem.clone(),
+            options: order
+                .iter()
+                .map(|&option| question.options[option].clone())
+                .collect(),
+        });
+    }
+
+    Document {
+        title: format!("{} - Paper {}", header.title, variant.as_str()),
+        blocks,
+    }
+}
+
+/// The correct letter of every question on each variant, the marking
+/// scheme and the explanations, for the teacher only.
+pub fn answer_key(set: &McqSet, header: &ExamHeader, seed: u64) -> Document {
+    let total = header.total(set);
+
+    let mut blocks = header_blocks(header);
+    blocks.push(Block::Subtitle("Answer key and marking scheme".to_string()));
+
+    let orders = option_orders(set, seed);
+
+    let mut table_header = vec![vec![Span::plain("Question")]];
+    table_header.extend(
+        ExamVariant::ALL
+            .iter()
+            .map(|variant| vec![Span::plain(format!("Paper {}", variant.as_str()))]),
+    );
+    table_header.push(vec![Span::plain("Marks")]);
+
+    let rows = set
+        .questions
+        .iter()
+        .enumerate()
+        .map(|(index, question)| {
+            let mut row = vec![vec![Span::plain((index + 1).to_string())]];
+            for variant_orders in &orders {
+                let position = variant_orders[index]
+                    .iter()
+                    .position(|&option| option == question.correct_index)
+                    .unwrap_or_default();
+                row.push(vec![Span::plain(option_letter(position).to_string())]);
+            }
+            row.push(vec![Span::plain(header.marks_per_question.to_string())]);
+            row
+        })
+        .collect();
+
+    blocks.push(heading("Answers"));
+    blocks.push(Block::Table {
+        header: table_header,
+        rows,
+    });
+
+    blocks.push(heading("Marking scheme"));
+    blocks.push(Block::List(
+        [
+            "Use the column of the paper letter printed at the top of the student's paper."
+                .to_string(),
+            format!(
+                "Each correct answer scores {}.",
+                marks(header.marks_per_question)
+            ),
+            "A wrong, blank or multiple answer scores 0 marks.".to_string(),
+            format!(
+                "Total: {} for {} questions.",
+                marks(total),
+                set.questions.len()
+            ),
+        ]
+        .into_iter()
+        .map(|item| ListItem::bullet(vec![Span::plain(item)]))
+        .collect(),
+    ));
+
+    let explained: Vec<_> = set
+        .questions
+        .iter()
+        .enumerate()
+        .filter(|(_, question)| !question.explanation.trim().is_empty())
+        .collect();
+    if !explained.is_empty() {
+        blocks.push(heading("Explanations"));
+        for (index, question) in explained {
+            blocks.push(Block::Paragraph(vec![
+                Span::bold(format!(
+                    "{}. {} ",
+                    index + 1,
+                    question.options[question.correct_index]
+                )),
+                Span::plain(question.explanation.as_str()),
+            ]));
+        }
+    }
+
+    Document {
+        title: format!("{} - Answer key", header.title),
+        blocks,
+    }
+}
+
+fn heading(text: &str) -> Block {
+    Block::Heading {
+        level: 1,
+        spans: vec![Span::plain(text)],
+    }
+}
+
+fn header_blocks(header: &ExamHeader) -> Vec<Block> {
+    let mut blocks = Vec::new();
+
+    match &header.school {
+        Some(school) => {
+            blocks.push(Block::Title(school.clone()));
+            blocks.push(Block::Subtitle(header.title.clone()));
+        }
+        None => blocks.push(Block::Title(header.title.clone())),
+    }
+
+    let lines = [
+        [("Subject", &header.subject), ("Class", &header.class_name)],
+        [("Duration", &header.duration), ("Date", &header.date)],
+    ];
+    for line in lines {
+        let fields: Vec<String> = line
+            .iter()
+            .filter_map(|(label, value)| {
+                value.as_ref().map(|value| format!("{}: {}", label, value))
+            })
+            .collect();
+        if !fields.is_empty() {
+            blocks.push(Block::Subtitle(fields.join(" \u{b7} ")));
+        }
+    }
+
+    blocks
+}
+
+/// For each variant and question, the original index of the option
+/// printed in each position. Options such as "All of the above" keep their
+/// position, the others are shuffled so that papers B and C differ from
+/// paper A wherever a question has two options to swap.
+fn option_orders(set: &McqSet, seed: u64) -> Vec<Vec<Vec<usize>>> {
+    let mut orders: Vec<Vec<Vec<usize>>> = Vec::new();
+
+    for variant in ExamVariant::ALL {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(variant as u64));
+        let variant_orders = set
+            .questions
+            .iter()
+            .enumerate()
+            .map(|(index, question)| {
+                let taken: Vec<&Vec<usize>> = orders.iter().map(|orders| &orders[index]).collect();
+                shuffled_order(question, &mut rng, &taken)
+            })
+            .collect();
+        orders.push(variant_orders);
+    }
+
+    orders
+}
+
+/// An order of the question's options unlike the `taken` orders of the
+/// earlier variants. When the shuffles keep repeating one, as they may with
+/// few options, the order only has to differ from the first variant's.
+fn shuffled_order(question: &McqQuestion, rng: &mut StdRng, taken: &[&Vec<usize>]) -> Vec<usize> {
+    let free: Vec<usize> = (0..question.options.len())
+        .filter(|&index| !is_positional(&question.options[index]))
+        .collect();
+
+    let arrange = |shuffled: &[usize]| {
+        let mut order: Vec<usize> = (0..question.options.len()).collect();
+        for (&position, &option) in free.iter().zip(shuffled) {
+            order[position] = option;
+        }
+        order
+    };
+
+    let mut order = arrange(&free);
+    for _ in 0..MAX_DRAWS {
+        let mut shuffled = free.clone();
+        shuffled.shuffle(rng);
+        order = arrange(&shuffled);
+        if !taken.contains(&&order) {
+            return order;
+        }
+    }
+
+    match taken.first() {
+        Some(first) if free.len() > 1 => {
+            let mut rotated: Vec<usize> = free.iter().map(|&position| first[position]).collect();
+            rotated.rotate_left(1);
+            arrange(&rotated)
+        }
+        _ => order,
+    }
+}
+
+/// Whether an option refers to the options printed before it.
+fn is_positional(option: &str) -> bool {
+    let option = option.trim().to_lowercase();
+    [
+        "all of the above",
+        "none of the above",
+        "all of these",
+        "none of these",
+    ]
+    .iter()
+    .any(|prefix| option.starts_with(prefix))
+}
+
+fn marks(count: u32) -> String {
+    match count {
+        1 => "1 mark".to_string(),
+        count => format!("{} marks", count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{answer_key, exam_paper, option_orders, ExamHeader};
+    use crate::{
+        models::{
+            generations::{ExamVariant, ExportFormat, ExportQuery},
+            mcq::McqSet,
+        },
+        services::export::{
+            document::{Block, Document},
+            test_support::sample_set,
+        },
+        utils::errors::AppError,
+    };
+
+    fn query(marks_per_question: Option<u32>) -> ExportQuery {
+        ExportQuery {
+            format: ExportFormat::ExamPdf,
+            variant: None,
+            school: None,
+            title: None,
+            subject: None,
+            class_name: None,
+            duration: None,
+            date: None,
+            instructions: None,
+            marks_per_question,
+        }
+    }
+
+    fn header() -> ExamHeader {
+        ExamHeader::new(&query(Some(2)), "Biology").unwrap()
+    }
+
+    /// A set whose last options must stay at the bottom of every paper.
+    fn positional_set() -> McqSet {
+        let mut set = sample_set();
+        set.questions[0]
+            .options
+            .push("None of the above".to_string());
+        set.questions[2].options[4] = "All of the above".to_string();
+        set
+    }
+
+    /// The options of every question, as printed.
+    fn printed_options(paper: &Document) -> Vec<Vec<String>> {
+        paper
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Question { options, .. } => Some(options.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The key's letter for every question, one column per variant.
+    fn key_letters(key: &Document) -> Vec<Vec<String>> {
+        let rows = key
+            .blocks
+            .iter()
+            .find_map(|block| match block {
+                Block::Table { rows, .. } => Some(rows),
+                _ => None,
+            })
+            .unwrap();
+
+        rows.iter()
+            .map(|row| {
+                row[1..=ExamVariant::ALL.len()]
+                    .iter()
+                    .map(|cell| cell[0].text.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_key_letters_point_at_the_correct_options() {
+        let set = positional_set();
+        let letters = key_letters(&answer_key(&set, &header(), 42));
+
+        for (column, variant) in ExamVariant::ALL.into_iter().enumerate() {
+            let printed = printed_options(&exam_paper(&set, &header(), variant, 42));
+            for (index, question) in set.questions.iter().enumerate() {
+                let letter = letters[index][column].chars().next().unwrap();
+                let position = (letter as u8 - b'A') as usize;
+                assert_eq!(
+                    printed[index][position],
+                    question.options[question.correct_index],
+                    "question {} of paper {}",
+                    index + 1,
+                    variant.as_str()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn positional_options_stay_in_place() {
+        let set = positional_set();
+
+        for variant in ExamVariant::ALL {
+            let printed = printed_options(&exam_paper(&set, &header(), variant, 7));
+            assert_eq!(printed[0][4], "None of the above");
+            assert_eq!(printed[2][4], "All of the above");
+        }
+    }
+
+    #[test]
+    fn a_seed_gives_the_same_papers() {
+        let set = sample_set();
+
+        for variant in ExamVariant::ALL {
+            assert_eq!(
+                printed_options(&exam_paper(&set, &header(), variant, 3)),
+                printed_options(&exam_paper(&set, &header(), variant, 3))
+            );
+        }
+        assert_eq!(
+            key_letters(&answer_key(&set, &header(), 3)),
+            key_letters(&answer_key(&set, &header(), 3))
+        );
+    }
+
+    #[test]
+    fn later_variants_differ_from_paper_a() {
+        let mut set = sample_set();
+        // two free options have only two orders, so the draws repeat often
+        set.questions[1].options[2] = "None of the above".to_string();
+
+        for seed in 0..50 {
+            let orders = option_orders(&set, seed);
+            for variant in [ExamVariant::B, ExamVariant::C] {
+                for (index, order) in orders[variant as usize].iter().enumerate() {
+                    assert_ne!(
+                        *order,
+                        orders[0][index],
+                        "question {} of paper {} with seed {}",
+                        index + 1,
+                        variant.as_str(),
+                        seed
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn marks_per_question_is_bounded() {
+        for marks in [0, 101, u32::MAX] {
+            assert!(matches!(
+                ExamHeader::new(&query(Some(marks)), "Biology"),
+                Err(AppError::BadRequest(_))
+            ));
+        }
+        assert_eq!(
+            ExamHeader::new(&query(None), "Biology")
+                .unwrap()
+                .marks_per_question,
+            1
+        );
+        assert_eq!(header().total(&sample_set()), 6);
+    }
+}
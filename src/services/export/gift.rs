@@ -0,0 +1,220 @@
+use crate::models::mcq::McqSet;
+
+/// Renders the questions in Moodle's GIFT format, in a category named after
+/// the title. Difficulty and topic are kept in comments above each question,
+/// the explanation is the general feedback.
+pub fn render(set: &McqSet, title: &str) -> String {
+    let mut gift = format!(
+        "$CATEGORY: $course$/{}\n\n",
+        single_line(title).replace('/', "-")
+    );
+
+    for (index, question) in set.questions.iter().enumerate() {
+        gift.push_str(&format!(
+            "// difficulty: {}\n",
+            question.difficulty.as_str()
+        ));
+        gift.push_str(&format!("// topic: {}\n", single_line(&question.topic)));
+        gift.push_str(&format!(
+            "::Question {}::{}{{\n",
+            index + 1,
+            escape(&question.stem)
+        ));
+
+        for (option_index, option) in question.options.iter().enumerate() {
+            let mark = if option_index == question.correct_index {
+                '='
+            } else {
+                '~'
+            };
+            gift.push_str(&format!("{}{}\n", mark, escape(option)));
+        }
+
+        if !question.explanation.is_empty() {
+            gift.push_str(&format!("####{}\n", escape(&question.explanation)));
+        }
+        gift.push_str("}\n\n");
+    }
+
+    gift
+}
+
+/// Escapes GIFT control characters with a backslash and writes line breaks
+/// as `\n`, so every question keeps its one-line-per-answer layout.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '~' | '=' | '#' | '{' | '}' | ':' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::{
+        models::mcq::{Difficulty, McqQuestion, McqSet},
+        services::export::test_support::sample_set,
+    };
+
+    enum Token {
+        Text(String),
+        Mark(char),
+    }
+
+    /// Splits GIFT into text and unescaped control characters.
+    fn tokenize(gift: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut text = String::new();
+        let mut chars = gift.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some(escaped) => text.push(escaped),
+                    None => {}
+                },
+                '~' | '=' | '#' | '{' | '}' | ':' => {
+                    if !text.is_empty() {
+                        tokens.push(Token::Text(std::mem::take(&mut text)));
+                    }
+                    tokens.push(Token::Mark(c));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+
+        tokens
+    }
+
+    /// Reads the questions back the way Moodle's GIFT importer does.
+    fn read(gift: &str) -> McqSet {
+        let mut questions = Vec::new();
+        let mut difficulty = None;
+        let mut topic = None;
+        let mut body = String::new();
+
+        for line in gift.lines() {
+            if let Some(comment) = line.strip_prefix("// ") {
+                if let Some(value) = comment.strip_prefix("difficulty: ") {
+                    difficulty = Some(value.parse().unwrap());
+                } else if let Some(value) = comment.strip_prefix("topic: ") {
+                    topic = Some(value.to_string());
+                }
+                continue;
+            }
+            if line.starts_with("$CATEGORY:") || (body.is_empty() && line.is_empty()) {
+                continue;
+            }
+
+            body.push_str(line);
+            body.push('\n');
+            if line == "}" {
+                questions.push(read_question(
+                    &body,
+                    difficulty.take().expect("difficulty comment"),
+                    topic.take().expect("topic comment"),
+                ));
+                body.clear();
+            }
+        }
+
+        McqSet { questions }
+    }
+
+    fn read_question(body: &str, difficulty: Difficulty, topic: String) -> McqQuestion {
+        let tokens = tokenize(body);
+        let mut question = McqQuestion {
+            stem: String::new(),
+            options: Vec::new(),
+            correct_index: usize::MAX,
+            explanation: String::new(),
+            difficulty,
+            topic,
+            sources: Vec::new(),
+        };
+
+        // ::title::stem{ answers }
+        let mut colons = 0;
+        let mut hashes = 0;
+        let mut in_answers = false;
+        let mut current: Option<char> = None;
+
+        for token in tokens {
+            match token {
+                Token::Mark(':') if !in_answers => colons += 1,
+                Token::Mark('{') => in_answers = true,
+                Token::Mark('}') => break,
+                Token::Mark('#') => {
+                    hashes += 1;
+                    current = Some('#');
+                }
+                Token::Mark(mark) => {
+                    if mark == '=' {
+                        question.correct_index = question.options.len();
+                    }
+                    question.options.push(String::new());
+                    current = Some(mark);
+                }
+                Token::Text(text) if !in_answers => {
+                    if colons == 4 {
+                        question.stem.push_str(&text);
+                    }
+                }
+                Token::Text(text) => match current {
+                    Some('#') if hashes == 4 => question.explanation.push_str(&text),
+                    Some('=' | '~') => question.options.last_mut().unwrap().push_str(&text),
+                    _ => {}
+                },
+            }
+        }
+
+        // each answer and the feedback end with the line break after them
+        for option in &mut question.options {
+            option.pop();
+        }
+        question.explanation.pop();
+
+        question
+    }
+
+    #[test]
+    fn round_trips_the_sample_set() {
+        let set = sample_set();
+
+        assert_eq!(read(&render(&set, "Organs")), set);
+    }
+
+    #[test]
+    fn leaves_citation_sources_out() {
+        let mut cited = sample_set();
+        cited.questions[0].sources = vec!["S1".to_string()];
+
+        assert_eq!(render(&cited, "Organs"), render(&sample_set(), "Organs"));
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        let gift = render(&sample_set(), "Organs");
+
+        assert!(gift.contains("\\{x, y\\} \\= \\{3, 4\\}"));
+        assert!(gift.contains("\\#2\\: it's correct"));
+        assert!(gift.contains("3 < 5 and 4 > 2.\\nBoth"));
+    }
+}
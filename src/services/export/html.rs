@@ -0,0 +1,234 @@
+use super::{
+    document::{Block, Document, ListItem, Span},
+    escape_xml,
+};
+
+const STYLE: &str = "\
+body { font-family: Georgia, 'Times New Roman', serif; line-height: 1.6; color: #222; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; }
+h1, h2, h3, h4, h5, h6 { font-family: Helvetica, Arial, sans-serif; line-height: 1.3; }
+.title { text-align: center; }
+.subtitle { text-align: center; margin: 0.25rem 0; }
+table { border-collapse: collapse; margin: 1rem 0; }
+th, td { border: 1px solid #999; padding: 0.35rem 0.6rem; text-align: left; vertical-align: top; }
+th { background: #f0f0f0; }
+blockquote { border-left: 4px solid #ccc; margin: 1rem 0; padding: 0 1rem; color: #555; }
+pre { background: #f6f6f6; padding: 0.75rem; overflow-x: auto; }
+code { font-family: Menlo, Consolas, monospace; font-size: 0.9em; }
+ol.options { list-style-type: upper-alpha; }
+@media print { body { margin: 0; max-width: none; } }";
+
+/// Renders `document` as a standalone HTML page with its styles inline, so
+/// it can be opened offline or printed from a browser.
+pub fn render(document: &Document) -> String {
+    let mut body = String::new();
+
+    for block in &document.blocks {
+        match block {
+            Block::Title(text) => {
+                body.push_str(&format!("<h1 class=\"title\">{}</h1>\n", escape_xml(text)))
+            }
+            Block::Subtitle(text) => {
+                body.push_str(&format!("<p class=\"subtitle\">{}</p>\n", escape_xml(text)))
+            }
+            Block::Heading { level, spans } => {
+                let level = (*level).clamp(1, 6);
+                body.push_str(&format!("<h{}>{}</h{}>\n", level, spans_html(spans), level));
+            }
+            Block::Paragraph(spans) => body.push_str(&format!("<p>{}</p>\n", spans_html(spans))),
+            Block::List(items) => body.push_str(&list(items)),
+            Block::Quote(spans) => body.push_str(&format!(
+                "<blockquote><p>{}</p></blockquote>\n",
+                spans_html(spans)
+            )),
+            Block::Code(code) => {
+                body.push_str(&format!("<pre><code>{}</code></pre>\n", escape_xml(code)))
+            }
+            Block::Question {
+                number,
+                stem,
+                options,
+            } => {
+                body.push_str(&format!(
+                    "<p><strong>{}.</strong> {}</p>\n<ol class=\"options\">\n",
+                    number,
+                    escape_xml(stem)
+                ));
+                for option in options {
+                    body.push_str(&format!("<li>{}</li>\n", escape_xml(option)));
+                }
+                body.push_str("</ol>\n");
+            }
+            Block::Table { header, rows } => body.push_str(&table(header, rows)),
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_xml(&document.title),
+        STYLE,
+        body
+    )
+}
+
+/// Nested `<ul>` and `<ol>` elements, each nested list inside the item it
+/// belongs to.
+fn list(items: &[ListItem]) -> String {
+    let mut html = String::new();
+    // the lists currently open, innermost last
+    let mut open: Vec<&str> = Vec::new();
+
+    for item in items {
+        let tag = if item.ordered { "ol" } else { "ul" };
+
+        while open.len() > item.level + 1 {
+            html.push_str(&format!("</li>\n</{}>\n", open.pop().unwrap_or_default()));
+        }
+        if open.len() == item.level + 1 {
+            html.push_str("</li>\n");
+            if open.last() != Some(&tag) {
+                html.push_str(&format!("</{}>\n", open.pop().unwrap_or_default()));
+            }
+        }
+        while open.len() < item.level + 1 {
+            html.push_str(&format!("<{}>\n", tag));
+            open.push(tag);
+        }
+
+        html.push_str(&format!("<li>{}", spans_html(&item.spans)));
+    }
+
+    while let Some(tag) = open.pop() {
+        html.push_str(&format!("</li>\n</{}>\n", tag));
+    }
+
+    html
+}
+
+fn table(header: &[Vec<Span>], rows: &[Vec<Vec<Span>>]) -> String {
+    let mut html = String::from("<table>\n<thead>\n<tr>");
+    for cell in header {
+        html.push_str(&format!("<th>{}</th>", spans_html(cell)));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", spans_html(cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+fn spans_html(spans: &[Span]) -> String {
+    let mut html = String::new();
+
+    for span in spans {
+        let mut text = escape_xml(&span.text).replace('\n', "<br>\n");
+        if span.code {
+            text = format!("<code>{}</code>", text);
+        }
+        if span.italic {
+            text = format!("<em>{}</em>", text);
+        }
+        if span.bold {
+            text = format!("<strong>{}</strong>", text);
+        }
+        if let Some(url) = span.link.as_deref().filter(|url| is_safe_url(url)) {
+            text = format!("<a href=\"{}\">{}</a>", escape_xml(url), text);
+        }
+        html.push_str(&text);
+    }
+
+    html
+}
+
+/// Links from generated content must not run script when clicked.
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    ["http://", "https://", "mailto:", "#", "/"]
+        .iter()
+        .any(|prefix| url.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::services::export::document::{Block, Document, ListItem, Span};
+
+    fn page(blocks: Vec<Block>) -> String {
+        render(&Document {
+            title: "Water".to_string(),
+            blocks,
+        })
+    }
+
+    fn item(level: usize, ordered: bool, text: &str) -> ListItem {
+        ListItem {
+            level,
+            ordered,
+            spans: vec![Span::plain(text)],
+        }
+    }
+
+    fn link(text: &str, url: &str) -> Span {
+        Span {
+            link: Some(url.to_string()),
+            ..Span::plain(text)
+        }
+    }
+
+    #[test]
+    fn nested_lists_sit_inside_their_item() {
+        let html = page(vec![Block::List(vec![
+            item(0, false, "Evaporation"),
+            item(1, true, "Heat"),
+            item(1, true, "Wind"),
+            item(2, false, "Dry air"),
+            item(0, false, "Condensation"),
+        ])]);
+
+        assert!(html.contains(
+            "<ul>\n<li>Evaporation<ol>\n<li>Heat</li>\n<li>Wind<ul>\n<li>Dry air</li>\n</ul>\n\
+             </li>\n</ol>\n</li>\n<li>Condensation</li>\n</ul>\n"
+        ));
+    }
+
+    #[test]
+    fn a_list_of_another_kind_starts_a_new_list() {
+        let html = page(vec![Block::List(vec![
+            item(0, true, "First"),
+            item(0, false, "Loose"),
+        ])]);
+
+        assert!(html.contains("<ol>\n<li>First</li>\n</ol>\n<ul>\n<li>Loose</li>\n</ul>\n"));
+    }
+
+    #[test]
+    fn script_links_are_dropped() {
+        let html = page(vec![Block::Paragraph(vec![
+            link("USGS", "https://www.usgs.gov/?a=1&b=2"),
+            link(" click", "javascript:alert(1)"),
+            link(" here", "  JavaScript:alert(1)"),
+            link(" data", "data:text/html,<script>alert(1)</script>"),
+        ])]);
+
+        assert!(html.contains("<a href=\"https://www.usgs.gov/?a=1&amp;b=2\">USGS</a>"));
+        assert!(html.contains(" click here data"));
+        assert!(!html.to_lowercase().contains("javascript:"));
+        assert!(!html.contains("data:"));
+        assert_eq!(html.matches("<a ").count(), 1);
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let html = page(vec![Block::Paragraph(vec![Span::plain("<b>x</b> & y")])]);
+
+        assert!(html.contains("<p>&lt;b&gt;x&lt;/b&gt; &amp; y</p>"));
+    }
+}
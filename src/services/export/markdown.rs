@@ -0,0 +1,697 @@
+use super::{
+    document::{list_numbers, Block, Document, ListItem, Span},
+    option_letter,
+};
+
+/// Reads the markdown the LLM writes into blocks: ATX and setext headings,
+/// paragraphs, nested bullet and numbered lists, pipe tables, quotes and
+/// fenced code, with bold, italic, inline code and links inside them.
+/// Horizontal rules are dropped; anything else is kept as text.
+pub fn parse(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut parser = Parser::default();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim();
+        index += 1;
+
+        if trimmed.is_empty() {
+            parser.flush_paragraph();
+            parser.blank = true;
+            continue;
+        }
+
+        if let Some(fence) = fence(trimmed) {
+            parser.flush();
+            let mut code = Vec::new();
+            while index < lines.len() && !lines[index].trim().starts_with(fence) {
+                code.push(lines[index]);
+                index += 1;
+            }
+            index += 1;
+            parser.blocks.push(Block::Code(code.join("\n")));
+            continue;
+        }
+
+        if let Some((level, heading)) = atx_heading(trimmed) {
+            parser.flush();
+            parser.blocks.push(Block::Heading {
+                level,
+                spans: inline(heading),
+            });
+            continue;
+        }
+
+        if !parser.paragraph.is_empty() && is_setext_underline(trimmed) {
+            let level = if trimmed.starts_with('=') { 1 } else { 2 };
+            let spans = inline(&parser.paragraph.join(" "));
+            parser.paragraph.clear();
+            parser.flush();
+            parser.blocks.push(Block::Heading { level, spans });
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            parser.flush();
+            continue;
+        }
+
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            if parser.quote.is_none() {
+                parser.flush();
+            }
+            parser.quote.get_or_insert_with(Vec::new).push(quote.trim());
+            continue;
+        }
+
+        if trimmed.contains('|') && index < lines.len() && is_table_separator(lines[index]) {
+            parser.flush();
+            let header = cells(trimmed);
+            index += 1;
+
+            let mut rows = Vec::new();
+            while index < lines.len() && lines[index].contains('|') {
+                let mut row = cells(lines[index].trim());
+                row.resize(header.len(), String::new());
+                rows.push(row);
+                index += 1;
+            }
+
+            parser.blocks.push(Block::Table {
+                header: header.iter().map(|cell| inline(cell)).collect(),
+                rows: rows
+                    .iter()
+                    .map(|row| row.iter().map(|cell| inline(cell)).collect())
+                    .collect(),
+            });
+            continue;
+        }
+
+        if let Some((ordered, item)) = list_item(trimmed) {
+            parser.flush_paragraph();
+            parser.flush_quote();
+            parser.list_item(indent(line), ordered, item);
+            continue;
+        }
+
+        // text right under a list item, or indented under it after a blank
+        // line, continues the item
+        if let Some(last) = parser.items.last_mut() {
+            if !parser.blank || indent(line) > 0 {
+                last.2.push(' ');
+                last.2.push_str(trimmed);
+                parser.blank = false;
+                continue;
+            }
+        }
+
+        parser.flush_list();
+        parser.flush_quote();
+        parser.paragraph.push(trimmed);
+        parser.blank = false;
+    }
+
+    parser.flush();
+    parser.blocks
+}
+
+#[derive(Default)]
+struct Parser<'a> {
+    blocks: Vec<Block>,
+    paragraph: Vec<&'a str>,
+    quote: Option<Vec<&'a str>>,
+    /// Open list items as level, ordered and text.
+    items: Vec<(usize, bool, String)>,
+    /// Indents of the list markers of the open nesting levels.
+    indents: Vec<usize>,
+    /// Whether the previous line was blank.
+    blank: bool,
+}
+
+impl Parser<'_> {
+    fn list_item(&mut self, indent: usize, ordered: bool, text: &str) {
+        while self.indents.last().is_some_and(|&top| indent < top) {
+            self.indents.pop();
+        }
+        if self.indents.last() != Some(&indent) {
+            self.indents.push(indent);
+        }
+
+        self.items
+            .push((self.indents.len() - 1, ordered, text.to_string()));
+        self.blank = false;
+    }
+
+    fn flush(&mut self) {
+        self.flush_paragraph();
+        self.flush_quote();
+        self.flush_list();
+    }
+
+    fn flush_paragraph(&mut self) {
+        if !self.paragraph.is_empty() {
+            let text = self.paragraph.join(" ");
+            self.blocks.push(Block::Paragraph(inline(&text)));
+            self.paragraph.clear();
+        }
+    }
+
+    fn flush_quote(&mut self) {
+        if let Some(quote) = self.quote.take() {
+            self.blocks.push(Block::Quote(inline(&quote.join(" "))));
+        }
+    }
+
+    fn flush_list(&mut self) {
+        if !self.items.is_empty() {
+            let items = self
+                .items
+                .drain(..)
+                .map(|(level, ordered, text)| ListItem {
+                    level,
+                    ordered,
+                    spans: inline(&text),
+                })
+                .collect();
+            self.blocks.push(Block::List(items));
+            self.indents.clear();
+        }
+    }
+}
+
+/// Leading whitespace in columns, a tab counting as four.
+fn indent(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn fence(line: &str) -> Option<&'static str> {
+    ["```", "~~~"]
+        .into_iter()
+        .find(|fence| line.starts_with(fence))
+}
+
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_setext_underline(line: &str) -> bool {
+    line.len() >= 2 && (line.chars().all(|c| c == '=') || line.chars().all(|c| c == '-'))
+}
+
+/// `---`, `***` or `___`, possibly spaced out.
+fn is_rule(line: &str) -> bool {
+    ['-', '*', '_'].into_iter().any(|marker| {
+        line.chars().filter(|&c| c == marker).count() >= 3
+            && line.chars().all(|c| c == marker || c == ' ')
+    })
+}
+
+/// A `-`, `*` or `+` bullet, or a number followed by `.` or `)`.
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some((false, item.trim()));
+        }
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = &line[digits..];
+    let item = rest
+        .strip_prefix(". ")
+        .or_else(|| rest.strip_prefix(") "))?;
+
+    Some((true, item.trim()))
+}
+
+/// The line under a table header, e.g. `|---|:---:|`.
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('|')
+        && line
+            .trim_matches('|')
+            .split('|')
+            .map(str::trim)
+            .all(|cell| {
+                cell.contains('-') && cell.chars().all(|c| c == '-' || c == ':' || c == ' ')
+            })
+}
+
+/// The cells of a table row. `\|` is a pipe inside a cell.
+fn cells(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => line,
+    };
+
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Splits text into spans at `**bold**`, `*italic*`, `` `code` `` and
+/// `[links](url)`. Markers without a partner are kept as text, so `5 * 3`
+/// or a citation like `[S1]` come through unchanged.
+pub fn inline(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut current = String::new();
+    let mut bold = false;
+    let mut italic = false;
+    let mut index = 0;
+
+    let flush = |spans: &mut Vec<Span>, current: &mut String, bold: bool, italic: bool| {
+        if !current.is_empty() {
+            spans.push(Span {
+                text: std::mem::take(current),
+                bold,
+                italic,
+                ..Span::default()
+            });
+        }
+    };
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        match c {
+            '\\' if chars.get(index + 1).is_some_and(char::is_ascii_punctuation) => {
+                current.push(chars[index + 1]);
+                index += 2;
+                continue;
+            }
+            '`' => {
+                if let Some(close) = find(&chars, index + 1, &['`']) {
+                    flush(&mut spans, &mut current, bold, italic);
+                    spans.push(Span {
+                        text: chars[index + 1..close].iter().collect(),
+                        code: true,
+                        ..Span::default()
+                    });
+                    index = close + 1;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((label, url, end)) = link(&chars, index) {
+                    flush(&mut spans, &mut current, bold, italic);
+                    spans.push(Span {
+                        text: label,
+                        bold,
+                        italic,
+                        link: Some(url),
+                        ..Span::default()
+                    });
+                    index = end;
+                    continue;
+                }
+            }
+            '*' | '_' => {
+                let run = chars[index..].iter().take_while(|&&d| d == c).count();
+                let width = run.min(3);
+                let marker = vec![c; width];
+                let before = index.checked_sub(1).map(|i| chars[i]);
+                let after = chars.get(index + run).copied();
+
+                // one marker is italic, two bold, three both
+                let (wants_italic, wants_bold) = (width != 2, width >= 2);
+
+                let closes = before.is_some_and(|b| !b.is_whitespace())
+                    && ((wants_italic && italic) || (wants_bold && bold));
+                let opens = !closes
+                    && after.is_some_and(|a| !a.is_whitespace())
+                    && !(c == '_' && before.is_some_and(char::is_alphanumeric))
+                    && find(&chars, index + run, &marker).is_some();
+
+                if closes || opens {
+                    flush(&mut spans, &mut current, bold, italic);
+                    italic = if wants_italic { opens } else { italic };
+                    bold = if wants_bold { opens } else { bold };
+                    current.extend(std::iter::repeat_n(c, run - width));
+                    index += run;
+                    continue;
+                }
+
+                current.extend(std::iter::repeat_n(c, run));
+                index += run;
+                continue;
+            }
+            _ => {}
+        }
+
+        current.push(c);
+        index += 1;
+    }
+
+    flush(&mut spans, &mut current, bold, italic);
+    spans
+}
+
+/// Index of the first occurrence of `pattern` at or after `from`.
+fn find(chars: &[char], from: usize, pattern: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&start| chars[start..].starts_with(pattern))
+}
+
+/// `[label](url)` starting at `start`: the label, url and the index after.
+fn link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let close = find(chars, start + 1, &[']'])?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    // the address may contain balanced parentheses
+    let mut depth = 0;
+    let end = (close + 2..chars.len()).find(|&index| match chars[index] {
+        '(' => {
+            depth += 1;
+            false
+        }
+        ')' if depth == 0 => true,
+        ')' => {
+            depth -= 1;
+            false
+        }
+        _ => false,
+    })?;
+
+    let label: String = chars[start + 1..close].iter().collect();
+    let url: String = chars[close + 2..end].iter().collect();
+    if label.is_empty() || url.trim().is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((label, url, end + 1))
+}
+
+/// Writes `document` as tidy markdown: ATX headings, `-` bullets, pipe
+/// tables and one blank line between blocks.
+pub fn render(document: &Document) -> String {
+    let mut blocks = Vec::new();
+
+    for block in &document.blocks {
+        let text = match block {
+            Block::Title(text) => format!("# {}", escape(text)),
+            Block::Subtitle(text) => format!("*{}*", escape(text)),
+            Block::Heading { level, spans } => {
+                format!("{} {}", "#".repeat((*level).clamp(1, 6)), spans_text(spans))
+            }
+            Block::Paragraph(spans) => spans_text(spans),
+            Block::List(items) => list(items),
+            Block::Quote(spans) => format!("> {}", spans_text(spans)),
+            Block::Code(code) => {
+                let fence = if code.contains("```") { "~~~" } else { "```" };
+                format!("{}\n{}\n{}", fence, code, fence)
+            }
+            Block::Question {
+                number,
+                stem,
+                options,
+            } => {
+                let mut text = format!("{}. {}", number, escape(stem));
+                for (index, option) in options.iter().enumerate() {
+                    text.push_str(&format!(
+                        "\n   {}. {}",
+                        option_letter(index),
+                        escape(option)
+                    ));
+                }
+                text
+            }
+            Block::Table { header, rows } => table(header, rows),
+        };
+        blocks.push(text);
+    }
+
+    let mut markdown = blocks.join("\n\n");
+    markdown.push('\n');
+    markdown
+}
+
+fn list(items: &[ListItem]) -> String {
+    let numbers = list_numbers(items);
+    // indent of each nesting level: under the text of its parent item
+    let mut indents: Vec<usize> = vec![0];
+    let mut lines = Vec::new();
+
+    for (item, number) in items.iter().zip(numbers) {
+        indents.truncate(item.level + 1);
+        let indent = indents[item.level];
+        let marker = match item.ordered {
+            true => format!("{}.", number),
+            false => "-".to_string(),
+        };
+
+        lines.push(format!(
+            "{}{} {}",
+            " ".repeat(indent),
+            marker,
+            spans_text(&item.spans)
+        ));
+        indents.push(indent + marker.len() + 1);
+    }
+
+    lines.join("\n")
+}
+
+fn table(header: &[Vec<Span>], rows: &[Vec<Vec<Span>>]) -> String {
+    let cell = |spans: &Vec<Span>| spans_text(spans).replace('|', "\\|");
+    let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+
+    let mut lines = vec![
+        row(header.iter().map(cell).collect()),
+        row(header.iter().map(|_| "---".to_string()).collect()),
+    ];
+    lines.extend(
+        rows.iter()
+            .map(|cells| row(cells.iter().map(cell).collect())),
+    );
+
+    lines.join("\n")
+}
+
+/// Styled text with the markers of each span. Spans of the same style are
+/// merged first and whitespace is kept outside the markers, which markdown
+/// requires.
+fn spans_text(spans: &[Span]) -> String {
+    let mut merged: Vec<Span> = Vec::new();
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if last.same_style(span) => last.text.push_str(&span.text),
+            _ => merged.push(span.clone()),
+        }
+    }
+
+    let mut text = String::new();
+    for span in merged {
+        let content = span.text.trim();
+        if content.is_empty() {
+            text.push_str(&span.text);
+            continue;
+        }
+
+        let leading = &span.text[..span.text.len() - span.text.trim_start().len()];
+        let trailing = &span.text[span.text.trim_end().len()..];
+
+        let mut styled = match span.code {
+            true => {
+                let ticks = if content.contains('`') { "``" } else { "`" };
+                format!("{}{}{}", ticks, content, ticks)
+            }
+            false => escape(content),
+        };
+        if span.italic {
+            styled = format!("*{}*", styled);
+        }
+        if span.bold {
+            styled = format!("**{}**", styled);
+        }
+        if let Some(url) = &span.link {
+            styled = format!("[{}]({})", styled, url);
+        }
+
+        text.push_str(leading);
+        text.push_str(&styled);
+        text.push_str(trailing);
+    }
+
+    text
+}
+
+/// Escapes the characters that would otherwise start markup.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inline, parse, render};
+    use crate::services::export::document::{Block, Document, Span};
+
+    const CONTENT: &str = "# The Water Cycle
+
+The **water cycle** moves water between the *oceans* and the land [S1].
+It runs on the sun's energy, so 5 * 3 stays as written.
+
+## Stages
+
+1. **Evaporation**: water turns into vapour.
+   - Faster when it is *hot*.
+   - Also from plants.
+2. **Condensation**: vapour cools
+   and forms clouds.
+
+3. **Precipitation**: rain or snow.
+
+| Term | Meaning |
+|------|:--------|
+| Runoff | Water over **land** \\| soil |
+
+> Water is only moved.
+
+```
+cycle = [\"evaporate\"]
+```
+
+See [USGS](https://www.usgs.gov/(water)) and `H2O`.
+";
+
+    fn document(text: &str) -> Document {
+        Document {
+            title: "The Water Cycle".to_string(),
+            blocks: parse(text),
+        }
+    }
+
+    #[test]
+    fn parses_structure() {
+        let blocks = parse(CONTENT);
+
+        let kinds: Vec<&str> = blocks
+            .iter()
+            .map(|block| match block {
+                Block::Heading { .. } => "heading",
+                Block::Paragraph(_) => "paragraph",
+                Block::List(_) => "list",
+                Block::Table { .. } => "table",
+                Block::Quote(_) => "quote",
+                Block::Code(_) => "code",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "heading",
+                "paragraph",
+                "heading",
+                "list",
+                "table",
+                "quote",
+                "code",
+                "paragraph"
+            ]
+        );
+
+        let Block::List(items) = &blocks[3] else {
+            panic!("expected a list");
+        };
+        let outline: Vec<(usize, bool)> = items
+            .iter()
+            .map(|item| (item.level, item.ordered))
+            .collect();
+        assert_eq!(
+            outline,
+            [(0, true), (1, false), (1, false), (0, true), (0, true)]
+        );
+        assert_eq!(
+            items[3].spans.last().unwrap().text,
+            ": vapour cools and forms clouds."
+        );
+
+        let Block::Table { header, rows } = &blocks[4] else {
+            panic!("expected a table");
+        };
+        assert_eq!(header.len(), 2);
+        assert_eq!(
+            rows[0][1],
+            [
+                Span::plain("Water over "),
+                Span::bold("land"),
+                Span::plain(" | soil")
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_inline_styles() {
+        assert_eq!(
+            inline("a **b** *c* `d*` [e](https://x.org/(f)) 5 * 3 snake_case [S1]"),
+            [
+                Span::plain("a "),
+                Span::bold("b"),
+                Span::plain(" "),
+                Span {
+                    italic: true,
+                    ..Span::plain("c")
+                },
+                Span::plain(" "),
+                Span {
+                    code: true,
+                    ..Span::plain("d*")
+                },
+                Span::plain(" "),
+                Span {
+                    link: Some("https://x.org/(f)".to_string()),
+                    ..Span::plain("e")
+                },
+                Span::plain(" 5 * 3 snake_case [S1]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rendered_markdown_reads_back_the_same() {
+        let first = render(&document(CONTENT));
+        let second = render(&document(&first));
+
+        assert_eq!(first, second);
+        assert!(first.contains("1. **Evaporation**: water turns into vapour.\n   - Faster"));
+        assert!(first.contains("| Runoff | Water over **land** \\| soil |"));
+        assert!(first.contains("so 5 \\* 3 stays"));
+    }
+}
@@ -0,0 +1,356 @@
+pub mod answer_sheet;
+pub mod content;
+pub mod document;
+pub mod docx;
+pub mod exam_paper;
+pub mod gift;
+pub mod html;
+pub mod markdown;
+pub mod moodle_xml;
+pub mod pdf;
+pub mod qti;
+
+use crate::{
+    models::{
+        generation_jobs::GenerationKind,
+        generations::{ExamVariant, ExportFormat, ExportQuery, Model as GenerationModel},
+        mcq::McqSet,
+    },
+    utils::errors::AppError,
+};
+use content::content_document;
+use document::Document;
+use exam_paper::{answer_key, exam_paper, ExamHeader};
+
+const DOCX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+/// A rendered generation, ready to download.
+pub struct ExportedFile {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Renders a stored generation in the format the query asks for.
+pub fn export_generation(
+    generation: &GenerationModel,
+    query: &ExportQuery,
+) -> Result<ExportedFile, AppError> {
+    let format = query.format;
+    let title = generation_title(generation);
+    let stem = file_stem(&title);
+    // exam variants shuffle the same way on every download of a generation
+    let seed = generation.id.as_u64_pair().0;
+    let variant = query.variant.unwrap_or(ExamVariant::A);
+
+    let file = match format {
+        ExportFormat::MoodleXml => ExportedFile {
+            file_name: format!("{}.xml", stem),
+            content_type: "application/xml",
+            bytes: moodle_xml::render(&mcq_set(generation, format)?, &title).into_bytes(),
+        },
+        ExportFormat::Gift => ExportedFile {
+            file_name: format!("{}.gift.txt", stem),
+            content_type: "text/plain; charset=utf-8",
+            bytes: gift::render(&mcq_set(generation, format)?, &title).into_bytes(),
+        },
+        ExportFormat::Qti => ExportedFile {
+            file_name: format!("{}-qti.zip", stem),
+            content_type: "application/zip",
+            bytes: qti::render(&mcq_set(generation, format)?)?,
+        },
+        ExportFormat::Csv => ExportedFile {
+            file_name: format!("{}.csv", stem),
+            content_type: "text/csv; charset=utf-8",
+            bytes: answer_sheet::render(&mcq_set(generation, format)?)?,
+        },
+        ExportFormat::ExamDocx | ExportFormat::ExamPdf => {
+            let set = mcq_set(generation, format)?;
+            let paper = exam_paper(&set, &ExamHeader::new(query, &title)?, variant, seed);
+            let file_name = format!("{}-paper-{}", stem, variant.as_str().to_lowercase());
+            printable(&paper, file_name, format == ExportFormat::ExamPdf)?
+        }
+        ExportFormat::AnswerKeyDocx | ExportFormat::AnswerKeyPdf => {
+            let set = mcq_set(generation, format)?;
+            let key = answer_key(&set, &ExamHeader::new(query, &title)?, seed);
+            let file_name = format!("{}-answer-key", stem);
+            printable(&key, file_name, format == ExportFormat::AnswerKeyPdf)?
+        }
+        ExportFormat::Docx => ExportedFile {
+            file_name: format!("{}.docx", stem),
+            content_type: DOCX_CONTENT_TYPE,
+            bytes: docx::render(&academic_content(generation, format, &title)?)?,
+        },
+        ExportFormat::Html => ExportedFile {
+            file_name: format!("{}.html", stem),
+            content_type: "text/html; charset=utf-8",
+            bytes: html::render(&academic_content(generation, format, &title)?).into_bytes(),
+        },
+        ExportFormat::Markdown => ExportedFile {
+            file_name: format!("{}.md", stem),
+            content_type: "text/markdown; charset=utf-8",
+            bytes: markdown::render(&academic_content(generation, format, &title)?).into_bytes(),
+        },
+    };
+
+    Ok(file)
+}
+
+/// A document as a PDF, or else as a Word file.
+fn printable(
+    document: &Document,
+    file_name: String,
+    as_pdf: bool,
+) -> Result<ExportedFile, AppError> {
+    let file = match as_pdf {
+        true => ExportedFile {
+            file_name: format!("{}.pdf", file_name),
+            content_type: "application/pdf",
+            bytes: pdf::render(document)?,
+        },
+        false => ExportedFile {
+            file_name: format!("{}.docx", file_name),
+            content_type: DOCX_CONTENT_TYPE,
+            bytes: docx::render(document)?,
+        },
+    };
+
+    Ok(file)
+}
+
+fn academic_content(
+    generation: &GenerationModel,
+    format: ExportFormat,
+    title: &str,
+) -> Result<Document, AppError> {
+    if generation.kind != GenerationKind::AcademicContent {
+        return Err(AppError::BadRequest(format!(
+            "Only academic content generations can be exported as {}",
+            format.as_str()
+        )));
+    }
+
+    Ok(content_document(generation, title))
+}
+
+/// The questions of an MCQ generation. Generations stored before questions
+/// were structured only have free text and cannot be exported.
+fn mcq_set(generation: &GenerationModel, format: ExportFormat) -> Result<McqSet, AppError> {
+    if generation.kind != GenerationKind::Mcq {
+        return Err(AppError::BadRequest(format!(
+            "Only MCQ generations can be exported as {}",
+            format.as_str()
+        )));
+    }
+
+    McqSet::parse(&generation.content, None).map_err(|_| {
+        AppError::BadRequest(
+            "This generation has no structured questions; regenerate it to export it".to_string(),
+        )
+    })
+}
+
+/// The topic the generation was requested for.
+fn generation_title(generation: &GenerationModel) -> String {
+    generation
+        .request
+        .get("topic")
+        .and_then(|topic| topic.as_str())
+        .map(str::trim)
+        .filter(|topic| !topic.is_empty())
+        .unwrap_or("Generation")
+        .to_string()
+}
+
+/// A file name made of the title's ASCII letters and digits, so it fits in
+/// a header, e.g. `functions-of-human-organs`.
+fn file_stem(title: &str) -> String {
+    let mut stem = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+    }
+
+    let stem = stem.chars().take(60).collect::<String>();
+    match stem.trim_end_matches('-') {
+        "" => "generation".to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+/// Escapes text for XML element content and attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Letter of the option at `index`: `A`, `B`, ...
+fn option_letter(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use quick_xml::{
+        events::{BytesStart, Event},
+        Reader,
+    };
+    use std::collections::HashMap;
+
+    use crate::models::mcq::{Difficulty, McqQuestion, McqSet};
+
+    /// Questions with the characters each format has to escape: markup,
+    /// GIFT control characters, CSV separators and quotes, newlines and
+    /// non-ASCII text.
+    pub fn sample_set() -> McqSet {
+        McqSet {
+            questions: vec![
+                McqQuestion {
+                    stem: "What is the main function of the kidneys in the human body?".to_string(),
+                    options: vec![
+                        "To digest food".to_string(),
+                        "To pump blood".to_string(),
+                        "To remove waste and excess water".to_string(),
+                        "To produce insulin".to_string(),
+                    ],
+                    correct_index: 2,
+                    explanation: "The kidneys filter the blood to remove waste and excess water, \
+                                  forming urine."
+                        .to_string(),
+                    difficulty: Difficulty::Easy,
+                    topic: "Functions of Human Organs".to_string(),
+                    sources: Vec::new(),
+                },
+                McqQuestion {
+                    stem: "Which statement about x < 5 & y > 2 is \"true\" when x = 3, y = 4?"
+                        .to_string(),
+                    options: vec![
+                        "Only x < 5 holds".to_string(),
+                        "Both hold: {x, y} = {3, 4}".to_string(),
+                        "Neither holds ~ check again".to_string(),
+                    ],
+                    correct_index: 1,
+                    explanation: "3 < 5 and 4 > 2.\nBoth conditions are met, so the answer is \
+                                  #2: it's correct."
+                        .to_string(),
+                    difficulty: Difficulty::Medium,
+                    topic: "Inequalities: x & y".to_string(),
+                    sources: Vec::new(),
+                },
+                McqQuestion {
+                    stem: "Ọ̀ná wo ni ó tọ́ sí Èkó? (Lagos, \"Centre of Excellence\")".to_string(),
+                    options: vec![
+                        "Ikeja, Lagos".to_string(),
+                        "Ibadan; Oyo".to_string(),
+                        "Abuja".to_string(),
+                        "Kano".to_string(),
+                        "C:\\Path\\to\\nowhere".to_string(),
+                    ],
+                    correct_index: 0,
+                    explanation: String::new(),
+                    difficulty: Difficulty::Hard,
+                    topic: "Nigerian Geography".to_string(),
+                    sources: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    /// An XML element with its attributes, children and text, enough to
+    /// read back what the exporters write.
+    #[derive(Debug, Default)]
+    pub struct Element {
+        pub name: String,
+        pub attributes: HashMap<String, String>,
+        pub children: Vec<Element>,
+        pub text: String,
+    }
+
+    impl Element {
+        pub fn attribute(&self, name: &str) -> Option<&str> {
+            self.attributes.get(name).map(String::as_str)
+        }
+
+        pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+            self.children.iter().filter(move |child| child.name == name)
+        }
+
+        /// The first descendant along `path`, matching local names.
+        pub fn find(&self, path: &[&str]) -> Option<&Element> {
+            let mut element = self;
+            for name in path {
+                element = element.children.iter().find(|child| child.name == *name)?;
+            }
+            Some(element)
+        }
+
+        pub fn text_at(&self, path: &[&str]) -> String {
+            self.find(path)
+                .map(|element| element.text.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    /// Parses a document into its root element, dropping namespace prefixes.
+    pub fn parse_xml(xml: &str) -> Element {
+        let mut reader = Reader::from_str(xml);
+        let mut stack = vec![Element::default()];
+
+        loop {
+            match reader.read_event().expect("well-formed XML") {
+                Event::Start(start) => stack.push(element(&start)),
+                Event::Empty(start) => {
+                    let element = element(&start);
+                    stack.last_mut().unwrap().children.push(element);
+                }
+                Event::End(_) => {
+                    let element = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(element);
+                }
+                Event::Text(text) => stack
+                    .last_mut()
+                    .unwrap()
+                    .text
+                    .push_str(&text.unescape().expect("valid escapes")),
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        stack.pop().unwrap().children.remove(0)
+    }
+
+    fn element(start: &BytesStart) -> Element {
+        Element {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+            attributes: start
+                .attributes()
+                .map(|attribute| {
+                    let attribute = attribute.expect("valid attribute");
+                    (
+                        String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                        attribute
+                            .unescape_value()
+                            .expect("valid escapes")
+                            .into_owned(),
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use super::escape_xml;
+use crate::models::mcq::McqSet;
+
+/// Renders the questions as a Moodle XML quiz in a category named after the
+/// title. Each question is single-answer multichoice with the explanation as
+/// general feedback; difficulty and topic become `difficulty:` and `topic:`
+/// tags.
+pub fn render(set: &McqSet, title: &str) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<quiz>\n");
+
+    // a slash would start a subcategory
+    xml.push_str(&format!(
+        "  <question type=\"category\">\n    <category><text>$course$/{}</text></category>\n  </question>\n",
+        escape_xml(&title.replace('/', "-"))
+    ));
+
+    for (index, question) in set.questions.iter().enumerate() {
+        xml.push_str("  <question type=\"multichoice\">\n");
+        xml.push_str(&format!(
+            "    <name><text>Question {}</text></name>\n",
+            index + 1
+        ));
+        xml.push_str(&format!(
+            "    <questiontext format=\"plain_text\"><text>{}</text></questiontext>\n",
+            escape_xml(&question.stem)
+        ));
+        xml.push_str(&format!(
+            "    <generalfeedback format=\"plain_text\"><text>{}</text></generalfeedback>\n",
+            escape_xml(&question.explanation)
+        ));
+        xml.push_str("    <defaultgrade>1</defaultgrade>\n");
+        xml.push_str("    <penalty>0.3333333</penalty>\n");
+        xml.push_str("    <hidden>0</hidden>\n");
+        xml.push_str("    <single>true</single>\n");
+        xml.push_str("    <shuffleanswers>true</shuffleanswers>\n");
+        xml.push_str("    <answernumbering>abc</answernumbering>\n");
+
+        for (option_index, option) in question.options.iter().enumerate() {
+            let fraction = if option_index == question.correct_index {
+                100
+            } else {
+                0
+            };
+            xml.push_str(&format!(
+                "    <answer fraction=\"{}\" format=\"plain_text\"><text>{}</text><feedback format=\"plain_text\"><text></text></feedback></answer>\n",
+                fraction,
+                escape_xml(option)
+            ));
+        }
+
+        xml.push_str(&format!(
+            "    <tags><tag><text>difficulty:{}</text></tag><tag><text>topic:{}</text></tag></tags>\n",
+            question.difficulty.as_str(),
+            escape_xml(&question.topic)
+        ));
+        xml.push_str("  </question>\n");
+    }
+
+    xml.push_str("</quiz>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::{
+        models::mcq::{McqQuestion, McqSet},
+        services::export::test_support::{parse_xml, sample_set, Element},
+    };
+
+    /// Reads the questions back the way Moodle's importer interprets them.
+    fn read(xml: &str) -> McqSet {
+        let quiz = parse_xml(xml);
+        assert_eq!(quiz.name, "quiz");
+
+        let questions = quiz
+            .children("question")
+            .filter(|question| question.attribute("type") == Some("multichoice"))
+            .map(read_question)
+            .collect();
+
+        McqSet { questions }
+    }
+
+    fn read_question(question: &Element) -> McqQuestion {
+        let answers = question.children("answer").collect::<Vec<_>>();
+        let tags = question
+            .find(&["tags"])
+            .map(|tags| {
+                tags.children("tag")
+                    .map(|tag| tag.text_at(&["text"]))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let tag = |prefix: &str| {
+            tags.iter()
+                .find_map(|tag| tag.strip_prefix(prefix))
+                .expect("tag present")
+                .to_string()
+        };
+
+        McqQuestion {
+            stem: question.text_at(&["questiontext", "text"]),
+            options: answers
+                .iter()
+                .map(|answer| answer.text_at(&["text"]))
+                .collect(),
+            correct_index: answers
+                .iter()
+                .position(|answer| answer.attribute("fraction") == Some("100"))
+                .expect("a correct answer"),
+            explanation: question.text_at(&["generalfeedback", "text"]),
+            difficulty: tag("difficulty:").parse().unwrap(),
+            topic: tag("topic:"),
+            sources: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn round_trips_the_sample_set() {
+        let set = sample_set();
+
+        assert_eq!(read(&render(&set, "Kidneys & <Organs>")), set);
+    }
+
+    #[test]
+    fn marks_exactly_one_answer_correct() {
+        let quiz = parse_xml(&render(&sample_set(), "Organs"));
+
+        for question in quiz.children("question").skip(1) {
+            let correct = question
+                .children("answer")
+                .filter(|answer| answer.attribute("fraction") == Some("100"))
+                .count();
+            assert_eq!(correct, 1);
+        }
+    }
+
+    #[test]
+    fn files_questions_in_a_category_named_after_the_title() {
+        let quiz = parse_xml(&render(&sample_set(), "Biology/Organs"));
+        let category = quiz.children("question").next().unwrap();
+
+        assert_eq!(category.attribute("type"), Some("category"));
+        assert_eq!(
+            category.text_at(&["category", "text"]),
+            "$course$/Biology-Organs"
+        );
+    }
+}
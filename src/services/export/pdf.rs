@@ -0,0 +1,697 @@
+use printpdf::{
+    FontData, FontMetrics, GlyphMetrics, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point,
+};
+use std::collections::HashMap;
+use ttf_parser::{Face, GlyphId};
+
+use super::{
+    document::{list_numbers, Block, Document, Span},
+    option_letter,
+};
+use crate::utils::errors::AppError;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const TITLE_SIZE: f32 = 16.0;
+const BODY_SIZE: f32 = 11.0;
+const TABLE_SIZE: f32 = 10.0;
+
+/// Space between blocks.
+const BLOCK_GAP: f32 = 2.5;
+/// Indent of list items, questions and options.
+const INDENT: f32 = 7.0;
+const CELL_PADDING: f32 = 1.5;
+
+const PT_TO_MM: f32 = 25.4 / 72.0;
+
+/// The DejaVu Sans faces, indexed by `Style`.
+const FONT_FILES: [&[u8]; 5] = [
+    include_bytes!("../../../assets/fonts/DejaVuSans.ttf"),
+    include_bytes!("../../../assets/fonts/DejaVuSans-Bold.ttf"),
+    include_bytes!("../../../assets/fonts/DejaVuSans-Oblique.ttf"),
+    include_bytes!("../../../assets/fonts/DejaVuSans-BoldOblique.ttf"),
+    include_bytes!("../../../assets/fonts/DejaVuSansMono.ttf"),
+];
+
+/// Renders `document` on A4 pages in DejaVu Sans, embedded in the file so
+/// that Latin, Greek and Cyrillic text prints with its accents, including
+/// combining ones such as Yoruba tone marks. Characters the font lacks are
+/// printed as `?`. Links are printed with their address.
+pub fn render(document: &Document) -> Result<Vec<u8>, AppError> {
+    let mut writer = Writer::new(&document.title)?;
+
+    for block in &document.blocks {
+        match block {
+            Block::Title(text) => {
+                writer.paragraph(&[Span::bold(text.as_str())], TITLE_SIZE, 0.0, true);
+                writer.gap(BLOCK_GAP);
+            }
+            Block::Subtitle(text) => {
+                writer.paragraph(&[Span::plain(text.as_str())], BODY_SIZE, 0.0, true);
+            }
+            Block::Heading { level, spans } => {
+                let size = match level {
+                    1 => 14.0,
+                    2 => 13.0,
+                    _ => 12.0,
+                };
+                writer.gap(BLOCK_GAP * 1.5);
+                writer.keep_lines(3, size);
+                writer.paragraph(&styled(spans, |span| span.bold = true), size, 0.0, false);
+                writer.gap(BLOCK_GAP / 2.0);
+            }
+            Block::Paragraph(spans) => {
+                writer.gap(BLOCK_GAP);
+                writer.paragraph(spans, BODY_SIZE, 0.0, false);
+            }
+            Block::List(items) => {
+                writer.gap(BLOCK_GAP);
+                for (item, number) in items.iter().zip(list_numbers(items)) {
+                    let marker = match (item.ordered, item.level) {
+                        (true, _) => format!("{}.", number),
+                        (false, 0) => "\u{2022}".to_string(),
+                        (false, _) => "\u{2013}".to_string(),
+                    };
+                    writer.marked(&marker, &item.spans, BODY_SIZE, item.level as f32 * INDENT);
+                }
+            }
+            Block::Quote(spans) => {
+                writer.gap(BLOCK_GAP);
+                writer.paragraph(
+                    &styled(spans, |span| span.italic = true),
+                    BODY_SIZE,
+                    INDENT,
+                    false,
+                );
+            }
+            Block::Code(code) => {
+                writer.gap(BLOCK_GAP);
+                writer.code(code);
+            }
+            Block::Question {
+                number,
+                stem,
+                options,
+            } => {
+                writer.gap(BLOCK_GAP * 2.0);
+                writer.keep_lines(options.len() + 1, BODY_SIZE);
+                writer.marked(
+                    &format!("{}.", number),
+                    &[Span::plain(stem.as_str())],
+                    BODY_SIZE,
+                    0.0,
+                );
+                for (index, option) in options.iter().enumerate() {
+                    writer.marked(
+                        &format!("{}.", option_letter(index)),
+                        &[Span::plain(option.as_str())],
+                        BODY_SIZE,
+                        INDENT,
+                    );
+                }
+            }
+            Block::Table { header, rows } => {
+                writer.gap(BLOCK_GAP);
+                writer.table(header, rows);
+            }
+        }
+    }
+
+    writer.finish()
+}
+
+fn styled(spans: &[Span], style: impl Fn(&mut Span)) -> Vec<Span> {
+    spans
+        .iter()
+        .map(|span| {
+            let mut span = span.clone();
+            style(&mut span);
+            span
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Style {
+    fn of(word: &Word) -> Self {
+        match (word.code, word.bold, word.italic) {
+            (true, _, _) => Self::Mono,
+            (false, false, false) => Self::Regular,
+            (false, true, false) => Self::Bold,
+            (false, false, true) => Self::Italic,
+            (false, true, true) => Self::BoldItalic,
+        }
+    }
+}
+
+/// The font faces, parsed for their glyph widths. A face is embedded in the
+/// document the first time a word uses it: fonts are embedded whole, so
+/// leaving out unused ones keeps the file small.
+struct Fonts {
+    faces: Vec<Face<'static>>,
+    embedded: [Option<IndirectFontRef>; 5],
+}
+
+impl Fonts {
+    fn new() -> Result<Self, AppError> {
+        let faces = FONT_FILES
+            .iter()
+            .map(|file| {
+                Face::parse(file, 0)
+                    .map_err(|e| AppError::InternalServerError(format!("PDF font error: {}", e)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            faces,
+            embedded: Default::default(),
+        })
+    }
+
+    /// Width of `text` in millimetres.
+    fn width(&self, style: Style, text: &str, size: f32) -> f32 {
+        let face = &self.faces[style as usize];
+        let units: u32 = text
+            .chars()
+            .filter_map(|c| face.glyph_index(c))
+            .filter_map(|glyph| face.glyph_hor_advance(glyph))
+            .map(u32::from)
+            .sum();
+        units as f32 / face.units_per_em() as f32 * size * PT_TO_MM
+    }
+
+    fn embed(&self, doc: &PdfDocumentReference, style: Style) -> IndirectFontRef {
+        let face = PdfFace(self.faces[style as usize].clone());
+        doc.add_external_font_data(FONT_FILES[style as usize].to_vec(), face)
+            .expect("adding font data does not fail")
+    }
+
+    /// Replaces characters the face has no glyph for, which would otherwise
+    /// be dropped silently.
+    fn printable(&self, style: Style, text: &str) -> String {
+        let face = &self.faces[style as usize];
+        text.chars()
+            .map(|c| match c {
+                c if c.is_whitespace() => ' ',
+                c if face.glyph_index(c).is_none() => '?',
+                c => c,
+            })
+            .collect()
+    }
+}
+
+/// A face as printpdf embeds it. Characters beyond the Basic Multilingual
+/// Plane are left out of the glyph map: printpdf writes them into the
+/// ToUnicode map as odd-length hex codes, which text extraction rejects, and
+/// DejaVu Sans only has a few rare scripts there.
+#[derive(Clone, Debug)]
+struct PdfFace(Face<'static>);
+
+impl FontData for PdfFace {
+    fn font_metrics(&self) -> FontMetrics {
+        FontMetrics {
+            ascent: self.0.ascender(),
+            descent: self.0.descender(),
+            units_per_em: self.0.units_per_em(),
+        }
+    }
+
+    fn glyph_id(&self, c: char) -> Option<u16> {
+        self.0.glyph_index(c).map(|glyph| glyph.0)
+    }
+
+    fn glyph_ids(&self) -> HashMap<u16, char> {
+        let mut glyphs = HashMap::new();
+        let subtables = self
+            .0
+            .tables()
+            .cmap
+            .into_iter()
+            .flat_map(|cmap| cmap.subtables);
+        for subtable in subtables.filter(|subtable| subtable.is_unicode()) {
+            subtable.codepoints(|code| {
+                let glyph = subtable.glyph_index(code).filter(|glyph| glyph.0 > 0);
+                if let (Some(c), Some(glyph)) = (char::from_u32(code), glyph) {
+                    if code <= 0xffff {
+                        glyphs.entry(glyph.0).or_insert(c);
+                    }
+                }
+            });
+        }
+        glyphs
+    }
+
+    fn glyph_count(&self) -> u16 {
+        self.0.number_of_glyphs()
+    }
+
+    fn glyph_metrics(&self, glyph_id: u16) -> Option<GlyphMetrics> {
+        let glyph = GlyphId(glyph_id);
+        let width = self.0.glyph_hor_advance(glyph)?;
+        let height = self
+            .0
+            .glyph_bounding_box(glyph)
+            .map(|bbox| bbox.y_max - bbox.y_min - self.0.descender())
+            .unwrap_or(1000);
+        Some(GlyphMetrics {
+            width: u32::from(width),
+            height: height as u32,
+        })
+    }
+}
+
+/// A word with the style it is printed in.
+struct Word {
+    text: String,
+    bold: bool,
+    italic: bool,
+    code: bool,
+    /// Whether whitespace separates it from the word before.
+    spaced: bool,
+    /// Whether it starts a new line, after a line break in the text.
+    breaks: bool,
+}
+
+/// A word placed on a line, `x` relative to the start of the line.
+struct Placed<'a> {
+    word: &'a Word,
+    x: f32,
+}
+
+/// Lays out text top to bottom, starting a new page when the current one is
+/// full.
+struct Writer {
+    doc: PdfDocumentReference,
+    fonts: Fonts,
+    layer: PdfLayerReference,
+    /// Distance of the next line's top from the top of the page.
+    y: f32,
+}
+
+impl Writer {
+    fn new(title: &str) -> Result<Self, AppError> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Page 1");
+        let fonts = Fonts::new()?;
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Ok(Self {
+            doc,
+            fonts,
+            layer,
+            y: MARGIN,
+        })
+    }
+
+    fn finish(self) -> Result<Vec<u8>, AppError> {
+        self.doc
+            .save_to_bytes()
+            .map_err(|e| AppError::InternalServerError(format!("PDF rendering error: {:?}", e)))
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = MARGIN;
+    }
+
+    fn gap(&mut self, height: f32) {
+        if self.y > MARGIN {
+            self.y += height;
+        }
+    }
+
+    /// Starts a new page unless `lines` more lines fit on this one, so a
+    /// heading or question is not split from what follows it.
+    fn keep_lines(&mut self, lines: usize, size: f32) {
+        if self.y + lines as f32 * line_height(size) > PAGE_HEIGHT - MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn paragraph(&mut self, spans: &[Span], size: f32, indent: f32, centered: bool) {
+        let words = words(spans);
+        let width = CONTENT_WIDTH - indent;
+
+        for (line, line_width) in wrap(&self.fonts, &words, size, width) {
+            let offset = match centered {
+                true => (width - line_width).max(0.0) / 2.0,
+                false => 0.0,
+            };
+            self.line(&line, size, MARGIN + indent + offset);
+        }
+    }
+
+    /// Text with a marker such as `1.` or a bullet hanging in front of it.
+    fn marked(&mut self, marker: &str, spans: &[Span], size: f32, indent: f32) {
+        let words = words(spans);
+        let marker = Word {
+            text: marker.to_string(),
+            bold: false,
+            italic: false,
+            code: false,
+            spaced: false,
+            breaks: false,
+        };
+
+        for (index, (line, _)) in wrap(&self.fonts, &words, size, CONTENT_WIDTH - indent - INDENT)
+            .into_iter()
+            .enumerate()
+        {
+            if index == 0 {
+                self.ensure_room(size);
+                self.word(&marker, size, MARGIN + indent);
+            }
+            self.line(&line, size, MARGIN + indent + INDENT);
+        }
+    }
+
+    /// Preformatted lines in the monospace font, indented and not wrapped.
+    fn code(&mut self, code: &str) {
+        for line in code.lines() {
+            self.ensure_room(TABLE_SIZE);
+            let word = Word {
+                text: line.to_string(),
+                bold: false,
+                italic: false,
+                code: true,
+                spaced: false,
+                breaks: false,
+            };
+            self.word(&word, TABLE_SIZE, MARGIN + INDENT);
+            self.y += line_height(TABLE_SIZE);
+        }
+    }
+
+    fn ensure_room(&mut self, size: f32) {
+        if self.y + line_height(size) > PAGE_HEIGHT - MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn line(&mut self, line: &[Placed], size: f32, x: f32) {
+        self.ensure_room(size);
+        for placed in line {
+            self.word(placed.word, size, x + placed.x);
+        }
+        self.y += line_height(size);
+    }
+
+    /// Prints a word on the current line without advancing it.
+    fn word(&mut self, word: &Word, size: f32, x: f32) {
+        let style = Style::of(word);
+        let baseline = PAGE_HEIGHT - self.y - size * PT_TO_MM;
+        let text = self.fonts.printable(style, &word.text);
+        let font = self.font(style);
+        self.layer.use_text(text, size, Mm(x), Mm(baseline), &font);
+    }
+
+    /// The document's font for `style`, embedding it on first use.
+    fn font(&mut self, style: Style) -> IndirectFontRef {
+        if let Some(font) = &self.fonts.embedded[style as usize] {
+            return font.clone();
+        }
+        let font = self.fonts.embed(&self.doc, style);
+        self.fonts.embedded[style as usize] = Some(font.clone());
+        font
+    }
+
+    /// A grid with a bold header row, repeated on every page the table
+    /// spans. Columns are as wide as their content needs, shrunk to fit.
+    fn table(&mut self, header: &[Vec<Span>], rows: &[Vec<Vec<Span>>]) {
+        let columns = rows.iter().map(Vec::len).fold(header.len(), usize::max);
+        if columns == 0 {
+            return;
+        }
+
+        let mut natural = vec![10.0f32; columns];
+        for (row, is_header) in
+            std::iter::once((header, true)).chain(rows.iter().map(|r| (r.as_slice(), false)))
+        {
+            for (index, cell) in row.iter().enumerate() {
+                let words = cell_words(cell, is_header);
+                let width = wrap(&self.fonts, &words, TABLE_SIZE, f32::INFINITY)
+                    .iter()
+                    .map(|(_, width)| *width)
+                    .fold(0.0, f32::max)
+                    + 2.0 * CELL_PADDING;
+                natural[index] = natural[index].max(width);
+            }
+        }
+        let total: f32 = natural.iter().sum();
+        let widths: Vec<f32> = match total > CONTENT_WIDTH {
+            true => natural.iter().map(|w| w * CONTENT_WIDTH / total).collect(),
+            false => natural,
+        };
+
+        let header_row = (!header.is_empty()).then(|| self.table_row(header, &widths, true));
+        if let Some(row) = &header_row {
+            self.draw_row(row, &widths, true);
+        }
+
+        for row in rows {
+            let row = self.table_row(row, &widths, false);
+            if self.y + row.height > PAGE_HEIGHT - MARGIN {
+                self.new_page();
+                if let Some(header_row) = &header_row {
+                    self.draw_row(header_row, &widths, true);
+                }
+            }
+            self.draw_row(&row, &widths, false);
+        }
+    }
+
+    fn table_row(&self, cells: &[Vec<Span>], widths: &[f32], is_header: bool) -> Row {
+        let cells: Vec<Vec<Word>> = (0..widths.len())
+            .map(|index| {
+                let cell = cells.get(index).map(Vec::as_slice).unwrap_or_default();
+                cell_words(cell, is_header)
+            })
+            .collect();
+
+        let lines = cells
+            .iter()
+            .zip(widths)
+            .map(|(words, width)| {
+                wrap(&self.fonts, words, TABLE_SIZE, width - 2.0 * CELL_PADDING).len()
+            })
+            .max()
+            .unwrap_or(1)
+            .max(1);
+
+        Row {
+            cells,
+            height: lines as f32 * line_height(TABLE_SIZE) + 2.0 * CELL_PADDING,
+        }
+    }
+
+    fn draw_row(&mut self, row: &Row, widths: &[f32], is_header: bool) {
+        if self.y + row.height > PAGE_HEIGHT - MARGIN {
+            self.new_page();
+        }
+
+        let top = self.y;
+        let mut x = MARGIN;
+        for (words, width) in row.cells.iter().zip(widths) {
+            self.y = top + CELL_PADDING;
+            for (line, _) in wrap(&self.fonts, words, TABLE_SIZE, width - 2.0 * CELL_PADDING) {
+                for placed in &line {
+                    self.word(placed.word, TABLE_SIZE, x + CELL_PADDING + placed.x);
+                }
+                self.y += line_height(TABLE_SIZE);
+            }
+            x += width;
+        }
+
+        let bottom = top + row.height;
+        let right = MARGIN + widths.iter().sum::<f32>();
+        self.layer
+            .set_outline_thickness(if is_header { 1.0 } else { 0.5 });
+        self.rule(MARGIN, right, bottom);
+        if is_header {
+            self.rule(MARGIN, right, top);
+        }
+        let mut x = MARGIN;
+        for width in std::iter::once(&0.0).chain(widths) {
+            x += width;
+            self.layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(x), Mm(PAGE_HEIGHT - top)), false),
+                    (Point::new(Mm(x), Mm(PAGE_HEIGHT - bottom)), false),
+                ],
+                is_closed: false,
+            });
+        }
+
+        self.y = bottom;
+    }
+
+    fn rule(&self, from: f32, to: f32, y: f32) {
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(from), Mm(PAGE_HEIGHT - y)), false),
+                (Point::new(Mm(to), Mm(PAGE_HEIGHT - y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+}
+
+struct Row {
+    cells: Vec<Vec<Word>>,
+    height: f32,
+}
+
+fn line_height(size: f32) -> f32 {
+    size * 1.4 * PT_TO_MM
+}
+
+/// The words of a table cell, bold in the header row.
+fn cell_words(cell: &[Span], is_header: bool) -> Vec<Word> {
+    match is_header {
+        true => words(&styled(cell, |span| span.bold = true)),
+        false => words(cell),
+    }
+}
+
+/// Splits styled text into words, remembering where whitespace and line
+/// breaks were so that e.g. a bold word followed by a comma stays together.
+fn words(spans: &[Span]) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut spaced = false;
+    let mut breaks = false;
+
+    for span in spans {
+        let address = span
+            .link
+            .as_ref()
+            .filter(|url| **url != span.text)
+            .map(|url| format!(" ({})", url));
+
+        let mut current = String::new();
+        for c in span
+            .text
+            .chars()
+            .chain(address.iter().flat_map(|a| a.chars()))
+        {
+            if c.is_whitespace() {
+                if !current.is_empty() {
+                    words.push(Word {
+                        text: std::mem::take(&mut current),
+                        bold: span.bold,
+                        italic: span.italic,
+                        code: span.code,
+                        spaced,
+                        breaks,
+                    });
+                    breaks = false;
+                }
+                spaced = true;
+                breaks |= c == '\n';
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            words.push(Word {
+                text: current,
+                bold: span.bold,
+                italic: span.italic,
+                code: span.code,
+                spaced,
+                breaks,
+            });
+            spaced = false;
+            breaks = false;
+        }
+    }
+
+    words
+}
+
+/// Breaks words into lines no wider than `width`, returning each line with
+/// its width. A word wider than a whole line gets a line of its own.
+fn wrap<'a>(
+    fonts: &Fonts,
+    words: &'a [Word],
+    size: f32,
+    width: f32,
+) -> Vec<(Vec<Placed<'a>>, f32)> {
+    let space = fonts.width(Style::Regular, " ", size);
+    let mut lines = Vec::new();
+    let mut line: Vec<Placed> = Vec::new();
+    let mut x = 0.0;
+
+    for word in words {
+        let word_width = fonts.width(Style::of(word), &word.text, size);
+        let gap = if word.spaced && !line.is_empty() {
+            space
+        } else {
+            0.0
+        };
+
+        if !line.is_empty() && (word.breaks || x + gap + word_width > width) {
+            lines.push((std::mem::take(&mut line), x));
+            x = 0.0;
+        }
+
+        let gap = if line.is_empty() { 0.0 } else { gap };
+        line.push(Placed { word, x: x + gap });
+        x += gap + word_width;
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push((line, x));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::services::export::{
+        document::{Block, Document},
+        test_support::sample_set,
+    };
+
+    #[test]
+    fn prints_text_beyond_western_european() {
+        let set = sample_set();
+        let document = Document {
+            title: "Ìdánwò".to_string(),
+            blocks: set
+                .questions
+                .into_iter()
+                .enumerate()
+                .map(|(index, question)| Block::Question {
+                    number: index + 1,
+                    stem: question.stem,
+                    options: question.options,
+                })
+                .collect(),
+        };
+
+        let pdf = render(&document).unwrap();
+        let text = pdf_extract::extract_text_from_mem(&pdf).unwrap();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        assert!(text.contains("Ọ̀ná wo ni ó tọ́ sí Èkó?"), "{}", text);
+        assert!(text.contains("{x, y} = {3, 4}"), "{}", text);
+        // only the question marks of the three stems, nothing replaced
+        assert_eq!(text.matches('?').count(), 3, "{}", text);
+    }
+}
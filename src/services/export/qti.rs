@@ -0,0 +1,261 @@
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use super::{escape_xml, option_letter};
+use crate::{
+    models::mcq::{Difficulty, McqQuestion, McqSet},
+    utils::errors::AppError,
+};
+
+const ITEM_RESOURCE_TYPE: &str = "imsqti_item_xmlv2p1";
+
+/// Renders the questions as an IMS QTI 2.1 content package: a zip with one
+/// assessment item per question and a manifest listing them. The manifest
+/// carries each question's topic and difficulty as LOM metadata; the
+/// explanation is shown as feedback once the question is answered.
+pub fn render(set: &McqSet) -> Result<Vec<u8>, AppError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+
+    let write_error =
+        |e: std::io::Error| AppError::InternalServerError(format!("Failed to write QTI: {}", e));
+    let zip_error = |e: zip::result::ZipError| {
+        AppError::InternalServerError(format!("Failed to write QTI: {}", e))
+    };
+
+    zip.start_file("imsmanifest.xml", options)
+        .map_err(zip_error)?;
+    zip.write_all(manifest(set).as_bytes())
+        .map_err(write_error)?;
+
+    for (index, question) in set.questions.iter().enumerate() {
+        zip.start_file(item_href(index), options)
+            .map_err(zip_error)?;
+        zip.write_all(item(index, question).as_bytes())
+            .map_err(write_error)?;
+    }
+
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+fn item_identifier(index: usize) -> String {
+    format!("question-{}", index + 1)
+}
+
+fn item_href(index: usize) -> String {
+    format!("items/{}.xml", item_identifier(index))
+}
+
+/// Difficulty in the LOM vocabulary.
+fn lom_difficulty(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "easy",
+        Difficulty::Medium => "medium",
+        Difficulty::Hard => "difficult",
+    }
+}
+
+fn manifest(set: &McqSet) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <manifest xmlns=\"http://www.imsglobal.org/xsd/imscp_v1p1\" \
+         xmlns:imsmd=\"http://ltsc.ieee.org/xsd/LOM\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.imsglobal.org/xsd/imscp_v1p1 http://www.imsglobal.org/xsd/qti/qtiv2p1/qtiv2p1_imscpv1p2_v1p0.xsd \
+         http://ltsc.ieee.org/xsd/LOM http://www.imsglobal.org/xsd/imsmd_loose_v1p3p2.xsd\" \
+         identifier=\"MANIFEST\">\n\
+         \x20 <metadata><schema>QTIv2.1 Package</schema><schemaversion>1.0.0</schemaversion></metadata>\n\
+         \x20 <organizations/>\n\
+         \x20 <resources>\n",
+    );
+
+    for (index, question) in set.questions.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <resource identifier=\"{}\" type=\"{}\" href=\"{}\">\n",
+            item_identifier(index),
+            ITEM_RESOURCE_TYPE,
+            item_href(index)
+        ));
+        xml.push_str(&format!(
+            "      <metadata><imsmd:lom>\
+             <imsmd:general><imsmd:keyword><imsmd:string>{}</imsmd:string></imsmd:keyword></imsmd:general>\
+             <imsmd:educational><imsmd:difficulty><imsmd:source>LOMv1.0</imsmd:source><imsmd:value>{}</imsmd:value></imsmd:difficulty></imsmd:educational>\
+             </imsmd:lom></metadata>\n",
+            escape_xml(&question.topic),
+            lom_difficulty(question.difficulty)
+        ));
+        xml.push_str(&format!("      <file href=\"{}\"/>\n", item_href(index)));
+        xml.push_str("    </resource>\n");
+    }
+
+    xml.push_str("  </resources>\n</manifest>\n");
+    xml
+}
+
+fn item(index: usize, question: &McqQuestion) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <assessmentItem xmlns=\"http://www.imsglobal.org/xsd/imsqti_v2p1\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.imsglobal.org/xsd/imsqti_v2p1 http://www.imsglobal.org/xsd/qti/qtiv2p1/imsqti_v2p1.xsd\" \
+         identifier=\"{}\" title=\"Question {}\" adaptive=\"false\" timeDependent=\"false\">\n",
+        item_identifier(index),
+        index + 1
+    );
+
+    xml.push_str(&format!(
+        "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"single\" baseType=\"identifier\">\n\
+         \x20   <correctResponse><value>{}</value></correctResponse>\n\
+         \x20 </responseDeclaration>\n",
+        option_letter(question.correct_index)
+    ));
+    xml.push_str(
+        "  <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\">\n\
+         \x20   <defaultValue><value>0</value></defaultValue>\n\
+         \x20 </outcomeDeclaration>\n\
+         \x20 <outcomeDeclaration identifier=\"FEEDBACK\" cardinality=\"single\" baseType=\"identifier\"/>\n",
+    );
+
+    xml.push_str("  <itemBody>\n");
+    xml.push_str(
+        "    <choiceInteraction responseIdentifier=\"RESPONSE\" shuffle=\"true\" maxChoices=\"1\">\n",
+    );
+    xml.push_str(&format!(
+        "      <prompt>{}</prompt>\n",
+        escape_xml(&question.stem)
+    ));
+    for (option_index, option) in question.options.iter().enumerate() {
+        xml.push_str(&format!(
+            "      <simpleChoice identifier=\"{}\">{}</simpleChoice>\n",
+            option_letter(option_index),
+            escape_xml(option)
+        ));
+    }
+    xml.push_str("    </choiceInteraction>\n  </itemBody>\n");
+
+    xml.push_str(
+        "  <responseProcessing>\n\
+         \x20   <responseCondition>\n\
+         \x20     <responseIf>\n\
+         \x20       <match><variable identifier=\"RESPONSE\"/><correct identifier=\"RESPONSE\"/></match>\n\
+         \x20       <setOutcomeValue identifier=\"SCORE\"><baseValue baseType=\"float\">1</baseValue></setOutcomeValue>\n\
+         \x20     </responseIf>\n\
+         \x20   </responseCondition>\n\
+         \x20   <setOutcomeValue identifier=\"FEEDBACK\"><baseValue baseType=\"identifier\">EXPLANATION</baseValue></setOutcomeValue>\n\
+         \x20 </responseProcessing>\n",
+    );
+
+    if !question.explanation.is_empty() {
+        xml.push_str(&format!(
+            "  <modalFeedback outcomeIdentifier=\"FEEDBACK\" identifier=\"EXPLANATION\" showHide=\"show\">{}</modalFeedback>\n",
+            escape_xml(&question.explanation)
+        ));
+    }
+
+    xml.push_str("</assessmentItem>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    use super::{render, ITEM_RESOURCE_TYPE};
+    use crate::{
+        models::mcq::{Difficulty, McqQuestion, McqSet},
+        services::export::test_support::{parse_xml, sample_set},
+    };
+
+    fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .expect("file in package")
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    /// Reads the package back the way an LMS importing it would: items in
+    /// manifest order, their metadata from the manifest.
+    fn read(package: &[u8]) -> McqSet {
+        let mut archive = ZipArchive::new(Cursor::new(package)).expect("a zip");
+        let manifest = parse_xml(&read_file(&mut archive, "imsmanifest.xml"));
+
+        let resources = manifest
+            .find(&["resources"])
+            .expect("resources")
+            .children("resource")
+            .filter(|resource| resource.attribute("type") == Some(ITEM_RESOURCE_TYPE))
+            .map(|resource| {
+                let href = resource.attribute("href").unwrap().to_string();
+                let topic = resource.text_at(&["metadata", "lom", "general", "keyword", "string"]);
+                let difficulty = match resource
+                    .text_at(&["metadata", "lom", "educational", "difficulty", "value"])
+                    .as_str()
+                {
+                    "easy" => Difficulty::Easy,
+                    "medium" => Difficulty::Medium,
+                    "difficult" => Difficulty::Hard,
+                    other => panic!("unexpected difficulty {}", other),
+                };
+                (href, topic, difficulty)
+            })
+            .collect::<Vec<_>>();
+
+        let questions = resources
+            .into_iter()
+            .map(|(href, topic, difficulty)| {
+                let item = parse_xml(&read_file(&mut archive, &href));
+                assert_eq!(item.name, "assessmentItem");
+
+                let interaction = item
+                    .find(&["itemBody", "choiceInteraction"])
+                    .expect("a choice interaction");
+                let choices = interaction.children("simpleChoice").collect::<Vec<_>>();
+                let correct = item.text_at(&["responseDeclaration", "correctResponse", "value"]);
+
+                McqQuestion {
+                    stem: interaction.text_at(&["prompt"]),
+                    options: choices.iter().map(|choice| choice.text.clone()).collect(),
+                    correct_index: choices
+                        .iter()
+                        .position(|choice| choice.attribute("identifier") == Some(correct.as_str()))
+                        .expect("correct response names a choice"),
+                    explanation: item.text_at(&["modalFeedback"]),
+                    difficulty,
+                    topic,
+                    sources: Vec::new(),
+                }
+            })
+            .collect();
+
+        McqSet { questions }
+    }
+
+    #[test]
+    fn round_trips_the_sample_set() {
+        let set = sample_set();
+
+        assert_eq!(read(&render(&set).unwrap()), set);
+    }
+
+    #[test]
+    fn lists_every_item_in_the_manifest() {
+        let package = render(&sample_set()).unwrap();
+        let archive = ZipArchive::new(Cursor::new(package.as_slice())).unwrap();
+
+        let mut names = archive.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "imsmanifest.xml",
+                "items/question-1.xml",
+                "items/question-2.xml",
+                "items/question-3.xml"
+            ]
+        );
+    }
+}
@@ -7,7 +7,10 @@ use std::{
     path::Path,
 };
 
-use pdf_extract::extract_text;
+use pdf_extract::extract_text_by_pages;
+use tempfile::NamedTempFile;
+
+use crate::services::{document_service::content_hash, rag_generate::PAGE_BREAK};
 
 pub async fn fetch_system_prompt(prompt_file_name: &str) -> Result<String, String> {
     let current_dir =
@@ -15,7 +18,6 @@ pub async fn fetch_system_prompt(prompt_file_name: &str) -> Result<String, Strin
 
     let base_dir = current_dir.join("src/services/prompts");
 
-
     let file_path = base_dir.join(format!("{}.txt", prompt_file_name));
 
     // let base_dir = Path::new("/app/src/services/prompts");
@@ -36,6 +38,12 @@ pub async fn fetch_system_prompt(prompt_file_name: &str) -> Result<String, Strin
     }
 }
 
+/// Identifies the text of a system prompt, e.g. `mcq@3f2a9c1d0b7e`, so stored
+/// generations record which wording produced them.
+pub fn prompt_version(prompt_file_name: &str, prompt: &str) -> String {
+    format!("{}@{}", prompt_file_name, &content_hash(prompt)[..12])
+}
+
 pub fn extract_from_file(file_path: &str) -> Result<String, String> {
     let path = Path::new(file_path);
     let extension = path
@@ -46,9 +54,13 @@ pub fn extract_from_file(file_path: &str) -> Result<String, String> {
 
     match extension.as_str() {
         "pdf" => {
-            // Extract text from PDF
-            let text = extract_text(file_path)
+            // Extract text from PDF, page by page so chunks can cite their page
+            let pages = extract_text_by_pages(file_path)
                 .map_err(|err| format!("Failed to extract text from PDF: {}", err))?;
+            let text = pages
+                .into_iter()
+                .map(|page| format!("{}{}", page, PAGE_BREAK))
+                .collect();
             Ok(text)
         }
         "docx" => {
@@ -63,6 +75,37 @@ pub fn extract_from_file(file_path: &str) -> Result<String, String> {
     }
 }
 
+/// Extracts text from an uploaded file. The bytes are written to a temporary
+/// file carrying the original extension so the right extractor is picked.
+pub async fn extract_from_upload(file_name: Option<&str>, data: &[u8]) -> Result<String, String> {
+    let file_extension = file_name
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("tmp");
+
+    let temp_file =
+        NamedTempFile::new().map_err(|e| format!("Failed to create temp file: {}", e))?;
+    let temp_file_path = temp_file.path().with_extension(file_extension);
+
+    tokio::fs::write(&temp_file_path, data)
+        .await
+        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+    let path = temp_file_path
+        .to_str()
+        .ok_or_else(|| "Failed to convert temp file path to string".to_string())?
+        .to_string();
+
+    // pdf and docx parsing is CPU bound
+    let extracted = tokio::task::spawn_blocking(move || extract_from_file(&path))
+        .await
+        .map_err(|e| format!("Extraction task failed: {}", e))?;
+
+    let _ = tokio::fs::remove_file(&temp_file_path).await;
+
+    extracted
+}
+
 // Helper function to read a file into a Vec<u8>
 fn read_to_vec(file_path: &str) -> Result<Vec<u8>, String> {
     let mut buffer = Vec::new();
@@ -81,6 +124,11 @@ fn parse_docx_json(json: &str) -> Result<String, String> {
 
     if let Some(children) = data["document"]["children"].as_array() {
         for child in children {
+            // mark headings the markdown way so chunks can cite their section
+            if let Some(level) = docx_heading_level(child) {
+                extracted_text.push_str(&"#".repeat(level));
+                extracted_text.push(' ');
+            }
             extract_text_from_children(child, &mut extracted_text);
         }
     }
@@ -88,6 +136,23 @@ fn parse_docx_json(json: &str) -> Result<String, String> {
     Ok(extracted_text)
 }
 
+// Heading level of a DOCX paragraph styled "Title" or "Heading1".."Heading6"
+fn docx_heading_level(node: &Value) -> Option<usize> {
+    if node["type"] != "paragraph" {
+        return None;
+    }
+
+    let style = node["data"]["property"]["style"].as_str()?;
+    if style == "Title" {
+        return Some(1);
+    }
+
+    style
+        .strip_prefix("Heading")
+        .and_then(|level| level.parse::<usize>().ok())
+        .filter(|level| (1..=6).contains(level))
+}
+
 // Recursive function to extract text from DOCX JSON nodes
 fn extract_text_from_children(node: &Value, extracted_text: &mut String) {
     if let Some(children) = node["data"]["children"].as_array() {
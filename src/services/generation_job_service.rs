@@ -0,0 +1,294 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, IntoActiveModel,
+    QueryFilter, Set, Statement,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::{
+    models::generation_jobs::{
+        ActiveModel as GenerationJobActiveModel, Column, Entity as GenerationJob, GenerationKind,
+        JobStatus, Model as GenerationJobModel,
+    },
+    services::{
+        content_service::content_service,
+        generation_service::GenerationContext,
+        heartbeat::{Heartbeat, STALE_AFTER_SECONDS},
+        mcq_service::mcq_service,
+        presentation_service::presentation_service,
+    },
+    utils::errors::AppError,
+};
+
+/// A job is retried after a worker crash or a transient failure at most this
+/// many times in total.
+const MAX_ATTEMPTS: i32 = 3;
+
+/// How often idle workers look for jobs queued by other replicas.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wait before the first retry of a failed job, doubled for every later one
+/// so a struggling provider is not hit again straight away.
+const RETRY_DELAY: Duration = Duration::from_secs(15);
+
+/// Stores a generation request and wakes a worker to run it.
+pub async fn enqueue_generation_job<T: Serialize>(
+    db: &DatabaseConnection,
+    notify: &Notify,
+    owner_id: Uuid,
+    kind: GenerationKind,
+    request: &T,
+) -> Result<GenerationJobModel, AppError> {
+    let request = serde_json::to_value(request)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    let job = GenerationJobActiveModel {
+        id: Set(Uuid::new_v4()),
+        owner_id: Set(owner_id),
+        kind: Set(kind),
+        status: Set(JobStatus::Queued),
+        progress: Set(0),
+        request: Set(request),
+        result: Set(None),
+        error: Set(None),
+        attempts: Set(0),
+        created_at: Set(Utc::now()),
+        updated_at: Set(Utc::now()),
+        started_at: Set(None),
+        completed_at: Set(None),
+        run_after: Set(None),
+    }
+    .insert(db)
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    notify.notify_one();
+
+    Ok(job)
+}
+
+pub async fn get_generation_job_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    job_id: Uuid,
+) -> Result<GenerationJobModel, AppError> {
+    GenerationJob::find_by_id(job_id)
+        .filter(Column::OwnerId.eq(owner_id))
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound("Job not found".to_string()))
+}
+
+/// Everything a worker needs to run the generation services.
+#[derive(Clone)]
+pub struct GenerationWorker {
+    pub ctx: GenerationContext,
+    pub notify: Arc<Notify>,
+}
+
+impl GenerationWorker {
+    /// Starts `count` workers sharing the jobs table. A running job's
+    /// heartbeat goes stale once its worker dies, and the job is then picked
+    /// up again.
+    pub fn spawn(self, count: usize) {
+        for _ in 0..count {
+            let worker = self.clone();
+            tokio::spawn(async move { worker.run().await });
+        }
+    }
+
+    async fn run(self) {
+        loop {
+            match claim_next(&self.ctx.db).await {
+                Ok(Some(job)) => self.execute(job).await,
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.notify.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to claim generation job: {:?}", e);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    async fn execute(&self, job: GenerationJobModel) {
+        let _heartbeat = Heartbeat::start(self.ctx.db.clone(), "generation_jobs", job.id);
+
+        if job.attempts > MAX_ATTEMPTS {
+            let error = format!("Gave up after {} attempts", MAX_ATTEMPTS);
+            self.finish(&job, Err(AppError::InternalServerError(error)))
+                .await;
+            return;
+        }
+
+        let result = self.generate(&job).await;
+        self.finish(&job, result).await;
+    }
+
+    async fn generate(&self, job: &GenerationJobModel) -> Result<serde_json::Value, AppError> {
+        let response = match job.kind {
+            GenerationKind::AcademicContent => {
+                content_service(&self.ctx, job.owner_id, parse_request(job)?, Some(job.id)).await?
+            }
+            GenerationKind::Mcq => {
+                mcq_service(&self.ctx, job.owner_id, parse_request(job)?, Some(job.id)).await?
+            }
+            GenerationKind::Presentation => {
+                presentation_service(&self.ctx, job.owner_id, parse_request(job)?, Some(job.id))
+                    .await?
+            }
+        };
+
+        serde_json::to_value(response)
+            .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))
+    }
+
+    async fn finish(&self, job: &GenerationJobModel, result: Result<serde_json::Value, AppError>) {
+        let mut active = job.clone().into_active_model();
+        active.updated_at = Set(Utc::now());
+
+        match result {
+            Ok(value) => {
+                active.status = Set(JobStatus::Completed);
+                active.progress = Set(100);
+                active.result = Set(Some(value));
+                active.error = Set(None);
+                active.completed_at = Set(Some(Utc::now()));
+            }
+            // server-side failures may be transient, anything the client
+            // sent wrong will fail the same way on every attempt
+            Err(AppError::InternalServerError(e) | AppError::ServiceUnavailable(e))
+                if job.attempts < MAX_ATTEMPTS =>
+            {
+                eprintln!(
+                    "Generation job {} failed on attempt {}, retrying: {}",
+                    job.id, job.attempts, e
+                );
+                active.status = Set(JobStatus::Queued);
+                active.progress = Set(0);
+                active.error = Set(Some(e));
+                active.run_after = Set(Some(Utc::now() + retry_delay(job.attempts)));
+            }
+            Err(e) => {
+                eprintln!("Generation job {} failed: {:?}", job.id, e);
+                active.status = Set(JobStatus::Failed);
+                active.error = Set(Some(e.into_message()));
+                active.completed_at = Set(Some(Utc::now()));
+            }
+        }
+
+        if let Err(e) = active.update(&self.ctx.db).await {
+            eprintln!("Failed to update generation job {}: {}", job.id, e);
+        }
+    }
+}
+
+/// Marks the oldest runnable job as running for the calling worker. SKIP LOCKED
+/// lets workers on several replicas claim jobs without blocking each other.
+async fn claim_next(db: &DatabaseConnection) -> Result<Option<GenerationJobModel>, AppError> {
+    GenerationJob::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "UPDATE generation_jobs \
+             SET status = 'running', progress = 10, attempts = attempts + 1, \
+                 started_at = now(), updated_at = now() \
+             WHERE id = ( \
+                 SELECT id FROM generation_jobs \
+                 WHERE (status = 'queued' AND (run_after IS NULL OR run_after <= now())) \
+                    OR (status = 'running' AND updated_at < now() - make_interval(secs => $1)) \
+                 ORDER BY created_at \
+                 FOR UPDATE SKIP LOCKED \
+                 LIMIT 1 \
+             ) \
+             RETURNING *",
+            [(STALE_AFTER_SECONDS as f64).into()],
+        ))
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))
+}
+
+/// How long a job waits after its `attempts`-th attempt failed.
+fn retry_delay(attempts: i32) -> Duration {
+    RETRY_DELAY * 2u32.pow(attempts.clamp(1, MAX_ATTEMPTS) as u32 - 1)
+}
+
+fn parse_request<T: DeserializeOwned>(job: &GenerationJobModel) -> Result<T, AppError> {
+    serde_json::from_value(job.request.clone()).map_err(|e| {
+        AppError::BadRequest(format!(
+            "Stored request of job {} is invalid: {}",
+            job.id, e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration as ChronoDuration, Utc};
+    use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Set};
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+    use uuid::Uuid;
+
+    use super::{claim_next, enqueue_generation_job, retry_delay};
+    use crate::{
+        models::generation_jobs::{Entity as GenerationJob, GenerationKind, JobStatus},
+        utils::test_db::test_db,
+    };
+
+    #[test]
+    fn retries_back_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(15));
+        assert_eq!(retry_delay(2), Duration::from_secs(30));
+        assert_eq!(retry_delay(3), Duration::from_secs(60));
+        assert_eq!(retry_delay(10), retry_delay(3));
+    }
+
+    #[tokio::test]
+    async fn requeued_jobs_wait_for_their_retry_time() {
+        let Some(db) = test_db().await else { return };
+        let job = enqueue_generation_job(
+            &db,
+            &Notify::new(),
+            Uuid::new_v4(),
+            GenerationKind::Mcq,
+            &json!({ "topic": "Water" }),
+        )
+        .await
+        .unwrap();
+
+        // older than anything else queued, so it is the first job claimed
+        let mut active = job.clone().into_active_model();
+        active.created_at = Set(Utc::now() - ChronoDuration::days(365 * 30));
+        active.run_after = Set(Some(Utc::now() + ChronoDuration::minutes(1)));
+        active.update(&db).await.unwrap();
+
+        let claimed = claim_next(&db).await.unwrap();
+        assert_ne!(claimed.map(|claimed| claimed.id), Some(job.id));
+
+        let mut active = GenerationJob::find_by_id(job.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_active_model();
+        active.run_after = Set(Some(Utc::now() - ChronoDuration::seconds(1)));
+        active.update(&db).await.unwrap();
+
+        let claimed = claim_next(&db).await.unwrap().unwrap();
+        assert_eq!(claimed.id, job.id);
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert_eq!(claimed.attempts, 1);
+
+        GenerationJob::delete_by_id(job.id).exec(&db).await.unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+use chrono::{Duration, Utc};
+use fastembed::{TextEmbedding, TextRerank};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, Set,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        features::{AcademicContentRequest, GeneratedResponse, McqGeneratorRequest},
+        generation_jobs::{GenerationKind, Model as GenerationJobModel},
+        generations::{
+            ActiveModel as GenerationActiveModel, Column, Entity as Generation, ExportQuery,
+            GenerationPage, GenerationQuery, GenerationSummary, Model as GenerationModel,
+        },
+        presentation::PresentationGeneratorRequest,
+    },
+    services::{
+        export::{export_generation, ExportedFile},
+        generation_job_service::enqueue_generation_job,
+        llm_provider::LlmProvider,
+        llm_service::ModelConfig,
+        presentation_service::MagicSlidesClient,
+        rag_generate::ChunkingConfig,
+        retrieval::RetrievalOptions,
+        vector_store::VectorStore,
+    },
+    utils::errors::AppError,
+};
+
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 100;
+
+/// What the content, MCQ and presentation services retrieve context and call
+/// models with, shared by request handlers and job workers.
+#[derive(Clone)]
+pub struct GenerationContext {
+    pub db: DatabaseConnection,
+    pub embedding_model: Arc<TextEmbedding>,
+    pub rag_store: Arc<dyn VectorStore>,
+    pub reranker: Option<Arc<TextRerank>>,
+    pub chunking: ChunkingConfig,
+    pub models: ModelConfig,
+    pub llm: Arc<dyn LlmProvider>,
+    pub magic_slides: Arc<MagicSlidesClient>,
+}
+
+/// A request as stored with its generation, so it can be run again.
+pub fn request_value<T: Serialize>(request: &T) -> Result<Value, AppError> {
+    serde_json::to_value(request)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))
+}
+
+/// Stores a finished generation with the request, prompt and model that
+/// produced it. A generation made by a job replaces the one an earlier
+/// attempt of the same job stored.
+pub async fn save_generation(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    kind: GenerationKind,
+    request: Value,
+    prompt_version: &str,
+    response: &GeneratedResponse,
+    job_id: Option<Uuid>,
+) -> Result<GenerationModel, AppError> {
+    let citations = serde_json::to_value(&response.citations)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+
+    let generation = GenerationActiveModel {
+        id: Set(Uuid::new_v4()),
+        owner_id: Set(owner_id),
+        kind: Set(kind),
+        request: Set(request),
+        prompt_version: Set(prompt_version.to_string()),
+        model: Set(response.model.clone().unwrap_or_default()),
+        content: Set(response.content.clone()),
+        citations: Set(citations),
+        created_at: Set(response.generated_at),
+        updated_at: Set(response.generated_at),
+        deleted_at: Set(None),
+        job_id: Set(job_id),
+    };
+
+    Generation::insert(generation)
+        .on_conflict(
+            OnConflict::column(Column::JobId)
+                .update_columns([
+                    Column::Request,
+                    Column::PromptVersion,
+                    Column::Model,
+                    Column::Content,
+                    Column::Citations,
+                    Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Failed to save generation: {}", e)))
+}
+
+/// The owner's generations, newest first.
+pub async fn list_generations_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    query: GenerationQuery,
+) -> Result<GenerationPage, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut select = Generation::find()
+        .filter(Column::OwnerId.eq(owner_id))
+        .filter(Column::DeletedAt.is_null());
+    if let Some(kind) = query.kind {
+        select = select.filter(Column::Kind.eq(kind));
+    }
+    if let Some(from) = query.from {
+        select = select.filter(Column::CreatedAt.gte(from.and_hms_opt(0, 0, 0).unwrap().and_utc()));
+    }
+    if let Some(to) = query.to {
+        let end = to.and_hms_opt(0, 0, 0).unwrap().and_utc() + Duration::days(1);
+        select = select.filter(Column::CreatedAt.lt(end));
+    }
+
+    let paginator = select
+        .order_by_desc(Column::CreatedAt)
+        .order_by_desc(Column::Id)
+        .paginate(db, per_page);
+
+    let total = paginator
+        .num_items()
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let items = paginator
+        .fetch_page(page - 1)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(GenerationPage {
+        items: items.into_iter().map(GenerationSummary::from).collect(),
+        page,
+        per_page,
+        total,
+    })
+}
+
+pub async fn get_generation_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    generation_id: Uuid,
+) -> Result<GenerationModel, AppError> {
+    Generation::find_by_id(generation_id)
+        .filter(Column::OwnerId.eq(owner_id))
+        .filter(Column::DeletedAt.is_null())
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound("Generation not found".to_string()))
+}
+
+/// Hides the generation from the history; the row is kept.
+pub async fn delete_generation_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    generation_id: Uuid,
+) -> Result<(), AppError> {
+    let mut generation = get_generation_service(db, owner_id, generation_id)
+        .await?
+        .into_active_model();
+    generation.deleted_at = Set(Some(Utc::now()));
+    generation.updated_at = Set(Utc::now());
+
+    generation
+        .update(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Queues a job running the generation's request again, with the fields of
+/// `overrides` replacing the stored ones. The new result is stored as a new
+/// generation.
+pub async fn regenerate_generation_service(
+    db: &DatabaseConnection,
+    notify: &Notify,
+    models: &ModelConfig,
+    reranker: Option<Arc<TextRerank>>,
+    owner_id: Uuid,
+    generation_id: Uuid,
+    overrides: Value,
+) -> Result<GenerationJobModel, AppError> {
+    let generation = get_generation_service(db, owner_id, generation_id).await?;
+
+    let mut request = match generation.request {
+        Value::Object(request) if !request.is_empty() => request,
+        _ => {
+            return Err(AppError::BadRequest(
+                "Generation was stored without its request and cannot be regenerated".to_string(),
+            ))
+        }
+    };
+
+    let overrides = match overrides {
+        Value::Object(overrides) => overrides,
+        Value::Null => Default::default(),
+        _ => {
+            return Err(AppError::BadRequest(
+                "Overrides must be a JSON object".to_string(),
+            ))
+        }
+    };
+    for (field, value) in overrides {
+        if !request.contains_key(&field) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown request field '{}'",
+                field
+            )));
+        }
+        request.insert(field, value);
+    }
+    let request = Value::Object(request);
+
+    // validated like a new request, before anything is queued
+    match generation.kind {
+        GenerationKind::AcademicContent => {
+            let request: AcademicContentRequest = parse_request(&request)?;
+            RetrievalOptions::new(request.retrieval_strategy, reranker)?;
+            models.chain(&models.content_model, request.model.as_deref())?;
+        }
+        GenerationKind::Mcq => {
+            let request: McqGeneratorRequest = parse_request(&request)?;
+            RetrievalOptions::new(request.retrieval_strategy, reranker)?;
+            models.chain(&models.mcq_model, request.model.as_deref())?;
+        }
+        GenerationKind::Presentation => {
+            let request: PresentationGeneratorRequest = parse_request(&request)?;
+            RetrievalOptions::new(request.retrieval_strategy, reranker)?;
+        }
+    }
+
+    enqueue_generation_job(db, notify, owner_id, generation.kind, &request).await
+}
+
+pub async fn export_generation_service(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    generation_id: Uuid,
+    query: &ExportQuery,
+) -> Result<ExportedFile, AppError> {
+    let generation = get_generation_service(db, owner_id, generation_id).await?;
+
+    export_generation(&generation, query)
+}
+
+fn parse_request<T: DeserializeOwned>(request: &Value) -> Result<T, AppError> {
+    serde_json::from_value(request.clone())
+        .map_err(|e| AppError::BadRequest(format!("Invalid request: {}", e)))
+}
@@ -0,0 +1,47 @@
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// How often a running job's `updated_at` is refreshed.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A running job whose heartbeat is older than this belongs to a worker that
+/// died and may be claimed by another one.
+pub const STALE_AFTER_SECONDS: i64 = 120;
+
+/// Refreshes `updated_at` of a running job in `table` until dropped, so other
+/// replicas can tell a live job from an abandoned one.
+pub struct Heartbeat(JoinHandle<()>);
+
+impl Heartbeat {
+    pub fn start(db: DatabaseConnection, table: &'static str, job_id: Uuid) -> Self {
+        let sql = format!(
+            "UPDATE {} SET updated_at = now() WHERE id = $1 AND status = 'running'",
+            table
+        );
+
+        Self(tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                ticks.tick().await;
+                let beat = db
+                    .execute(Statement::from_sql_and_values(
+                        DbBackend::Postgres,
+                        &sql,
+                        [job_id.into()],
+                    ))
+                    .await;
+                if let Err(e) = beat {
+                    eprintln!("Failed to refresh heartbeat of job {}: {}", job_id, e);
+                }
+            }
+        }))
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
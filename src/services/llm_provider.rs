@@ -0,0 +1,421 @@
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+use crate::{
+    models::features::TokenUsage,
+    services::resilience::{ResilientClient, RetryPolicy},
+    utils::errors::AppError,
+};
+
+/// The text of a completion and the tokens it used, when the backend
+/// reports them.
+pub struct LlmCompletion {
+    pub content: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// What a completion is asked to contain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    #[default]
+    Text,
+    /// A single JSON object, requested with `response_format`. The prompt
+    /// still has to describe the expected shape.
+    Json,
+}
+
+/// A chat completion backend. Services only talk to this trait, so the
+/// backend is chosen at startup with `LLM_PROVIDER`.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Name reported in logs, e.g. `openrouter`.
+    fn name(&self) -> &str;
+
+    /// Runs a single-message prompt and returns the completion.
+    async fn complete(
+        &self,
+        prompt: &str,
+        model: &str,
+        format: ResponseFormat,
+    ) -> Result<LlmCompletion, AppError>;
+
+    /// Runs a prompt, forwarding each content delta to `deltas` as it arrives.
+    /// Returns the whole completion once the stream ends. A closed receiver
+    /// (the client went away) does not stop the completion, so the result can
+    /// still be stored.
+    async fn stream(
+        &self,
+        prompt: &str,
+        model: &str,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmCompletion, AppError>;
+}
+
+const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// Any server exposing the OpenAI chat completions API: OpenRouter, or a
+/// self-hosted one such as Ollama (`http://localhost:11434/v1`) or the
+/// llama.cpp server. Requests go through a [`ResilientClient`], so transient
+/// failures are retried and a failing server trips its circuit breaker.
+pub struct OpenAiCompatibleProvider {
+    name: &'static str,
+    client: ResilientClient,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(base_url: &str, api_key: Option<String>, policy: RetryPolicy) -> Self {
+        Self::named("openai_compatible", base_url, api_key, policy)
+    }
+
+    pub fn openrouter(api_key: String, policy: RetryPolicy) -> Self {
+        Self::named("openrouter", OPENROUTER_BASE_URL, Some(api_key), policy)
+    }
+
+    fn named(
+        name: &'static str,
+        base_url: &str,
+        api_key: Option<String>,
+        policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            name,
+            client: ResilientClient::new(name, policy),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    async fn send(
+        &self,
+        prompt: &str,
+        model: &str,
+        stream: bool,
+        format: ResponseFormat,
+    ) -> Result<reqwest::Response, AppError> {
+        let request = self
+            .client
+            .post(&format!("{}/chat/completions", self.base_url))
+            .json(&chat_body(prompt, model, stream, format));
+
+        let request = match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        };
+
+        self.client.send_to(model, request).await
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn complete(
+        &self,
+        prompt: &str,
+        model: &str,
+        format: ResponseFormat,
+    ) -> Result<LlmCompletion, AppError> {
+        let response = self.send(prompt, model, false, format).await?;
+
+        let response: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Invalid LLM response: {}", e)))?;
+
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .filter(|content| !content.is_empty())
+            .ok_or_else(|| AppError::InternalServerError("No response from LLM".to_string()))?;
+
+        Ok(LlmCompletion {
+            content,
+            usage: response.usage,
+        })
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        model: &str,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmCompletion, AppError> {
+        let response = self.send(prompt, model, true, ResponseFormat::Text).await?;
+        collect_stream(response.bytes_stream(), deltas).await
+    }
+}
+
+/// Replays scripted completions in order and records every prompt it is
+/// given, so generation can be exercised without a network. Once the script
+/// runs out it answers with a fixed text naming the model.
+#[derive(Default)]
+pub struct MockLlmProvider {
+    script: Mutex<VecDeque<Result<String, String>>>,
+    prompts: Mutex<Vec<String>>,
+}
+
+impl MockLlmProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a completion.
+    #[cfg(test)]
+    pub fn with_response(self, content: impl Into<String>) -> Self {
+        self.script.lock().unwrap().push_back(Ok(content.into()));
+        self
+    }
+
+    /// Queues a failure, reported as an internal server error.
+    #[cfg(test)]
+    pub fn with_error(self, message: impl Into<String>) -> Self {
+        self.script.lock().unwrap().push_back(Err(message.into()));
+        self
+    }
+
+    /// Prompts received so far, oldest first.
+    #[cfg(test)]
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+
+    /// Usage is reported as one token per word.
+    fn next(&self, prompt: &str, model: &str) -> Result<LlmCompletion, AppError> {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+
+        let content = match self.script.lock().unwrap().pop_front() {
+            Some(Ok(content)) => content,
+            Some(Err(message)) => return Err(AppError::InternalServerError(message)),
+            None => format!("Mock completion from {}.", model),
+        };
+
+        Ok(LlmCompletion {
+            usage: Some(TokenUsage {
+                prompt_tokens: prompt.split_whitespace().count() as i32,
+                completion_tokens: content.split_whitespace().count() as i32,
+            }),
+            content,
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockLlmProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn complete(
+        &self,
+        prompt: &str,
+        model: &str,
+        _format: ResponseFormat,
+    ) -> Result<LlmCompletion, AppError> {
+        self.next(prompt, model)
+    }
+
+    async fn stream(
+        &self,
+        prompt: &str,
+        model: &str,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmCompletion, AppError> {
+        let completion = self.next(prompt, model)?;
+        let content = &completion.content;
+
+        // one delta per word, keeping the whitespace so the deltas add up
+        let mut start = 0;
+        for (index, _) in content.match_indices(' ') {
+            let _ = deltas.send(content[start..=index].to_string()).await;
+            start = index + 1;
+        }
+        if start < content.len() {
+            let _ = deltas.send(content[start..].to_string()).await;
+        }
+
+        Ok(completion)
+    }
+}
+
+fn chat_body(prompt: &str, model: &str, stream: bool, format: ResponseFormat) -> serde_json::Value {
+    let mut body = json!({
+        "model": model,
+        "messages": [{ "role": "user", "content": prompt }],
+        "stream": stream,
+    });
+
+    // streams only report usage in a final chunk when asked to
+    if stream {
+        body["stream_options"] = json!({ "include_usage": true });
+    }
+
+    if format == ResponseFormat::Json {
+        body["response_format"] = json!({ "type": "json_object" });
+    }
+
+    body
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+/// Collects the server-sent events a chat completion request made with
+/// `stream: true` answers with.
+async fn collect_stream<B, E>(
+    body: impl Stream<Item = Result<B, E>>,
+    deltas: mpsc::Sender<String>,
+) -> Result<LlmCompletion, AppError>
+where
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut content = String::new();
+    let mut usage = None;
+    // raw bytes, since a network chunk may end inside a multibyte character;
+    // only complete lines are decoded
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut body = std::pin::pin!(body);
+
+    // the body is server-sent events: `data: {json}` lines, `: comment`
+    // keep-alives, and a final `data: [DONE]`
+    'stream: while let Some(bytes) = body.next().await {
+        let bytes = bytes.map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        buffer.extend_from_slice(bytes.as_ref());
+
+        while let Some(newline) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let line = std::str::from_utf8(&line).map_err(|e| {
+                AppError::InternalServerError(format!("Invalid UTF-8 in LLM stream: {}", e))
+            })?;
+            let line = line.trim();
+
+            let Some(data) = line.strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data == "[DONE]" {
+                break 'stream;
+            }
+
+            let chunk: StreamChunk = serde_json::from_str(data).map_err(|e| {
+                AppError::InternalServerError(format!("Invalid LLM stream chunk: {}", e))
+            })?;
+
+            usage = chunk.usage.or(usage);
+            for choice in chunk.choices {
+                if let Some(delta) = choice.delta.content.filter(|d| !d.is_empty()) {
+                    content.push_str(&delta);
+                    let _ = deltas.send(delta).await;
+                }
+            }
+        }
+    }
+
+    if content.is_empty() {
+        return Err(AppError::InternalServerError(
+            "No response from LLM".to_string(),
+        ));
+    }
+
+    Ok(LlmCompletion { content, usage })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mock_replays_its_script_then_names_the_model() {
+        let llm = MockLlmProvider::new()
+            .with_response("first answer")
+            .with_error("overloaded");
+
+        let first = llm
+            .complete("a prompt", "model-a", ResponseFormat::Text)
+            .await
+            .unwrap();
+        assert_eq!(first.content, "first answer");
+        let usage = first.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (2, 2));
+
+        assert!(llm
+            .complete("a prompt", "model-a", ResponseFormat::Text)
+            .await
+            .is_err());
+
+        let fallback = llm
+            .complete("another prompt", "model-b", ResponseFormat::Text)
+            .await
+            .unwrap();
+        assert_eq!(fallback.content, "Mock completion from model-b.");
+        assert_eq!(
+            llm.prompts(),
+            vec!["a prompt", "a prompt", "another prompt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn collect_stream_decodes_characters_split_across_chunks() {
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"Ọ̀ná wo\"}}]}\n\n\
+                    data: {\"choices\":[{\"delta\":{\"content\":\" ni Èkó?\"}}]}\n\n\
+                    data: [DONE]\n"
+            .as_bytes();
+        // split between the two bytes of the combining grave accent
+        let split = body.iter().position(|byte| *byte == 0xcc).unwrap() + 1;
+        let chunks = vec![
+            Ok::<_, std::io::Error>(body[..split].to_vec()),
+            Ok(body[split..].to_vec()),
+        ];
+
+        let (deltas, mut received) = mpsc::channel(8);
+        let completion = collect_stream(futures::stream::iter(chunks), deltas)
+            .await
+            .unwrap();
+
+        assert_eq!(completion.content, "Ọ̀ná wo ni Èkó?");
+        assert_eq!(received.recv().await.unwrap(), "Ọ̀ná wo");
+        assert_eq!(received.recv().await.unwrap(), " ni Èkó?");
+    }
+}
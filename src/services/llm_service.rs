@@ -1,61 +1,278 @@
 use chrono::Utc;
-use openrouter_api::types::chat::{ChatCompletionRequest, Message};
-use openrouter_api::OpenRouterClient;
-use std::ops::Deref;
-use std::sync::Arc;
-use std::time::Instant;
+use std::{collections::HashMap, time::Instant};
+use tokio::sync::mpsc;
 
-use crate::models::features::GeneratedResponse;
+use crate::models::features::{GeneratedResponse, TokenUsage};
+use crate::services::llm_provider::{LlmProvider, ResponseFormat};
 use crate::utils::errors::AppError;
 
+/// Which models the generation features run on.
+#[derive(Clone, Debug)]
+pub struct ModelConfig {
+    pub content_model: String,
+    pub mcq_model: String,
+    /// Models a request may ask for instead of the feature default.
+    pub allowed_models: Vec<String>,
+    /// Tried in order when the chosen model fails or times out.
+    pub fallback_models: Vec<String>,
+    /// Prices used to estimate the cost of each call.
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+/// US dollars per million tokens.
+#[derive(Clone, Copy, Debug)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+/// Parses `model=prompt/completion` pairs separated by commas, e.g.
+/// `openai/gpt-4o=2.5/10,qwen/qwq-32b:free=0/0`.
+pub fn parse_prices(value: &str) -> Result<HashMap<String, ModelPrice>, String> {
+    let mut prices = HashMap::new();
+
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let invalid = || {
+            format!(
+                "Invalid price '{}', expected model=prompt/completion",
+                entry
+            )
+        };
+
+        let (model, price) = entry.rsplit_once('=').ok_or_else(invalid)?;
+        let (prompt, completion) = price.split_once('/').ok_or_else(invalid)?;
+
+        prices.insert(
+            model.trim().to_string(),
+            ModelPrice {
+                prompt: prompt.trim().parse().map_err(|_| invalid())?,
+                completion: completion.trim().parse().map_err(|_| invalid())?,
+            },
+        );
+    }
+
+    Ok(prices)
+}
+
+impl ModelConfig {
+    /// The models to try for a request, starting with the requested model or
+    /// the feature default. Requested models must be on the allowlist.
+    pub fn chain(&self, default: &str, requested: Option<&str>) -> Result<Vec<String>, AppError> {
+        let primary = match requested.map(str::trim).filter(|m| !m.is_empty()) {
+            Some(model) if model == default || self.allowed_models.iter().any(|m| m == model) => {
+                model
+            }
+            Some(model) => {
+                return Err(AppError::BadRequest(format!(
+                    "Model '{}' is not allowed, expected one of: {}",
+                    model,
+                    std::iter::once(default)
+                        .chain(self.allowed_models.iter().map(String::as_str))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )))
+            }
+            None => default,
+        };
+
+        let mut models = vec![primary.to_string()];
+        for model in &self.fallback_models {
+            if !models.contains(model) {
+                models.push(model.clone());
+            }
+        }
+
+        Ok(models)
+    }
+
+    /// Cost of a call in US dollars, or None when the model has no price.
+    pub fn estimate_cost(&self, model: &str, usage: &TokenUsage) -> Option<f64> {
+        self.prices.get(model).map(|price| {
+            (usage.prompt_tokens as f64 * price.prompt
+                + usage.completion_tokens as f64 * price.completion)
+                / 1_000_000.0
+        })
+    }
+}
+
+/// Runs a prompt on the first model of the chain that answers. Each model
+/// gets the provider's full retry budget before the next one is tried.
 pub async fn run_prompt(
     prompt: &str,
-    model: &str,
-    client: Arc<OpenRouterClient<openrouter_api::Ready>>,
+    chain: &[String],
+    llm: &dyn LlmProvider,
+    format: ResponseFormat,
+) -> Result<GeneratedResponse, AppError> {
+    let mut last_error = None;
+
+    for model in chain {
+        let start_time = Instant::now();
+
+        let result = llm.complete(prompt, model, format).await;
+        println!(
+            "LLM response from {} ({}) received in {:2?}",
+            llm.name(),
+            model,
+            start_time.elapsed()
+        );
+
+        match result {
+            Ok(completion) => {
+                return Ok(GeneratedResponse {
+                    content: completion.content,
+                    model: Some(model.clone()),
+                    usage: completion.usage,
+                    generated_at: Utc::now(),
+                    citations: Vec::new(),
+                    generation_id: None,
+                    questions: None,
+                })
+            }
+            Err(e) => {
+                eprintln!("Model {} failed: {:?}", model, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(no_models))
+}
+
+/// Runs a prompt, forwarding each content delta to `deltas` as it arrives.
+/// See [`LlmProvider::stream`]. The next model in the chain is only tried
+/// while nothing has been streamed yet, so a client never sees two
+/// completions mixed together.
+pub async fn stream_prompt(
+    prompt: &str,
+    chain: &[String],
+    llm: &dyn LlmProvider,
+    deltas: mpsc::Sender<String>,
 ) -> Result<GeneratedResponse, AppError> {
-    dotenvy::dotenv().ok();
-
-    let request = ChatCompletionRequest {
-        model: model.to_string(),
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: prompt.to_string(),
-            name: None,
-            tool_calls: None,
-        }],
-        stream: None,
-        response_format: None,
-        tools: None,
-        provider: None,
-        models: None,
-        transforms: None,
-    };
-
-    let start_time = Instant::now();
-
-    let response = client
-        .deref()
-        .chat()
-        .map_err(|e| AppError::InternalServerError(e.to_string()))?
-        .chat_completion(request)
-        .await
-        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
-    let duration = start_time.elapsed();
-
-    match response.choices.first() {
-        Some(choice) => {
-            let content = choice.message.content.clone();
-            println!("LLM response received in {:2?}", duration);
-            Ok(GeneratedResponse {
-                content,
-                generated_at: Utc::now(),
-            })
+    let mut last_error = None;
+
+    for model in chain {
+        let start_time = Instant::now();
+        let (attempt_deltas, mut attempt_receiver) = mpsc::channel::<String>(32);
+        let mut streamed = false;
+
+        let attempt = llm.stream(prompt, model, attempt_deltas);
+        let forward = async {
+            while let Some(delta) = attempt_receiver.recv().await {
+                streamed = true;
+                let _ = deltas.send(delta).await;
+            }
+        };
+        let (result, _) = tokio::join!(attempt, forward);
+        println!(
+            "LLM stream from {} ({}) completed in {:2?}",
+            llm.name(),
+            model,
+            start_time.elapsed()
+        );
+
+        match result {
+            Ok(completion) => {
+                return Ok(GeneratedResponse {
+                    content: completion.content,
+                    model: Some(model.clone()),
+                    usage: completion.usage,
+                    generated_at: Utc::now(),
+                    citations: Vec::new(),
+                    generation_id: None,
+                    questions: None,
+                })
+            }
+            Err(e) if streamed => return Err(e),
+            Err(e) => {
+                eprintln!("Model {} failed: {:?}", model, e);
+                last_error = Some(e);
+            }
         }
-        None => {
-            println!("LLM response received in {:2?}", duration);
-            Err(AppError::InternalServerError(
-                "No response from LLM".to_string(),
-            ))
+    }
+
+    Err(last_error.unwrap_or_else(no_models))
+}
+
+fn no_models() -> AppError {
+    AppError::InternalServerError("No model configured".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_provider::MockLlmProvider;
+
+    fn chain() -> Vec<String> {
+        vec!["primary".to_string(), "fallback".to_string()]
+    }
+
+    #[tokio::test]
+    async fn run_prompt_falls_back_to_the_next_model() {
+        let llm = MockLlmProvider::new()
+            .with_error("primary is down")
+            .with_response("from the fallback");
+
+        let response = run_prompt("prompt", &chain(), &llm, ResponseFormat::Text)
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "from the fallback");
+        assert_eq!(response.model.as_deref(), Some("fallback"));
+        assert_eq!(llm.prompts().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn run_prompt_returns_the_last_error_when_every_model_fails() {
+        let llm = MockLlmProvider::new()
+            .with_error("primary is down")
+            .with_error("fallback is down");
+
+        match run_prompt("prompt", &chain(), &llm, ResponseFormat::Text).await {
+            Err(AppError::InternalServerError(message)) => {
+                assert_eq!(message, "fallback is down")
+            }
+            other => panic!(
+                "expected the fallback's error, got {:?}",
+                other.map(|r| r.content)
+            ),
         }
     }
+
+    #[tokio::test]
+    async fn stream_prompt_falls_back_before_anything_is_streamed() {
+        let llm = MockLlmProvider::new()
+            .with_error("primary is down")
+            .with_response("streamed from the fallback");
+        let (deltas, mut received) = mpsc::channel(32);
+
+        let response = stream_prompt("prompt", &chain(), &llm, deltas)
+            .await
+            .unwrap();
+
+        let mut streamed = String::new();
+        while let Some(delta) = received.recv().await {
+            streamed.push_str(&delta);
+        }
+        assert_eq!(response.model.as_deref(), Some("fallback"));
+        assert_eq!(streamed, "streamed from the fallback");
+        assert_eq!(response.content, streamed);
+    }
+
+    #[test]
+    fn chain_rejects_models_off_the_allowlist() {
+        let models = ModelConfig {
+            content_model: "primary".to_string(),
+            mcq_model: "primary".to_string(),
+            allowed_models: vec!["other".to_string()],
+            fallback_models: vec!["primary".to_string(), "fallback".to_string()],
+            prices: HashMap::new(),
+        };
+
+        assert_eq!(
+            models.chain("primary", Some("other")).unwrap(),
+            vec!["other", "primary", "fallback"]
+        );
+        assert_eq!(models.chain("primary", None).unwrap(), chain());
+        assert!(models.chain("primary", Some("unknown")).is_err());
+    }
 }
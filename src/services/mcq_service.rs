@@ -1,32 +1,46 @@
-use super::{extract::fetch_system_prompt, rag_generate::implement_rag};
+use super::extract::{fetch_system_prompt, prompt_version};
 use crate::{
     models::{
-        embeddings::{Column, Entity as Embedding},
         features::{GeneratedResponse, McqGeneratorRequest},
+        generation_jobs::GenerationKind,
+        mcq::McqSet,
     },
     services::{
+        citations::{resolve_citations, CITATION_INSTRUCTIONS},
+        document_service::resolve_context_documents,
+        generation_service::{request_value, save_generation, GenerationContext},
+        llm_provider::ResponseFormat,
         llm_service::run_prompt,
-        rag_store::{retrieve_relevant_chunks, RagStore},
+        retrieval::RetrievalOptions,
+        usage_service::record_llm_usage,
+        vector_store::{format_context, retrieve_relevant_chunks},
     },
     utils::errors::AppError,
 };
-use fastembed::TextEmbedding;
-use openrouter_api::client;
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
-use sha2::{Digest, Sha256};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// How often malformed questions are sent back to the model for repair.
+const MAX_REPAIRS: usize = 1;
 
 pub async fn mcq_service(
-    db: &DatabaseConnection,
+    ctx: &GenerationContext,
+    owner_id: Uuid,
     req: McqGeneratorRequest,
-    model: &TextEmbedding,
-    rag_store: Arc<Mutex<RagStore>>,
-    client: Arc<client::OpenRouterClient<client::Ready>>,
+    job_id: Option<Uuid>,
 ) -> Result<GeneratedResponse, AppError> {
+    let retrieval = RetrievalOptions::new(req.retrieval_strategy, ctx.reranker.clone())?;
+    let chain = ctx
+        .models
+        .chain(&ctx.models.mcq_model, req.model.as_deref())?;
+    let request = request_value(&req)?;
+    let expected = usize::try_from(req.number_of_questions)
+        .ok()
+        .filter(|&n| n > 0);
+
     let sys_prompt = fetch_system_prompt("mcq").await.map_err(|e| {
         AppError::InternalServerError(format!("Failed to fetch system prompt: {:?}", e))
     })?;
+    let prompt_version = prompt_version("mcq", &sys_prompt);
 
     let base_prompt = format!(
         "{}\n\nGrade level: {}\n Number Of Questions: {}\nTopic: {}\nStandard objective: {}\nAdditional criteria: {}",
@@ -38,82 +52,109 @@ pub async fn mcq_service(
         req.additional_criteria.unwrap_or("None".to_string()),
     );
 
-    let ragged_prompt = if let Some(uploaded_content) = req.uploaded_content.as_deref() {
-        let mut hasher = Sha256::new();
-        hasher.update(uploaded_content);
-        let file_hash = format!("{:x}", hasher.finalize());
-
-        let cached = Embedding::find()
-            .filter(Column::FileHash.eq(file_hash.clone()))
-            .all(db)
-            .await
-            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
-
-        let mut store = rag_store.lock().await;
-
-        if cached.is_empty() {
-            println!("No cached embeddings found for file hash: {}", file_hash);
-
-            let (chunks, embeddings) = implement_rag(uploaded_content, model).map_err(|e| {
-                AppError::InternalServerError(format!("Failed to implement RAG: {}", e))
-            })?;
+    let sources = resolve_context_documents(
+        &ctx.db,
+        owner_id,
+        &req.document_ids,
+        &req.uploaded_content,
+        ctx.embedding_model.clone(),
+        ctx.rag_store.clone(),
+        &ctx.chunking,
+    )
+    .await?;
+
+    let retrieved = retrieve_relevant_chunks(
+        &ctx.db,
+        &req.topic,
+        ctx.rag_store.as_ref(),
+        ctx.embedding_model.clone(),
+        owner_id,
+        &sources,
+        &retrieval,
+    )
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to retrieve chunks: {:?}", e)))?;
+    let ragged_prompt = format_context(&retrieved);
+
+    let prompt = if ragged_prompt.is_empty() {
+        base_prompt
+    } else {
+        format!(
+            "{}\n\nRelevant context from the provided documents, grouped by source:\n{}\n\n{}",
+            base_prompt, ragged_prompt, CITATION_INSTRUCTIONS
+        )
+    };
 
-            if chunks.is_empty() || embeddings.is_empty() {
-                return Err(AppError::BadRequest(
-                    "No chunks or embeddings generated.".to_string(),
-                ));
+    let mut response = run_prompt(&prompt, &chain, ctx.llm.as_ref(), ResponseFormat::Json).await?;
+    record_llm_usage(
+        &ctx.db,
+        &ctx.models,
+        owner_id,
+        GenerationKind::Mcq,
+        job_id,
+        0,
+        &response,
+    )
+    .await;
+
+    let mut repairs = 0;
+    let (set, citations) = loop {
+        let (content, citations) = resolve_citations(&response.content, &retrieved);
+
+        match McqSet::parse(&content, expected) {
+            Ok(set) => break (set, citations),
+            Err(problem) if repairs < MAX_REPAIRS => {
+                repairs += 1;
+                eprintln!("Rejected MCQ output, asking for a repair: {}", problem);
+
+                let repair = repair_prompt(&prompt, &response.content, &problem);
+                response =
+                    run_prompt(&repair, &chain, ctx.llm.as_ref(), ResponseFormat::Json).await?;
+                record_llm_usage(
+                    &ctx.db,
+                    &ctx.models,
+                    owner_id,
+                    GenerationKind::Mcq,
+                    job_id,
+                    repairs as i32,
+                    &response,
+                )
+                .await;
             }
-
-            println!("Adding {} chunks and embeddings to the store", chunks.len());
-
-            store
-                .add_chunks_and_embeddings(db.clone(), &file_hash, chunks, embeddings)
-                .await
-                .map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to add chunks and embeddings: {:?}",
-                        e
-                    ))
-                })?;
-        } else {
-            println!("Using cached embeddings for file hash: {}", file_hash);
-
-            let chunks = cached.iter().map(|c| c.chunk.clone()).collect::<Vec<_>>();
-            let embeddings = cached
-                .iter()
-                .map(|c| c.embedding.clone())
-                .collect::<Vec<_>>();
-
-            for (chunk, embedding) in chunks.into_iter().zip(embeddings.into_iter()) {
-                store.add(chunk, embedding).map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to add cached embedding: {:?}",
-                        e
-                    ))
-                })?;
+            Err(problem) => {
+                return Err(AppError::InternalServerError(format!(
+                    "The model returned invalid questions: {}",
+                    problem
+                )))
             }
         }
-
-        let relevant_chunks = retrieve_relevant_chunks(&req.topic, &store, model).map_err(|e| {
-            AppError::InternalServerError(format!("Failed to retrieve chunks: {:?}", e))
-        })?;
-        let context = relevant_chunks.join("\n");
-
-        context
-    } else {
-        return Err(AppError::BadRequest(
-            "Uploaded content is required to create a RagStore.".to_string(),
-        ));
     };
 
-    let prompt = format!(
-        "{}\n\nRelevant context from uploaded content:\n{}",
-        base_prompt, ragged_prompt
-    );
-
-    let response = run_prompt(&prompt, "qwen/qwq-32b:free", client)
-        .await
-        .map_err(|e| AppError::InternalServerError(format!("Failed to run prompt: {:?}", e)))?;
+    response.content = serde_json::to_string(&set)
+        .map_err(|e| AppError::InternalServerError(format!("JSON serialization error: {}", e)))?;
+    response.citations = citations;
+    response.questions = Some(set.questions);
+
+    let generation = save_generation(
+        &ctx.db,
+        owner_id,
+        GenerationKind::Mcq,
+        request,
+        &prompt_version,
+        &response,
+        job_id,
+    )
+    .await?;
+    response.generation_id = Some(generation.id);
 
     Ok(response)
 }
+
+/// Asks for the rejected output to be corrected, keeping the original
+/// instructions and context.
+fn repair_prompt(prompt: &str, output: &str, problem: &str) -> String {
+    format!(
+        "{}\n\nYour previous answer was rejected because {}. Previous answer:\n{}\n\nAnswer again with only the corrected JSON object.",
+        prompt, problem, output
+    )
+}
@@ -1,8 +1,22 @@
 pub mod auth_service;
+pub mod citations;
 pub mod content_service;
+pub mod document_service;
+pub mod export;
 pub mod extract;
+pub mod generation_job_service;
+pub mod generation_service;
+pub mod heartbeat;
+pub mod llm_provider;
 pub mod llm_service;
 pub mod mcq_service;
+pub mod pgvector_store;
 pub mod presentation_service;
+pub mod quota_service;
 pub mod rag_generate;
 pub mod rag_store;
+pub mod reindex_service;
+pub mod resilience;
+pub mod retrieval;
+pub mod usage_service;
+pub mod vector_store;
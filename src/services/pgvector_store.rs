@@ -0,0 +1,216 @@
+use async_trait::async_trait;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult,
+    PaginatorTrait, QueryFilter, Statement, Value,
+};
+use uuid::Uuid;
+
+use crate::{
+    models::embeddings::{Column, Entity as Embedding},
+    services::{
+        rag_generate::{ChunkProvenance, DocumentChunk, EmbeddingModelConfig},
+        vector_store::{cache_embeddings, SearchScope, VectorStore},
+    },
+    utils::errors::AppError,
+};
+
+/// Vector store backed directly by `cached_embeddings`. The `embedding_vec`
+/// column is generated from `embedding` and carries a partial HNSW index per
+/// dimension, so every replica sees the same index and nothing is held in
+/// process memory.
+pub struct PgVectorStore {
+    db: DatabaseConnection,
+    embedding_model: String,
+    dimensions: usize,
+}
+
+#[derive(FromQueryResult)]
+struct ScoredChunk {
+    chunk: String,
+    page: Option<i32>,
+    heading: Option<String>,
+    start_offset: Option<i64>,
+    end_offset: Option<i64>,
+    distance: f64,
+}
+
+impl PgVectorStore {
+    pub async fn new(
+        db: DatabaseConnection,
+        embedding: &EmbeddingModelConfig,
+    ) -> Result<Self, AppError> {
+        let column = db
+            .query_one(Statement::from_string(
+                DbBackend::Postgres,
+                "SELECT 1 FROM information_schema.columns \
+                 WHERE table_name = 'cached_embeddings' AND column_name = 'embedding_vec'",
+            ))
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        if column.is_none() {
+            return Err(AppError::InternalServerError(
+                "cached_embeddings.embedding_vec is missing; is the pgvector extension installed?"
+                    .to_string(),
+            ));
+        }
+
+        // HNSW indexes need a fixed dimension, so each configured model gets a
+        // partial index over the rows of its dimension
+        let dimensions = embedding.dimensions;
+        db.execute_unprepared(&format!(
+            "CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_cached_embeddings_embedding_vec_{dim} \
+             ON cached_embeddings USING hnsw ((embedding_vec::vector({dim})) vector_cosine_ops) \
+             WHERE vector_dims(embedding_vec) = {dim}",
+            dim = dimensions
+        ))
+        .await
+        .map_err(|e| {
+            AppError::InternalServerError(format!("Failed to create vector index: {}", e))
+        })?;
+
+        println!(
+            "PgVectorStore initialized for {} with dimension {}",
+            embedding.name, dimensions
+        );
+
+        Ok(Self {
+            db,
+            embedding_model: embedding.name.clone(),
+            dimensions,
+        })
+    }
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    async fn prepare(&self, owner_id: Uuid, file_hash: &str) -> Result<bool, AppError> {
+        let cached = Embedding::find()
+            .filter(Column::OwnerId.eq(owner_id))
+            .filter(Column::FileHash.eq(file_hash))
+            .filter(Column::EmbeddingModel.eq(&self.embedding_model))
+            .count(&self.db)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        Ok(cached > 0)
+    }
+
+    async fn add(
+        &self,
+        owner_id: Uuid,
+        file_hash: &str,
+        chunks: Vec<DocumentChunk>,
+        embeddings: Vec<Vec<f32>>,
+        chunking: &str,
+    ) -> Result<usize, AppError> {
+        if self.prepare(owner_id, file_hash).await? {
+            return Ok(0);
+        }
+
+        if embeddings.iter().any(|emb| emb.len() != self.dimensions) {
+            return Err(AppError::InternalServerError(
+                "Embedding dimensions mismatch".to_string(),
+            ));
+        }
+
+        if !cache_embeddings(
+            &self.db,
+            owner_id,
+            file_hash,
+            &chunks,
+            &embeddings,
+            chunking,
+            &self.embedding_model,
+        )
+        .await?
+        {
+            return Ok(0);
+        }
+
+        Ok(chunks.len())
+    }
+
+    async fn search(
+        &self,
+        query_embedding: &[f32],
+        count: usize,
+        scope: &SearchScope<'_>,
+    ) -> Result<Vec<(DocumentChunk, f32)>, AppError> {
+        if query_embedding.len() != self.dimensions {
+            return Err(AppError::InternalServerError(
+                "Embedding dimensions mismatch".to_string(),
+            ));
+        }
+
+        // the HNSW index is partial on the dimension, so the cast and the
+        // vector_dims predicate have to match it for the planner to use it
+        let sql = format!(
+            "SELECT chunk, page, heading, start_offset, end_offset, \
+             (embedding_vec::vector({dim}) <=> $1::vector({dim})) AS distance \
+             FROM cached_embeddings \
+             WHERE owner_id = $2 AND file_hash = ANY($3) AND embedding_model = $5 \
+             AND vector_dims(embedding_vec) = {dim} \
+             ORDER BY embedding_vec::vector({dim}) <=> $1::vector({dim}) \
+             LIMIT $4",
+            dim = self.dimensions
+        );
+
+        let query_vector = format!(
+            "[{}]",
+            query_embedding
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        let values: Vec<Value> = vec![
+            query_vector.into(),
+            scope.owner_id.into(),
+            scope.file_hashes.to_vec().into(),
+            (count as i64).into(),
+            self.embedding_model.clone().into(),
+        ];
+
+        let rows = ScoredChunk::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            values,
+        ))
+        .all(&self.db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Search failed: {}", e)))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let chunk = DocumentChunk {
+                    text: row.chunk,
+                    provenance: ChunkProvenance {
+                        page: row.page,
+                        heading: row.heading,
+                        start_offset: row.start_offset,
+                        end_offset: row.end_offset,
+                    },
+                };
+                (chunk, row.distance as f32)
+            })
+            .collect())
+    }
+
+    async fn delete_by_hash(&self, owner_id: Uuid, file_hash: &str) -> Result<usize, AppError> {
+        let deleted = Embedding::delete_many()
+            .filter(Column::OwnerId.eq(owner_id))
+            .filter(Column::FileHash.eq(file_hash))
+            .exec(&self.db)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        Ok(deleted.rows_affected as usize)
+    }
+}
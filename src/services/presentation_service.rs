@@ -1,37 +1,41 @@
-use super::{extract::fetch_system_prompt, rag_generate::implement_rag};
+use super::extract::{fetch_system_prompt, prompt_version};
 use crate::models::presentation::MagicSlidesRequest;
 use crate::{
-    config::{self, config::Configuration},
     models::{
-        embeddings::{Column, Entity as Embedding},
         features::GeneratedResponse,
+        generation_jobs::GenerationKind,
         presentation::{
             MagicSlidesApiResponse, PresentationDetails, PresentationGeneratorRequest,
             PresentationGeneratorResponse, Slide, SlideContent,
         },
     },
-    services::rag_store::{retrieve_relevant_chunks, RagStore},
+    services::{
+        citations::collect_citations,
+        document_service::resolve_context_documents,
+        generation_service::{request_value, save_generation, GenerationContext},
+        resilience::ResilientClient,
+        retrieval::RetrievalOptions,
+        vector_store::{format_context, retrieve_relevant_chunks},
+    },
     utils::errors::AppError,
 };
 use chrono::Utc;
-use fastembed::TextEmbedding;
-use openrouter_api::client;
-use reqwest::Client;
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
-use sha2::{Digest, Sha256};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use uuid::Uuid;
 
 pub async fn presentation_service(
-    db: &DatabaseConnection,
+    ctx: &GenerationContext,
+    owner_id: Uuid,
     req: PresentationGeneratorRequest,
-    model: &TextEmbedding,
-    rag_store: Arc<Mutex<RagStore>>,
-    client: Arc<client::OpenRouterClient<client::Ready>>,
+    job_id: Option<Uuid>,
 ) -> Result<GeneratedResponse, AppError> {
+    let retrieval = RetrievalOptions::new(req.retrieval_strategy, ctx.reranker.clone())?;
+    let request = request_value(&req)?;
+    ctx.magic_slides.email()?;
+
     let sys_prompt = fetch_system_prompt("presentation").await.map_err(|e| {
         AppError::InternalServerError(format!("Failed to fetch system prompt: {:?}", e))
     })?;
+    let prompt_version = prompt_version("presentation", &sys_prompt);
 
     let base_prompt = format!(
         "{}\n\nGrade level: {}\n Number Of Slides: {}\nTopic: {}\nStandard objective: {}\nAdditional criteria: {}",
@@ -43,103 +47,95 @@ pub async fn presentation_service(
         req.additional_criteria.clone().unwrap_or("None".to_string()),
     );
 
-    let ragged_prompt = if let Some(uploaded_content) = req.uploaded_content.as_deref() {
-        let mut hasher = Sha256::new();
-        hasher.update(uploaded_content);
-        let file_hash = format!("{:x}", hasher.finalize());
-
-        let cached = Embedding::find()
-            .filter(Column::FileHash.eq(file_hash.clone()))
-            .all(db)
-            .await
-            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
-
-        let mut store = rag_store.lock().await;
-
-        if cached.is_empty() {
-            println!("No cached embeddings found for file hash: {}", file_hash);
-
-            let (chunks, embeddings) = implement_rag(uploaded_content, model).map_err(|e| {
-                AppError::InternalServerError(format!("Failed to implement RAG: {}", e))
-            })?;
-
-            if chunks.is_empty() || embeddings.is_empty() {
-                return Err(AppError::BadRequest(
-                    "No chunks or embeddings generated.".to_string(),
-                ));
-            }
-
-            println!("Adding {} chunks and embeddings to the store", chunks.len());
-
-            store
-                .add_chunks_and_embeddings(db.clone(), &file_hash, chunks, embeddings)
-                .await
-                .map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to add chunks and embeddings: {:?}",
-                        e
-                    ))
-                })?;
-        } else {
-            println!("Using cached embeddings for file hash: {}", file_hash);
-
-            let chunks = cached.iter().map(|c| c.chunk.clone()).collect::<Vec<_>>();
-            let embeddings = cached
-                .iter()
-                .map(|c| c.embedding.clone())
-                .collect::<Vec<_>>();
-
-            for (chunk, embedding) in chunks.into_iter().zip(embeddings.into_iter()) {
-                store.add(chunk, embedding).map_err(|e| {
-                    AppError::InternalServerError(format!(
-                        "Failed to add cached embedding: {:?}",
-                        e
-                    ))
-                })?;
-            }
-        }
-
-        let relevant_chunks = retrieve_relevant_chunks(&req.topic, &store, model).map_err(|e| {
-            AppError::InternalServerError(format!("Failed to retrieve chunks: {:?}", e))
-        })?;
-        let context = relevant_chunks.join("\n");
-
-        context
-    } else {
-        return Err(AppError::BadRequest(
-            "Uploaded content is required to create a RagStore.".to_string(),
-        ));
-    };
+    let sources = resolve_context_documents(
+        &ctx.db,
+        owner_id,
+        &req.document_ids,
+        &req.uploaded_content,
+        ctx.embedding_model.clone(),
+        ctx.rag_store.clone(),
+        &ctx.chunking,
+    )
+    .await?;
+
+    let retrieved = retrieve_relevant_chunks(
+        &ctx.db,
+        &req.topic,
+        ctx.rag_store.as_ref(),
+        ctx.embedding_model.clone(),
+        owner_id,
+        &sources,
+        &retrieval,
+    )
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Failed to retrieve chunks: {:?}", e)))?;
+    let ragged_prompt = format_context(&retrieved);
 
     let extra_info_source = format!(
-        "{}\n\nRelevant context from uploaded content:\n{}",
+        "{}\n\nRelevant context from the provided documents, grouped by source:\n{}",
         base_prompt, ragged_prompt
     );
 
-    let magic_response = send_external_request(&req, extra_info_source).await?;
+    let magic_response = send_external_request(&req, extra_info_source, &ctx.magic_slides).await?;
 
-    let response = GeneratedResponse {
+    let mut response = GeneratedResponse {
         content: serde_json::to_string(&magic_response).map_err(|e| {
             AppError::InternalServerError(format!("Failed to serialize response: {}", e))
         })?,
+        model: None,
+        usage: None,
         generated_at: Utc::now(),
+        citations: collect_citations(&retrieved),
+        generation_id: None,
+        questions: None,
     };
 
+    let generation = save_generation(
+        &ctx.db,
+        owner_id,
+        GenerationKind::Presentation,
+        request,
+        &prompt_version,
+        &response,
+        job_id,
+    )
+    .await?;
+    response.generation_id = Some(generation.id);
+
     Ok(response)
 }
 
+/// The MagicSlides API and the account presentations are created under.
+pub struct MagicSlidesClient {
+    pub client: ResilientClient,
+    pub base_url: String,
+    pub access_id: String,
+    /// The account's email, from `Magic_Slide_Email`. Presentations cannot be
+    /// created without it.
+    pub email: Option<String>,
+}
+
+impl MagicSlidesClient {
+    fn email(&self) -> Result<&str, AppError> {
+        self.email.as_deref().ok_or_else(|| {
+            AppError::ServiceUnavailable(
+                "Presentation generation is not configured: set Magic_Slide_Email".to_string(),
+            )
+        })
+    }
+}
+
 // will write a robust service later on to handle all types of external request with all request and response types
 pub async fn send_external_request(
     req: &PresentationGeneratorRequest,
     prompt: String,
+    magic_slides: &MagicSlidesClient,
 ) -> Result<PresentationGeneratorResponse, AppError> {
-    let configuration = Configuration::init_env();
-
     let magic_slides_req = MagicSlidesRequest {
         topic: req.topic.clone(),
         extra_info_source: prompt,
-        email: "micahshallom@gmail.com".to_string(),
-        access_id: configuration.magic_slide_access_id,
+        email: magic_slides.email()?.to_string(),
+        access_id: magic_slides.access_id.clone(),
         template: "bullet-point1".to_string(),
         language: "en".to_string(),
         slide_count: req.number_of_slides,
@@ -151,26 +147,16 @@ pub async fn send_external_request(
         presentation_for: format!("grade {} students", req.grade_level),
     };
 
-    // Send request to MagicSlidesAPI
-    let client = Client::new();
-    let response = client
-        .post(configuration.magic_slide_base_url)
-        .json(&magic_slides_req)
-        .send()
-        .await
-        .map_err(|e| {
-            AppError::InternalServerError(format!("MagicSlides API request failed: {}", e))
-        })?;
-
-    // Check response status
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(AppError::InternalServerError(format!(
-            "MagicSlides API returned error: status {}, body {}",
-            status, body
-        )));
-    }
+    // Send request to MagicSlidesAPI, retrying transient failures
+    let response = magic_slides
+        .client
+        .send(
+            magic_slides
+                .client
+                .post(&magic_slides.base_url)
+                .json(&magic_slides_req),
+        )
+        .await?;
 
     // Parse response
     let magic_slides_resp: MagicSlidesApiResponse = response.json().await.map_err(|e| {
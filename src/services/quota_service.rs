@@ -0,0 +1,488 @@
+use axum::{
+    http::{header::RETRY_AFTER, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    FromQueryResult, IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    Statement, TransactionTrait,
+};
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    models::{
+        generation_jobs::GenerationKind,
+        generation_requests::{
+            ActiveModel as GenerationRequestActiveModel, Column as GenerationRequestColumn,
+            Entity as GenerationRequest,
+        },
+        plans::{
+            ActiveModel as PlanActiveModel, Column as PlanColumn, Entity as Plan,
+            Model as PlanModel, PlanLimits, SetUserPlanRequest,
+        },
+        user::Entity as User,
+    },
+    utils::errors::AppError,
+};
+
+/// One of the plan's limits and how much of it is used.
+#[derive(Debug, Serialize)]
+pub struct QuotaUsage {
+    /// Which limit: `generations_per_day`, `tokens_per_month` or
+    /// `presentation_credits_per_month`.
+    pub quota: &'static str,
+    pub limit: i64,
+    pub used: i64,
+    pub remaining: i64,
+    pub reset_at: DateTime<Utc>,
+}
+
+impl QuotaUsage {
+    fn new(quota: &'static str, limit: i64, used: i64, reset_at: DateTime<Utc>) -> Self {
+        Self {
+            quota,
+            limit,
+            used,
+            remaining: (limit - used).max(0),
+            reset_at,
+        }
+    }
+
+    /// Reports the limit on a successful response.
+    pub fn insert_headers(&self, headers: &mut HeaderMap) {
+        let values = [
+            ("x-ratelimit-limit", self.limit.to_string()),
+            ("x-ratelimit-remaining", self.remaining.to_string()),
+            ("x-ratelimit-reset", self.reset_at.timestamp().to_string()),
+        ];
+
+        for (name, value) in values {
+            if let Ok(value) = value.parse() {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        }
+    }
+}
+
+/// A plan limit the request would go over.
+#[derive(Debug)]
+pub struct QuotaExceeded(pub QuotaUsage);
+
+impl IntoResponse for QuotaExceeded {
+    fn into_response(self) -> Response {
+        let retry_after = (self.0.reset_at - Utc::now()).num_seconds().max(1);
+        let body = json!({
+            "status": "error",
+            "error": format!("Your plan's {} limit has been reached", self.0.quota),
+            "data": self.0,
+        });
+
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+            Json(body),
+        )
+            .into_response()
+    }
+}
+
+/// A generation request counted against the plan before it runs.
+#[derive(Debug)]
+pub struct QuotaReservation {
+    request_id: Uuid,
+    /// The limit closest to being reached, this request included.
+    pub tightest: Option<QuotaUsage>,
+}
+
+#[derive(FromQueryResult)]
+struct TokenTotal {
+    tokens: i64,
+}
+
+pub async fn load_user_plan(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+) -> Result<PlanModel, AppError> {
+    let user = User::find_by_id(owner_id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+    Plan::find_by_id(user.plan.clone())
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::InternalServerError(format!("Plan {} does not exist", user.plan)))
+}
+
+/// Checks the limits a new generation of `kind` counts against and, when
+/// none is reached, counts the request. Both happen in one transaction that
+/// holds the user's row lock, so concurrent requests cannot all slip under a
+/// limit. Only LLM features use tokens; only presentations use presentation
+/// credits. Tokens are only known once the LLM answers, so that limit stops
+/// requests once it has been used up rather than reserving ahead.
+pub async fn reserve_generation_quota(
+    db: &DatabaseConnection,
+    plan: &PlanModel,
+    owner_id: Uuid,
+    kind: GenerationKind,
+) -> Result<Result<QuotaReservation, QuotaExceeded>, AppError> {
+    let now = Utc::now();
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    User::find_by_id(owner_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+    let mut usages = Vec::new();
+
+    if let Some(limit) = plan.generations_per_day {
+        let used = count_requests(&txn, owner_id, None, day_start(now)).await?;
+        usages.push(QuotaUsage::new(
+            "generations_per_day",
+            limit.into(),
+            used,
+            next_day_start(now),
+        ));
+    }
+
+    match kind {
+        GenerationKind::AcademicContent | GenerationKind::Mcq => {
+            if let Some(limit) = plan.tokens_per_month {
+                let used = tokens_used(&txn, owner_id, month_start(now)).await?;
+                usages.push(QuotaUsage::new(
+                    "tokens_per_month",
+                    limit,
+                    used,
+                    next_month_start(now),
+                ));
+            }
+        }
+        GenerationKind::Presentation => {
+            if let Some(limit) = plan.presentation_credits_per_month {
+                let used = count_requests(&txn, owner_id, Some(kind), month_start(now)).await?;
+                usages.push(QuotaUsage::new(
+                    "presentation_credits_per_month",
+                    limit.into(),
+                    used,
+                    next_month_start(now),
+                ));
+            }
+        }
+    }
+
+    if let Some(index) = usages.iter().position(|usage| usage.remaining == 0) {
+        // nothing was written, dropping the transaction releases the lock
+        return Ok(Err(QuotaExceeded(usages.swap_remove(index))));
+    }
+
+    let request_id = Uuid::new_v4();
+    GenerationRequestActiveModel {
+        id: Set(request_id),
+        owner_id: Set(owner_id),
+        kind: Set(kind),
+        created_at: Set(now),
+    }
+    .insert(&txn)
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    txn.commit()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    // the request just counted uses one of each limit counting requests
+    for usage in &mut usages {
+        if usage.quota != "tokens_per_month" {
+            usage.used += 1;
+            usage.remaining -= 1;
+        }
+    }
+    let tightest = usages.into_iter().min_by_key(|usage| usage.remaining);
+
+    Ok(Ok(QuotaReservation {
+        request_id,
+        tightest,
+    }))
+}
+
+/// Gives back a reservation whose request failed, so it does not count
+/// against the plan.
+pub async fn release_generation_quota(db: &DatabaseConnection, reservation: QuotaReservation) {
+    let result = GenerationRequest::delete_by_id(reservation.request_id)
+        .exec(db)
+        .await;
+
+    if let Err(e) = result {
+        eprintln!(
+            "Failed to release generation request {}: {}",
+            reservation.request_id, e
+        );
+    }
+}
+
+pub async fn list_plans_service(db: &DatabaseConnection) -> Result<Vec<PlanModel>, AppError> {
+    Plan::find()
+        .order_by_asc(PlanColumn::Name)
+        .all(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// Creates the plan or replaces its limits.
+pub async fn upsert_plan_service(
+    db: &DatabaseConnection,
+    name: String,
+    payload: PlanLimits,
+) -> Result<PlanModel, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    if name.is_empty() || name.len() > 32 {
+        return Err(AppError::BadRequest(
+            "Plan name must be 1 to 32 characters".to_string(),
+        ));
+    }
+
+    let existing = Plan::find_by_id(name.clone())
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut plan = match &existing {
+        Some(plan) => plan.clone().into_active_model(),
+        None => PlanActiveModel {
+            name: Set(name),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        },
+    };
+    plan.generations_per_day = Set(payload.generations_per_day);
+    plan.tokens_per_month = Set(payload.tokens_per_month);
+    plan.presentation_credits_per_month = Set(payload.presentation_credits_per_month);
+    plan.max_upload_bytes = Set(payload.max_upload_bytes);
+    plan.updated_at = Set(Utc::now());
+
+    let plan = match existing {
+        Some(_) => plan.update(db).await,
+        None => plan.insert(db).await,
+    };
+
+    plan.map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+pub async fn set_user_plan_service(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    payload: SetUserPlanRequest,
+) -> Result<(), AppError> {
+    Plan::find_by_id(payload.plan.clone())
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::BadRequest(format!("Plan {} does not exist", payload.plan)))?;
+
+    let mut user = User::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?
+        .into_active_model();
+    user.plan = Set(payload.plan);
+    user.updated_at = Set(Utc::now());
+
+    user.update(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    Ok(())
+}
+
+async fn count_requests(
+    db: &impl ConnectionTrait,
+    owner_id: Uuid,
+    kind: Option<GenerationKind>,
+    since: DateTime<Utc>,
+) -> Result<i64, AppError> {
+    let mut query = GenerationRequest::find()
+        .filter(GenerationRequestColumn::OwnerId.eq(owner_id))
+        .filter(GenerationRequestColumn::CreatedAt.gte(since));
+    if let Some(kind) = kind {
+        query = query.filter(GenerationRequestColumn::Kind.eq(kind));
+    }
+
+    let count = query
+        .count(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(count as i64)
+}
+
+async fn tokens_used(
+    db: &impl ConnectionTrait,
+    owner_id: Uuid,
+    since: DateTime<Utc>,
+) -> Result<i64, AppError> {
+    let total = TokenTotal::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT COALESCE(SUM(prompt_tokens + completion_tokens), 0)::bigint AS tokens \
+         FROM llm_usage \
+         WHERE owner_id = $1 AND created_at >= $2",
+        [owner_id.into(), since.into()],
+    ))
+    .one(db)
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(total.map_or(0, |total| total.tokens))
+}
+
+fn day_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+fn next_day_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    day_start(now) + Duration::days(1)
+}
+
+fn month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    first_of_month(now.year(), now.month())
+}
+
+fn next_month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    match now.month() {
+        12 => first_of_month(now.year() + 1, 1),
+        month => first_of_month(now.year(), month + 1),
+    }
+}
+
+fn first_of_month(year: i32, month: u32) -> DateTime<Utc> {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+    use uuid::Uuid;
+
+    use super::{release_generation_quota, reserve_generation_quota};
+    use crate::{
+        models::{
+            generation_jobs::GenerationKind,
+            plans::{ActiveModel as PlanActiveModel, Model as PlanModel},
+            user::ActiveModel as UserActiveModel,
+        },
+        utils::test_db::test_db,
+    };
+
+    /// A user on a plan of their own allowing `generations_per_day`.
+    async fn user_with_limit(
+        db: &DatabaseConnection,
+        generations_per_day: i32,
+    ) -> (PlanModel, Uuid) {
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        let plan = PlanActiveModel {
+            name: Set(format!("test-{}", &id.simple().to_string()[..8])),
+            generations_per_day: Set(Some(generations_per_day)),
+            tokens_per_month: Set(None),
+            presentation_credits_per_month: Set(None),
+            max_upload_bytes: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+
+        UserActiveModel {
+            id: Set(id),
+            email: Set(format!("{}@example.com", id)),
+            password_hash: Set(String::new()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            first_name: Set(None),
+            last_name: Set(None),
+            is_admin: Set(false),
+            plan: Set(plan.name.clone()),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+
+        (plan, id)
+    }
+
+    #[tokio::test]
+    async fn reserves_up_to_the_limit_and_releases() {
+        let Some(db) = test_db().await else { return };
+        let (plan, owner_id) = user_with_limit(&db, 2).await;
+        let reserve = || reserve_generation_quota(&db, &plan, owner_id, GenerationKind::Mcq);
+
+        let first = reserve().await.unwrap().unwrap();
+        let usage = first.tightest.as_ref().unwrap();
+        assert_eq!((usage.used, usage.remaining), (1, 1));
+        let second = reserve().await.unwrap().unwrap();
+        assert_eq!(second.tightest.as_ref().unwrap().remaining, 0);
+
+        let exceeded = reserve().await.unwrap().unwrap_err();
+        assert_eq!(exceeded.0.quota, "generations_per_day");
+        assert_eq!(exceeded.0.used, 2);
+
+        release_generation_quota(&db, second).await;
+        assert!(reserve().await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn concurrent_reservations_do_not_overshoot() {
+        let Some(db) = test_db().await else { return };
+        let (plan, owner_id) = user_with_limit(&db, 3).await;
+
+        let attempts = (0..8).map(|_| {
+            let (db, plan) = (db.clone(), plan.clone());
+            tokio::spawn(async move {
+                reserve_generation_quota(&db, &plan, owner_id, GenerationKind::AcademicContent)
+                    .await
+                    .unwrap()
+                    .is_ok()
+            })
+        });
+        let granted = futures::future::join_all(attempts)
+            .await
+            .into_iter()
+            .filter(|granted| *granted.as_ref().unwrap())
+            .count();
+
+        assert_eq!(granted, 3);
+    }
+
+    #[tokio::test]
+    async fn unknown_users_are_rejected() {
+        let Some(db) = test_db().await else { return };
+        let (plan, _) = user_with_limit(&db, 1).await;
+
+        let result =
+            reserve_generation_quota(&db, &plan, Uuid::new_v4(), GenerationKind::Mcq).await;
+
+        assert!(result.is_err());
+    }
+}
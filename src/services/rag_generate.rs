@@ -1,25 +1,299 @@
-use fastembed::TextEmbedding;
-use text_splitter::MarkdownSplitter;
+use fastembed::{EmbeddingModel, TextEmbedding};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use text_splitter::{ChunkConfig, MarkdownSplitter, TextSplitter};
+use tiktoken_rs::cl100k_base;
+
+/// How a document is cut into chunks before embedding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkingStrategy {
+    /// Plain text boundaries, sized in characters.
+    Character,
+    /// Plain text boundaries, sized in cl100k tokens.
+    Token,
+    /// Markdown-aware boundaries, sized in characters.
+    Markdown,
+}
+
+impl fmt::Display for ChunkingStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Character => "character",
+            Self::Token => "token",
+            Self::Markdown => "markdown",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ChunkingStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "character" => Ok(Self::Character),
+            "token" => Ok(Self::Token),
+            "markdown" => Ok(Self::Markdown),
+            other => Err(format!(
+                "Unknown chunking strategy '{}', expected 'character', 'token' or 'markdown'",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkingConfig {
+    pub strategy: ChunkingStrategy,
+    /// Size chunks are filled to before the splitter looks for a boundary,
+    /// in the same unit as `chunk_size`.
+    pub target_chunk_size: usize,
+    /// Maximum chunk size, in characters or tokens depending on the strategy.
+    pub chunk_size: usize,
+    /// How much consecutive chunks overlap, in the same unit as `chunk_size`.
+    pub chunk_overlap: usize,
+    /// Chunks with fewer characters than this (e.g. lone headers) are dropped.
+    pub min_chunk_size: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkingStrategy::Markdown,
+            target_chunk_size: 1000,
+            chunk_size: 2000,
+            chunk_overlap: 0,
+            min_chunk_size: 51,
+        }
+    }
+}
+
+/// Per-document chunking settings; unset fields fall back to the server config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkingOverrides {
+    pub strategy: Option<ChunkingStrategy>,
+    pub target_chunk_size: Option<usize>,
+    pub chunk_size: Option<usize>,
+    pub chunk_overlap: Option<usize>,
+    pub min_chunk_size: Option<usize>,
+}
+
+impl ChunkingOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl ChunkingConfig {
+    pub fn with_overrides(&self, overrides: &ChunkingOverrides) -> Self {
+        Self {
+            strategy: overrides.strategy.unwrap_or(self.strategy),
+            target_chunk_size: overrides
+                .target_chunk_size
+                .unwrap_or(self.target_chunk_size),
+            chunk_size: overrides.chunk_size.unwrap_or(self.chunk_size),
+            chunk_overlap: overrides.chunk_overlap.unwrap_or(self.chunk_overlap),
+            min_chunk_size: overrides.min_chunk_size.unwrap_or(self.min_chunk_size),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.chunk_size == 0 {
+            return Err("Chunk size must be greater than zero".to_string());
+        }
+
+        if self.target_chunk_size == 0 {
+            return Err("Chunk target size must be greater than zero".to_string());
+        }
+
+        if self.chunk_overlap >= self.target() {
+            return Err(
+                "Chunk overlap must be smaller than the chunk target and maximum sizes".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The target size, capped at the maximum so lowering only `chunk_size`
+    /// still gives a valid range.
+    fn target(&self) -> usize {
+        self.target_chunk_size.min(self.chunk_size)
+    }
+
+    /// Stored with every chunk. Embeddings whose signature differs from the
+    /// current settings were chunked differently and must be rebuilt.
+    pub fn signature(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            self.strategy,
+            self.target(),
+            self.chunk_size,
+            self.chunk_overlap,
+            self.min_chunk_size
+        )
+    }
+}
+
+/// The model documents and queries are embedded with.
+#[derive(Debug, Clone)]
+pub struct EmbeddingModelConfig {
+    pub model: EmbeddingModel,
+    /// Model code, e.g. `intfloat/multilingual-e5-small`. Stored with every
+    /// chunk so embeddings from another model are never mixed into a search.
+    pub name: String,
+    pub dimensions: usize,
+}
+
+impl FromStr for EmbeddingModelConfig {
+    type Err = String;
+
+    /// Accepts a fastembed model code or variant name, case-insensitively.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+
+        TextEmbedding::list_supported_models()
+            .into_iter()
+            .find(|info| {
+                info.model_code.eq_ignore_ascii_case(value)
+                    || format!("{:?}", info.model).eq_ignore_ascii_case(value)
+            })
+            .map(|info| Self {
+                model: info.model,
+                name: info.model_code,
+                dimensions: info.dim,
+            })
+            .ok_or_else(|| format!("Unknown embedding model '{}'", value))
+    }
+}
+
+/// Separates pages in extracted text. Only paged formats (PDF) contain it.
+pub const PAGE_BREAK: char = '\x0c';
+
+/// Where a chunk sits in its document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkProvenance {
+    /// 1-based page number, for paged formats.
+    pub page: Option<i32>,
+    /// Nearest heading at or before the start of the chunk.
+    pub heading: Option<String>,
+    /// Byte range of the chunk within the extracted text.
+    pub start_offset: Option<i64>,
+    pub end_offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentChunk {
+    pub text: String,
+    pub provenance: ChunkProvenance,
+}
+
+/// Markdown headings in `text` with the byte offset of their line.
+fn find_headings(text: &str) -> Vec<(usize, String)> {
+    let mut headings = Vec::new();
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            headings.push((offset, trimmed[level..].trim().to_string()));
+        }
+        offset += line.len();
+    }
+
+    headings
+}
+
+/// Byte offsets of the page breaks in `text`, or `None` for unpaged text.
+fn find_page_breaks(text: &str) -> Option<Vec<usize>> {
+    let breaks: Vec<usize> = text
+        .match_indices(PAGE_BREAK)
+        .map(|(offset, _)| offset)
+        .collect();
+
+    (!breaks.is_empty()).then_some(breaks)
+}
+
+fn provenance_for(
+    start: usize,
+    end: usize,
+    page_breaks: Option<&[usize]>,
+    headings: &[(usize, String)],
+) -> ChunkProvenance {
+    let page =
+        page_breaks.map(|breaks| breaks.partition_point(|offset| *offset < start) as i32 + 1);
+
+    // prefer the section the chunk starts in, else the first one it opens
+    let heading = match headings.partition_point(|(offset, _)| *offset <= start) {
+        0 => headings.first().filter(|(offset, _)| *offset < end),
+        started => headings.get(started - 1),
+    }
+    .map(|(_, heading)| heading.clone());
+
+    ChunkProvenance {
+        page,
+        heading,
+        start_offset: Some(start as i64),
+        end_offset: Some(end as i64),
+    }
+}
+
+pub fn split_text(text: &str, config: &ChunkingConfig) -> Result<Vec<DocumentChunk>, String> {
+    config.validate()?;
+
+    let chunk_config = ChunkConfig::new(config.target()..=config.chunk_size)
+        .with_overlap(config.chunk_overlap)
+        .map_err(|e| format!("Invalid chunking config: {}", e))?;
+
+    let spans: Vec<(usize, &str)> = match config.strategy {
+        ChunkingStrategy::Character => TextSplitter::new(chunk_config)
+            .chunk_indices(text)
+            .collect(),
+        ChunkingStrategy::Markdown => MarkdownSplitter::new(chunk_config)
+            .chunk_indices(text)
+            .collect(),
+        ChunkingStrategy::Token => {
+            let tokenizer =
+                cl100k_base().map_err(|e| format!("Failed to load tokenizer: {}", e))?;
+            TextSplitter::new(chunk_config.with_sizer(tokenizer))
+                .chunk_indices(text)
+                .collect()
+        }
+    };
+
+    let page_breaks = find_page_breaks(text);
+    let headings = find_headings(text);
+
+    Ok(spans
+        .into_iter()
+        .filter(|(_, chunk)| chunk.chars().count() >= config.min_chunk_size)
+        .map(|(start, chunk)| DocumentChunk {
+            text: chunk.to_string(),
+            provenance: provenance_for(
+                start,
+                start + chunk.len(),
+                page_breaks.as_deref(),
+                &headings,
+            ),
+        })
+        .collect())
+}
 
 pub fn implement_rag(
     text: &str,
     model: &TextEmbedding,
-) -> Result<(Vec<String>, Vec<Vec<f32>>), String> {
-    let chunk_size = 1000..2000; // Range for flexibility, adjust based on testing
-
-    // Use MarkdownSplitter for semantic chunking
-    let splitter = MarkdownSplitter::new(chunk_size);
-    let chunks: Vec<String> = splitter
-        .chunks(text)
-        .map(|s| s.to_string())
-        .filter(|s| s.len() > 50) // Skip very short chunks (e.g., headers)
-        .collect();
+    config: &ChunkingConfig,
+) -> Result<(Vec<DocumentChunk>, Vec<Vec<f32>>), String> {
+    let chunks = split_text(text, config)?;
 
     if chunks.is_empty() {
         return Err("No valid chunks generated".to_string());
     }
 
-    let embeddings = generate_text_embeddings(chunks.clone(), model)?;
+    let texts = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+    let embeddings = generate_text_embeddings(texts, model)?;
 
     Ok((chunks, embeddings))
 }
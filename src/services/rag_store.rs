@@ -1,31 +1,229 @@
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+};
 
 use crate::{
-    models::embeddings::{ActiveModel as EmbeddingActiveModel, Entity as Embedding},
+    models::embeddings::{Column, Entity as Embedding, Model as EmbeddingModel},
+    services::{
+        rag_generate::{DocumentChunk, EmbeddingModelConfig},
+        vector_store::{cache_embeddings, SearchScope, VectorStore},
+    },
     utils::errors::AppError,
 };
-use chrono::Utc;
-use fastembed::TextEmbedding;
-use sea_orm::{DatabaseConnection, EntityTrait, Set};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult,
+    QueryFilter, Statement,
+};
+use serde::{Deserialize, Serialize};
 use usearch::{Index, IndexOptions, MetricKind, ScalarKind};
 use uuid::Uuid;
 
+#[derive(Clone, Debug)]
+pub struct ChunkEntry {
+    pub owner_id: Uuid,
+    pub file_hash: String,
+    pub chunk: DocumentChunk,
+}
+
+/// Identifies a document resident in the index: the same file uploaded by two
+/// users is indexed twice, once per owner.
+type ResidentKey = (Uuid, String);
+
+const SNAPSHOT_INDEX_FILE: &str = "index.usearch";
+const SNAPSHOT_MANIFEST_FILE: &str = "manifest.json";
+
+/// Describes what a saved index contains so it can be restored without
+/// touching the database.
+#[derive(Serialize, Deserialize)]
+struct SnapshotManifest {
+    dimensions: usize,
+    embedding_model: String,
+    saved_at: DateTime<Utc>,
+    next_key: u64,
+    documents: Vec<SnapshotDocument>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotDocument {
+    owner_id: Uuid,
+    file_hash: String,
+    chunks: Vec<(u64, DocumentChunk)>,
+}
+
+/// What `cached_embeddings` holds for a document, used to tell whether a
+/// snapshot or resident copy of it is still current.
+#[derive(FromQueryResult)]
+struct DocumentVersion {
+    owner_id: Uuid,
+    file_hash: String,
+    chunks: i64,
+    latest: DateTime<Utc>,
+}
+
+/// Bookkeeping that has to change together with the index.
+#[derive(Default)]
+struct IndexState {
+    chunks: HashMap<u64, ChunkEntry>,
+    resident: HashMap<ResidentKey, Vec<u64>>,
+    /// Newest `created_at` of the rows each resident document was loaded
+    /// from. Rows replaced by a reindex on any replica are newer.
+    versions: HashMap<ResidentKey, DateTime<Utc>>,
+    next_key: u64,
+}
+
+/// In-process usearch index over `cached_embeddings`, which stays the source
+/// of truth: documents missing from the index are hydrated from it on demand.
+///
+/// Searches share a read lock and run in parallel; loading and unloading
+/// documents take the write lock. The lock is never held across an await.
+///
+/// Every replica keeps its own index, so a resident document is checked
+/// against the database before each use and reloaded when its rows were
+/// replaced, e.g. by a reindex job running on another replica.
 pub struct RagStore {
-    chunks: Vec<String>,
-    embeddings: Vec<Vec<f32>>,
+    db: DatabaseConnection,
+    embedding_model: String,
     index: Index,
+    state: RwLock<IndexState>,
+    snapshot_dir: PathBuf,
+    dirty: AtomicBool,
 }
 
 impl RagStore {
+    /// Restores the last snapshot from `snapshot_dir` when there is one and
+    /// reconciles it with `cached_embeddings`, which may have changed while
+    /// the process was down. Only embeddings produced by `embedding` are
+    /// indexed.
     pub async fn new(
         db: DatabaseConnection,
-        dimensions: usize,
-    ) -> Result<Arc<Mutex<Self>>, AppError> {
-        let capacity = dimensions;
+        embedding: &EmbeddingModelConfig,
+        snapshot_dir: PathBuf,
+    ) -> Result<Self, AppError> {
+        let (store, snapshot_taken_at) =
+            match Self::restore_snapshot(db.clone(), embedding, &snapshot_dir) {
+                Ok(Some((store, saved_at))) => {
+                    println!(
+                        "Restored RagStore snapshot with {} chunks taken at {}",
+                        store.read_state().chunks.len(),
+                        saved_at
+                    );
+                    (store, Some(saved_at))
+                }
+                Ok(None) => (Self::empty(db.clone(), embedding, snapshot_dir)?, None),
+                Err(e) => {
+                    eprintln!("Ignoring unusable RagStore snapshot: {:?}", e);
+                    (Self::empty(db.clone(), embedding, snapshot_dir)?, None)
+                }
+            };
+
+        // rows cached before chunks carried an owner can never match a search scope
+        let versions = DocumentVersion::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT owner_id, file_hash, COUNT(*) AS chunks, MAX(created_at) AS latest \
+             FROM cached_embeddings \
+             WHERE embedding_model = $1 AND owner_id IS NOT NULL \
+             GROUP BY owner_id, file_hash",
+            [embedding.name.clone().into()],
+        ))
+        .all(&db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        let versions: HashMap<ResidentKey, DocumentVersion> = versions
+            .into_iter()
+            .map(|version| ((version.owner_id, version.file_hash.clone()), version))
+            .collect();
+
+        // a restored document is only kept while the database still holds the
+        // rows it was built from: deleted documents are dropped, and documents
+        // whose rows were added to or replaced are reloaded below
+        let restored: Vec<(ResidentKey, usize)> = store
+            .read_state()
+            .resident
+            .iter()
+            .map(|(key, keys)| (key.clone(), keys.len()))
+            .collect();
+        for (key, chunks) in restored {
+            let unchanged = versions.get(&key).filter(|version| {
+                version.chunks == chunks as i64
+                    && snapshot_taken_at.is_some_and(|saved_at| version.latest <= saved_at)
+            });
+            match unchanged {
+                Some(version) => {
+                    store.write_state().versions.insert(key, version.latest);
+                }
+                None => {
+                    store.unload(key.0, &key.1);
+                }
+            }
+        }
+
+        let missing: Vec<&ResidentKey> = versions
+            .keys()
+            .filter(|(owner_id, file_hash)| !store.is_loaded(*owner_id, file_hash))
+            .collect();
+
+        if !missing.is_empty() {
+            let mut query = Embedding::find().filter(Column::EmbeddingModel.eq(&embedding.name));
+            if snapshot_taken_at.is_some() {
+                query = query.filter(missing.iter().fold(
+                    Condition::any(),
+                    |condition, (owner_id, file_hash)| {
+                        condition.add(
+                            Column::OwnerId
+                                .eq(*owner_id)
+                                .and(Column::FileHash.eq(file_hash.as_str())),
+                        )
+                    },
+                ));
+            }
+
+            let cached = query
+                .all(&db)
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+            let mut documents: HashMap<ResidentKey, Vec<EmbeddingModel>> = HashMap::new();
+            for row in cached {
+                let Some(owner_id) = row.owner_id else {
+                    continue;
+                };
+
+                let key = (owner_id, row.file_hash.clone());
+                documents.entry(key).or_default().push(row);
+            }
+
+            for ((owner_id, file_hash), rows) in documents {
+                store.load_rows(owner_id, &file_hash, rows)?;
+            }
+        }
+
+        println!(
+            "RagStore initialized for {} with dimension {}",
+            embedding.name, embedding.dimensions
+        );
+        println!("RagStore created successfully");
+
+        Ok(store)
+    }
+
+    fn empty(
+        db: DatabaseConnection,
+        embedding: &EmbeddingModelConfig,
+        snapshot_dir: PathBuf,
+    ) -> Result<Self, AppError> {
+        let capacity = embedding.dimensions;
 
         let options = IndexOptions {
-            dimensions,
+            dimensions: embedding.dimensions,
             metric: MetricKind::Cos,
             quantization: ScalarKind::F32,
             ..Default::default()
@@ -39,42 +237,275 @@ impl RagStore {
             AppError::InternalServerError(format!("Failed to reserve capacity: {}", err))
         })?;
 
-        let mut store = Self {
-            chunks: Vec::new(),
-            embeddings: Vec::new(),
+        Ok(Self {
+            db,
+            embedding_model: embedding.name.clone(),
             index,
-        };
+            state: RwLock::new(IndexState::default()),
+            snapshot_dir,
+            dirty: AtomicBool::new(false),
+        })
+    }
 
-        store.index.reserve(capacity).map_err(|err| {
-            AppError::InternalServerError(format!("Failed to reserve capacity: {}", err))
+    fn restore_snapshot(
+        db: DatabaseConnection,
+        embedding: &EmbeddingModelConfig,
+        snapshot_dir: &Path,
+    ) -> Result<Option<(Self, DateTime<Utc>)>, AppError> {
+        let index_path = snapshot_dir.join(SNAPSHOT_INDEX_FILE);
+        let manifest_path = snapshot_dir.join(SNAPSHOT_MANIFEST_FILE);
+
+        if !index_path.exists() || !manifest_path.exists() {
+            return Ok(None);
+        }
+
+        let manifest = fs::read(&manifest_path).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to read snapshot manifest: {}", e))
+        })?;
+        let manifest: SnapshotManifest = serde_json::from_slice(&manifest).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to parse snapshot manifest: {}", e))
         })?;
 
-        let cached = Embedding::find()
-            .all(&db)
-            .await
-            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+        if manifest.embedding_model != embedding.name || manifest.dimensions != embedding.dimensions
+        {
+            return Err(AppError::InternalServerError(format!(
+                "Snapshot was built with {} ({} dimensions) but {} ({} dimensions) is configured",
+                manifest.embedding_model, manifest.dimensions, embedding.name, embedding.dimensions
+            )));
+        }
+
+        let store = Self::empty(db, embedding, snapshot_dir.to_path_buf())?;
+        store.index.load(path_str(&index_path)?).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to load snapshot index: {}", e))
+        })?;
+
+        {
+            let mut state = store.write_state();
+            for document in manifest.documents {
+                let mut keys = Vec::with_capacity(document.chunks.len());
+                for (key, chunk) in document.chunks {
+                    state.chunks.insert(
+                        key,
+                        ChunkEntry {
+                            owner_id: document.owner_id,
+                            file_hash: document.file_hash.clone(),
+                            chunk,
+                        },
+                    );
+                    keys.push(key);
+                }
+                state
+                    .resident
+                    .insert((document.owner_id, document.file_hash), keys);
+            }
+
+            if store.index.size() != state.chunks.len() {
+                return Err(AppError::InternalServerError(format!(
+                    "Snapshot index holds {} vectors but the manifest lists {} chunks",
+                    store.index.size(),
+                    state.chunks.len()
+                )));
+            }
+
+            state.next_key = manifest.next_key;
+        }
+
+        Ok(Some((store, manifest.saved_at)))
+    }
+
+    fn read_state(&self) -> RwLockReadGuard<'_, IndexState> {
+        // a panic while holding the lock cannot leave the maps half-updated in
+        // a way that matters for reads, so recover from poisoning
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_state(&self) -> RwLockWriteGuard<'_, IndexState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes the index and its manifest to the snapshot directory if anything
+    /// changed since the last save. The state is copied under the read lock,
+    /// which is released before the files are written on a blocking thread,
+    /// so searches and writers only wait for the copy.
+    pub async fn save_snapshot(&self) -> Result<(), AppError> {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+
+        let snapshot_dir = self.snapshot_dir.clone();
+        let result = match self.take_snapshot() {
+            Ok(snapshot) => tokio::task::spawn_blocking(move || snapshot.write(&snapshot_dir))
+                .await
+                .unwrap_or_else(|e| {
+                    Err(AppError::InternalServerError(format!(
+                        "Snapshot task failed: {}",
+                        e
+                    )))
+                }),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Release);
+        }
+
+        result
+    }
+
+    /// Copies the index and the chunks it holds. The read lock keeps writers
+    /// out so the two agree.
+    fn take_snapshot(&self) -> Result<Snapshot, AppError> {
+        let state = self.read_state();
+
+        let mut index = vec![0; self.index.serialized_length()];
+        self.index.save_to_buffer(&mut index).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to serialize snapshot index: {}", e))
+        })?;
 
-        if !cached.is_empty() {
-            let chunks = cached.iter().map(|c| c.chunk.clone()).collect::<Vec<_>>();
-            let embeddings = cached
+        let manifest = SnapshotManifest {
+            dimensions: self.index.dimensions(),
+            embedding_model: self.embedding_model.clone(),
+            saved_at: Utc::now(),
+            next_key: state.next_key,
+            documents: state
+                .resident
                 .iter()
-                .map(|c| c.embedding.clone())
-                .collect::<Vec<_>>();
+                .map(|((owner_id, file_hash), keys)| SnapshotDocument {
+                    owner_id: *owner_id,
+                    file_hash: file_hash.clone(),
+                    chunks: keys
+                        .iter()
+                        .filter_map(|key| state.chunks.get(key).map(|c| (*key, c.chunk.clone())))
+                        .collect(),
+                })
+                .collect(),
+        };
+
+        Ok(Snapshot {
+            index,
+            manifest,
+            chunks: state.chunks.len(),
+        })
+    }
+
+    /// How many chunks of the document are resident and the version they were
+    /// loaded at.
+    fn resident_version(&self, owner_id: Uuid, file_hash: &str) -> Option<(usize, DateTime<Utc>)> {
+        let state = self.read_state();
+        let key = (owner_id, file_hash.to_string());
+        let keys = state.resident.get(&key)?;
+        let version = state.versions.get(&key)?;
+        Some((keys.len(), *version))
+    }
+
+    pub fn is_loaded(&self, owner_id: Uuid, file_hash: &str) -> bool {
+        self.read_state()
+            .resident
+            .contains_key(&(owner_id, file_hash.to_string()))
+    }
+
+    /// Adds a document's `cached_embeddings` rows to the index unless it is
+    /// already resident.
+    fn load_rows(
+        &self,
+        owner_id: Uuid,
+        file_hash: &str,
+        rows: Vec<EmbeddingModel>,
+    ) -> Result<usize, AppError> {
+        let Some(version) = rows.iter().map(|row| row.created_at).max() else {
+            return Ok(0);
+        };
+        let rows = rows
+            .into_iter()
+            .map(|mut row| {
+                let embedding = std::mem::take(&mut row.embedding);
+                (DocumentChunk::from(row), embedding)
+            })
+            .collect();
+
+        self.load(owner_id, file_hash, version, rows)
+    }
 
-            for (embedding, chunk) in embeddings.iter().zip(chunks.iter()) {
-                store.add(chunk.clone(), embedding.clone()).map_err(|e| {
-                    AppError::InternalServerError(format!("Failed to add embedding: {:?}", e))
-                })?;
+    /// Adds a document's chunks to the index unless it is already resident.
+    /// `version` is the newest `created_at` of the rows they come from.
+    /// Returns the number of chunks that were added.
+    pub fn load(
+        &self,
+        owner_id: Uuid,
+        file_hash: &str,
+        version: DateTime<Utc>,
+        rows: Vec<(DocumentChunk, Vec<f32>)>,
+    ) -> Result<usize, AppError> {
+        let mut state = self.write_state();
+
+        // checked again under the write lock: another request may have loaded
+        // the same document while this one was reading it from the database
+        if state
+            .resident
+            .contains_key(&(owner_id, file_hash.to_string()))
+            || rows.is_empty()
+        {
+            return Ok(0);
+        }
+
+        self.index
+            .reserve(self.index.size() + rows.len())
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Failed to reserve index capacity: {}", e))
+            })?;
+
+        let mut keys = Vec::with_capacity(rows.len());
+        for (chunk, embedding) in rows {
+            let entry = ChunkEntry {
+                owner_id,
+                file_hash: file_hash.to_string(),
+                chunk,
+            };
+
+            match self.insert_chunk(&mut state, entry, embedding) {
+                Ok(key) => keys.push(key),
+                Err(e) => {
+                    // leave nothing half-loaded behind
+                    self.remove_keys(&mut state, &keys);
+                    return Err(e);
+                }
             }
         }
 
-        println!("RagStore initialized with dimension {}", dimensions);
-        println!("RagStore created successfully");
+        let added = keys.len();
+        state
+            .resident
+            .insert((owner_id, file_hash.to_string()), keys);
+        state
+            .versions
+            .insert((owner_id, file_hash.to_string()), version);
+        self.dirty.store(true, Ordering::Release);
+
+        Ok(added)
+    }
 
-        Ok(Arc::new(Mutex::new(store)))
+    /// Removes a document's chunks from the index. Unloading a document that is
+    /// not resident is a no-op. Returns the number of chunks that were removed.
+    pub fn unload(&self, owner_id: Uuid, file_hash: &str) -> usize {
+        let mut state = self.write_state();
+        let key = (owner_id, file_hash.to_string());
+        state.versions.remove(&key);
+
+        match state.resident.remove(&key) {
+            Some(keys) => {
+                self.remove_keys(&mut state, &keys);
+                self.dirty.store(true, Ordering::Release);
+                keys.len()
+            }
+            None => 0,
+        }
     }
 
-    pub fn add(&mut self, chunk: String, embedding: Vec<f32>) -> Result<(), AppError> {
+    fn insert_chunk(
+        &self,
+        state: &mut IndexState,
+        chunk: ChunkEntry,
+        embedding: Vec<f32>,
+    ) -> Result<u64, AppError> {
         if self.index.dimensions() != embedding.len() {
             return Err(AppError::InternalServerError(
                 "Embedding dimensions mismatch".to_string(),
@@ -87,96 +518,208 @@ impl RagStore {
             ));
         }
 
-        let idx = self.chunks.len() as u64;
-        self.index.add(idx, &embedding).map_err(|e| {
+        let key = state.next_key;
+        self.index.add(key, &embedding).map_err(|e| {
             AppError::InternalServerError(format!("Failed to add embedding: {}", e))
         })?;
-        self.chunks.push(chunk);
-        self.embeddings.push(embedding);
-        Ok(())
+        state.next_key += 1;
+        state.chunks.insert(key, chunk);
+        Ok(key)
     }
 
-    pub async fn add_chunks_and_embeddings(
-        &mut self,
-        db: DatabaseConnection,
-        filehash: &str,
-        chunks: Vec<String>,
-        embeddings: Vec<Vec<f32>>,
-    ) -> Result<(), AppError> {
-        if embeddings.len() != chunks.len() {
-            return Err(AppError::InternalServerError(
-                "Chunks and embeddings must have equal dimensions".to_string(),
-            ));
+    fn remove_keys(&self, state: &mut IndexState, keys: &[u64]) {
+        for key in keys {
+            if let Err(e) = self.index.remove(*key) {
+                eprintln!("Failed to remove key {} from index: {}", key, e);
+            }
+            state.chunks.remove(key);
         }
+    }
+}
 
-        if embeddings.is_empty() {
-            return Err(AppError::InternalServerError(
-                "Cannot create ragstore with empty embeddings".to_string(),
-            ));
+#[async_trait]
+impl VectorStore for RagStore {
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    async fn prepare(&self, owner_id: Uuid, file_hash: &str) -> Result<bool, AppError> {
+        let current = DocumentVersion::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT owner_id, file_hash, COUNT(*) AS chunks, MAX(created_at) AS latest \
+             FROM cached_embeddings \
+             WHERE owner_id = $1 AND file_hash = $2 AND embedding_model = $3 \
+             GROUP BY owner_id, file_hash",
+            [
+                owner_id.into(),
+                file_hash.into(),
+                self.embedding_model.clone().into(),
+            ],
+        ))
+        .one(&self.db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        let Some(current) = current else {
+            self.unload(owner_id, file_hash);
+            return Ok(false);
+        };
+
+        if self.resident_version(owner_id, file_hash)
+            == Some((current.chunks as usize, current.latest))
+        {
+            return Ok(true);
         }
 
-        let dimension = embeddings[0].len();
+        // not resident, or resident with rows that have since been replaced
+        self.unload(owner_id, file_hash);
 
-        if embeddings.iter().any(|emb| emb.len() != dimension) {
-            return Err(AppError::InternalServerError(
-                "All embeddings must have the same dimension".to_string(),
-            ));
+        let cached = Embedding::find()
+            .filter(Column::OwnerId.eq(owner_id))
+            .filter(Column::FileHash.eq(file_hash))
+            .filter(Column::EmbeddingModel.eq(&self.embedding_model))
+            .all(&self.db)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        if cached.is_empty() {
+            return Ok(false);
         }
 
-        self.index.reserve(chunks.len() as usize).map_err(|e| {
-            AppError::InternalServerError(format!("Failed to reserve index capacity: {}", e))
+        self.load_rows(owner_id, file_hash, cached).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to add cached embedding: {:?}", e))
         })?;
 
-        let values = chunks
-            .iter()
-            .zip(embeddings.iter())
-            .map(|(chunk, embedding)| EmbeddingActiveModel {
-                id: Set(Uuid::new_v4()),
-                file_hash: Set(filehash.to_string()),
-                chunk: Set(chunk.clone()),
-                embedding: Set(embedding.clone()),
-                created_at: Set(Utc::now()),
-            })
-            .collect::<Vec<_>>();
-
-        Embedding::insert_many(values)
-            .exec(&db)
-            .await
-            .map_err(|e| {
-                AppError::InternalServerError(format!("Failed to cache embeddings: {}", e))
-            })?;
+        Ok(true)
+    }
 
-        for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
-            self.add(chunk.clone(), embedding.clone()).map_err(|e| {
-                AppError::InternalServerError(format!("Failed to add chunk and embedding: {:?}", e))
-            })?;
+    async fn add(
+        &self,
+        owner_id: Uuid,
+        file_hash: &str,
+        chunks: Vec<DocumentChunk>,
+        embeddings: Vec<Vec<f32>>,
+        chunking: &str,
+    ) -> Result<usize, AppError> {
+        if self.is_loaded(owner_id, file_hash) {
+            return Ok(0);
         }
 
-        println!("RagStore created successfully");
-        Ok(())
+        let cached = cache_embeddings(
+            &self.db,
+            owner_id,
+            file_hash,
+            &chunks,
+            &embeddings,
+            chunking,
+            &self.embedding_model,
+        )
+        .await?;
+
+        // index the rows as stored so the resident copy carries their version;
+        // when a concurrent request cached this document first, these are its
+        // rows rather than ours, keeping the two identical
+        self.prepare(owner_id, file_hash).await?;
+
+        Ok(if cached { chunks.len() } else { 0 })
     }
 
-    pub fn search(
+    async fn search(
         &self,
         query_embedding: &[f32],
         count: usize,
-    ) -> Result<Vec<(String, f32)>, AppError> {
+        scope: &SearchScope<'_>,
+    ) -> Result<Vec<(DocumentChunk, f32)>, AppError> {
+        let state = self.read_state();
+
         let results = self
             .index
-            .search(query_embedding, count)
+            .filtered_search(query_embedding, count, |key| {
+                state
+                    .chunks
+                    .get(&key)
+                    .is_some_and(|entry| scope.allows(entry.owner_id, &entry.file_hash))
+            })
             .map_err(|e| AppError::InternalServerError(format!("Search failed: {}", e)))?;
         let output = results
             .keys
             .iter()
             .zip(results.distances.iter())
-            .filter_map(|(&idx, distance)| {
-                let idx = idx as usize;
-                self.chunks.get(idx).map(|chunk| (chunk.clone(), *distance))
+            .filter_map(|(key, distance)| {
+                state
+                    .chunks
+                    .get(key)
+                    .filter(|entry| scope.allows(entry.owner_id, &entry.file_hash))
+                    .map(|entry| (entry.chunk.clone(), *distance))
             })
             .collect();
 
         Ok(output)
     }
+
+    async fn delete_by_hash(&self, owner_id: Uuid, file_hash: &str) -> Result<usize, AppError> {
+        let deleted = Embedding::delete_many()
+            .filter(Column::OwnerId.eq(owner_id))
+            .filter(Column::FileHash.eq(file_hash))
+            .exec(&self.db)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        let unloaded = self.unload(owner_id, file_hash);
+
+        Ok(unloaded.max(deleted.rows_affected as usize))
+    }
+
+    async fn evict(&self, owner_id: Uuid, file_hash: &str) {
+        self.unload(owner_id, file_hash);
+    }
+
+    async fn persist(&self) -> Result<(), AppError> {
+        self.save_snapshot().await
+    }
+}
+
+/// A copy of the index and its manifest, taken to be written to disk.
+struct Snapshot {
+    index: Vec<u8>,
+    manifest: SnapshotManifest,
+    chunks: usize,
+}
+
+impl Snapshot {
+    /// Files are written aside and renamed into place so a crash mid-save
+    /// never leaves a torn snapshot behind.
+    fn write(self, snapshot_dir: &Path) -> Result<(), AppError> {
+        fs::create_dir_all(snapshot_dir).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to create snapshot directory: {}", e))
+        })?;
+
+        let manifest = serde_json::to_vec(&self.manifest).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to serialize snapshot manifest: {}", e))
+        })?;
+
+        let index_path = snapshot_dir.join(SNAPSHOT_INDEX_FILE);
+        let manifest_path = snapshot_dir.join(SNAPSHOT_MANIFEST_FILE);
+        let index_tmp = index_path.with_extension("usearch.tmp");
+        let manifest_tmp = manifest_path.with_extension("json.tmp");
+
+        fs::write(&index_tmp, &self.index).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to save snapshot index: {}", e))
+        })?;
+        fs::write(&manifest_tmp, manifest).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to write snapshot manifest: {}", e))
+        })?;
+
+        fs::rename(&index_tmp, &index_path)
+            .and_then(|_| fs::rename(&manifest_tmp, &manifest_path))
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Failed to move snapshot into place: {}", e))
+            })?;
+
+        println!("RagStore snapshot saved with {} chunks", self.chunks);
+
+        Ok(())
+    }
 }
 
 // pub fn cosine_similarity(vec1: &[f32], vec2: &[f32]) -> f32 {
@@ -194,24 +737,125 @@ impl RagStore {
 //     }
 // }
 
-pub fn retrieve_relevant_chunks(
-    query: &str,
-    store: &RagStore,
-    model: &TextEmbedding,
-) -> Result<Vec<String>, AppError> {
-    let query_embedding = model
-        .embed(vec![query.to_string()], None)
-        .map_err(|e| AppError::InternalServerError(format!("Failed to embed query: {}", e)))?[0]
-        .clone();
-
-    //search for top-k relevant chunks (e.g. 5)
-    let results = store.search(&query_embedding, 5)?;
-
-    //extract chunks, ignoring distances for now
-    let chunks = results
-        .into_iter()
-        .map(|(chunk, _distance)| chunk)
-        .collect();
-
-    Ok(chunks)
+fn path_str(path: &Path) -> Result<&str, AppError> {
+    path.to_str().ok_or_else(|| {
+        AppError::InternalServerError(format!("Invalid snapshot path: {}", path.display()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+    use uuid::Uuid;
+
+    use super::RagStore;
+    use crate::{
+        services::{
+            rag_generate::{ChunkProvenance, DocumentChunk, EmbeddingModelConfig},
+            vector_store::{cache_embeddings, VectorStore},
+        },
+        utils::test_db::test_db,
+    };
+
+    fn chunks(texts: &[&str], dimensions: usize) -> (Vec<DocumentChunk>, Vec<Vec<f32>>) {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let chunk = DocumentChunk {
+                    text: text.to_string(),
+                    provenance: ChunkProvenance::default(),
+                };
+                let mut vector = vec![0.0; dimensions];
+                vector[i] = 1.0;
+                (chunk, vector)
+            })
+            .unzip()
+    }
+
+    #[tokio::test]
+    async fn a_saved_snapshot_restores() {
+        let embedding: EmbeddingModelConfig = "AllMiniLML6V2".parse().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = RagStore::empty(
+            DatabaseConnection::Disconnected,
+            &embedding,
+            dir.path().to_path_buf(),
+        )
+        .unwrap();
+
+        let owner_id = Uuid::new_v4();
+        let (chunks, vectors) = chunks(&["one", "two", "three"], embedding.dimensions);
+        let rows = chunks.into_iter().zip(vectors).collect();
+        store.load(owner_id, "hash", Utc::now(), rows).unwrap();
+        store.save_snapshot().await.unwrap();
+
+        let (restored, _) =
+            RagStore::restore_snapshot(DatabaseConnection::Disconnected, &embedding, dir.path())
+                .unwrap()
+                .unwrap();
+
+        assert!(restored.is_loaded(owner_id, "hash"));
+        assert_eq!(restored.index.size(), 3);
+        assert_eq!(restored.read_state().chunks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn rows_replaced_elsewhere_are_reloaded() {
+        let Some(db) = test_db().await else { return };
+        let embedding: EmbeddingModelConfig = "AllMiniLML6V2".parse().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = RagStore::empty(db.clone(), &embedding, dir.path().to_path_buf()).unwrap();
+        let owner_id = Uuid::new_v4();
+
+        let (old_chunks, old_vectors) = chunks(&["old one", "old two"], embedding.dimensions);
+        cache_embeddings(
+            &db,
+            owner_id,
+            "hash",
+            &old_chunks,
+            &old_vectors,
+            "test",
+            &embedding.name,
+        )
+        .await
+        .unwrap();
+        assert!(store.prepare(owner_id, "hash").await.unwrap());
+
+        // what a reindex swap on another replica does
+        let delete = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "DELETE FROM cached_embeddings WHERE owner_id = $1",
+            [owner_id.into()],
+        );
+        db.execute(delete.clone()).await.unwrap();
+        let (new_chunks, new_vectors) = chunks(&["new one", "new two"], embedding.dimensions);
+        cache_embeddings(
+            &db,
+            owner_id,
+            "hash",
+            &new_chunks,
+            &new_vectors,
+            "test",
+            &embedding.name,
+        )
+        .await
+        .unwrap();
+
+        assert!(store.prepare(owner_id, "hash").await.unwrap());
+        let mut texts: Vec<String> = store
+            .read_state()
+            .chunks
+            .values()
+            .map(|entry| entry.chunk.text.clone())
+            .collect();
+        texts.sort();
+        assert_eq!(texts, ["new one", "new two"]);
+        assert_eq!(store.index.size(), 2);
+
+        db.execute(delete).await.unwrap();
+        assert!(!store.prepare(owner_id, "hash").await.unwrap());
+        assert!(!store.is_loaded(owner_id, "hash"));
+    }
 }
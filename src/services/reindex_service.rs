@@ -0,0 +1,497 @@
+use chrono::Utc;
+use fastembed::TextEmbedding;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    FromQueryResult, IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    SqlErr, Statement, TransactionTrait,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::task;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        documents::{Column as DocumentColumn, Entity as Document, Model as DocumentModel},
+        embeddings::{Column as EmbeddingColumn, Entity as Embedding},
+        reindex_jobs::{
+            ActiveModel as ReindexJobActiveModel, Column, Entity as ReindexJob,
+            Model as ReindexJobModel, ReindexStatus, StartReindexRequest,
+        },
+        staged_embeddings::{
+            ActiveModel as StagedEmbeddingActiveModel, Column as StagedColumn,
+            Entity as StagedEmbedding,
+        },
+    },
+    services::{
+        document_service::document_chunking,
+        heartbeat::{Heartbeat, STALE_AFTER_SECONDS},
+        rag_generate::{implement_rag, ChunkingConfig},
+        vector_store::{has_stale_chunks, VectorStore},
+    },
+    utils::errors::AppError,
+};
+
+/// Documents fetched and rebuilt per round trip.
+const REINDEX_BATCH_SIZE: u64 = 16;
+
+/// How often each replica looks for reindex jobs whose worker died.
+const RESUME_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Starts rebuilding every library document with the current chunking
+/// settings and embedding model. Only one job runs at a time, which a unique
+/// index on running jobs enforces across replicas.
+pub async fn start_reindex_service(
+    db: &DatabaseConnection,
+    request: StartReindexRequest,
+    chunking: &ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) -> Result<ReindexJobModel, AppError> {
+    let running = ReindexJob::find()
+        .filter(Column::Status.eq(ReindexStatus::Running))
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    if let Some(job) = running {
+        return Err(AppError::Conflict(format!(
+            "Reindex job {} is already running",
+            job.id
+        )));
+    }
+
+    let total_documents = Document::find()
+        .count(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    let job = ReindexJobActiveModel {
+        id: Set(Uuid::new_v4()),
+        status: Set(ReindexStatus::Running),
+        chunking: Set(chunking.signature()),
+        embedding_model: Set(rag_store.embedding_model().to_string()),
+        force: Set(request.force),
+        total_documents: Set(total_documents as i32),
+        processed_documents: Set(0),
+        failed_documents: Set(0),
+        cursor: Set(None),
+        error: Set(None),
+        created_at: Set(Utc::now()),
+        updated_at: Set(Utc::now()),
+        completed_at: Set(None),
+    }
+    .insert(db)
+    .await
+    .map_err(|e| match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => {
+            AppError::Conflict("A reindex job is already running".to_string())
+        }
+        _ => AppError::InternalServerError(e.to_string()),
+    })?;
+
+    tokio::spawn(run_reindex_job(
+        db.clone(),
+        job.clone(),
+        chunking.clone(),
+        model,
+        rag_store,
+    ));
+
+    Ok(job)
+}
+
+pub async fn list_reindex_jobs_service(
+    db: &DatabaseConnection,
+) -> Result<Vec<ReindexJobModel>, AppError> {
+    ReindexJob::find()
+        .order_by_desc(Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+pub async fn get_reindex_job_service(
+    db: &DatabaseConnection,
+    job_id: Uuid,
+) -> Result<ReindexJobModel, AppError> {
+    ReindexJob::find_by_id(job_id)
+        .one(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound("Reindex job not found".to_string()))
+}
+
+/// Periodically picks up reindex jobs whose worker died, from their last
+/// committed document.
+pub fn spawn_reindex_resumer(
+    db: DatabaseConnection,
+    chunking: ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) =
+                resume_reindex_jobs(&db, &chunking, model.clone(), rag_store.clone()).await
+            {
+                eprintln!("Failed to resume reindex jobs: {:?}", e);
+            }
+            tokio::time::sleep(RESUME_INTERVAL).await;
+        }
+    });
+}
+
+/// Claims running jobs whose heartbeat went stale. SKIP LOCKED lets every
+/// replica look without two of them resuming the same job. A job started
+/// under different settings is abandoned, since finishing it would swap in
+/// embeddings this server cannot search.
+async fn resume_reindex_jobs(
+    db: &DatabaseConnection,
+    chunking: &ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) -> Result<(), AppError> {
+    let running = ReindexJob::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "UPDATE reindex_jobs SET updated_at = now() \
+             WHERE id IN ( \
+                 SELECT id FROM reindex_jobs \
+                 WHERE status = 'running' AND updated_at < now() - make_interval(secs => $1) \
+                 FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING *",
+            [(STALE_AFTER_SECONDS as f64).into()],
+        ))
+        .all(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    for job in running {
+        if job.chunking != chunking.signature()
+            || job.embedding_model != rag_store.embedding_model()
+        {
+            fail_job(
+                db,
+                &job,
+                "Chunking settings or embedding model changed while the job was running",
+            )
+            .await;
+            continue;
+        }
+
+        println!("Resuming reindex job {} after {:?}", job.id, job.cursor);
+        tokio::spawn(run_reindex_job(
+            db.clone(),
+            job,
+            chunking.clone(),
+            model.clone(),
+            rag_store.clone(),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn run_reindex_job(
+    db: DatabaseConnection,
+    job: ReindexJobModel,
+    chunking: ChunkingConfig,
+    model: Arc<TextEmbedding>,
+    rag_store: Arc<dyn VectorStore>,
+) {
+    let _heartbeat = Heartbeat::start(db.clone(), "reindex_jobs", job.id);
+
+    let result = rebuild_documents(&db, &job, &chunking, model).await;
+    let result = match result {
+        Ok(()) => swap_in_staged(&db, job.id, rag_store.as_ref()).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Reindex job {} failed: {:?}", job.id, e);
+        fail_job(&db, &job, &format!("{:?}", e)).await;
+    }
+}
+
+/// Walks documents in id order after the job's cursor. Each document's staged
+/// chunks are written in the same transaction that advances the cursor, so a
+/// crash never loses or duplicates work.
+async fn rebuild_documents(
+    db: &DatabaseConnection,
+    job: &ReindexJobModel,
+    chunking: &ChunkingConfig,
+    model: Arc<TextEmbedding>,
+) -> Result<(), AppError> {
+    let mut cursor = job.cursor;
+    let mut processed = job.processed_documents;
+    let mut failed = job.failed_documents;
+
+    loop {
+        let mut query = Document::find()
+            .order_by_asc(DocumentColumn::Id)
+            .limit(REINDEX_BATCH_SIZE);
+        if let Some(cursor) = cursor {
+            query = query.filter(DocumentColumn::Id.gt(cursor));
+        }
+
+        let batch = query
+            .all(db)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        for document in batch {
+            let config = document_chunking(chunking, &document);
+            let staged = stage_document(db, job, &document, &config, model.clone()).await;
+
+            let rows = match staged {
+                Ok(rows) => {
+                    processed += 1;
+                    rows
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Reindex job {} could not rebuild document {}: {:?}",
+                        job.id, document.id, e
+                    );
+                    failed += 1;
+                    None
+                }
+            };
+
+            let txn = db
+                .begin()
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+            if let Some(rows) = rows {
+                StagedEmbedding::delete_many()
+                    .filter(StagedColumn::JobId.eq(job.id))
+                    .filter(StagedColumn::OwnerId.eq(document.owner_id))
+                    .filter(StagedColumn::FileHash.eq(document.file_hash.clone()))
+                    .exec(&txn)
+                    .await
+                    .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+                StagedEmbedding::insert_many(rows)
+                    .exec(&txn)
+                    .await
+                    .map_err(|e| {
+                        AppError::InternalServerError(format!("Failed to stage embeddings: {}", e))
+                    })?;
+            }
+
+            let mut active = job.clone().into_active_model();
+            active.cursor = Set(Some(document.id));
+            active.processed_documents = Set(processed);
+            active.failed_documents = Set(failed);
+            active.updated_at = Set(Utc::now());
+            active
+                .update(&txn)
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+            txn.commit()
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+            cursor = Some(document.id);
+        }
+    }
+}
+
+async fn is_cached(db: &DatabaseConnection, document: &DocumentModel) -> Result<bool, AppError> {
+    let cached = Embedding::find()
+        .filter(EmbeddingColumn::OwnerId.eq(document.owner_id))
+        .filter(EmbeddingColumn::FileHash.eq(document.file_hash.clone()))
+        .count(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(cached > 0)
+}
+
+/// Re-chunks and re-embeds one document. Returns `None` when its cached
+/// embeddings are already current and the job is not forced.
+async fn stage_document(
+    db: &DatabaseConnection,
+    job: &ReindexJobModel,
+    document: &DocumentModel,
+    chunking: &ChunkingConfig,
+    model: Arc<TextEmbedding>,
+) -> Result<Option<Vec<StagedEmbeddingActiveModel>>, AppError> {
+    let signature = chunking.signature();
+
+    if !job.force
+        && is_cached(db, document).await?
+        && !has_stale_chunks(
+            db,
+            document.owner_id,
+            &document.file_hash,
+            &signature,
+            &job.embedding_model,
+        )
+        .await?
+    {
+        return Ok(None);
+    }
+
+    let content = document.extracted_text.clone();
+    let config = chunking.clone();
+    let (chunks, embeddings) =
+        task::spawn_blocking(move || implement_rag(&content, &model, &config))
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Embedding task failed: {}", e)))?
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Failed to implement RAG: {}", e))
+            })?;
+
+    let rows = chunks
+        .into_iter()
+        .zip(embeddings)
+        .map(|(chunk, embedding)| StagedEmbeddingActiveModel {
+            id: Set(Uuid::new_v4()),
+            job_id: Set(job.id),
+            owner_id: Set(document.owner_id),
+            file_hash: Set(document.file_hash.clone()),
+            chunk: Set(chunk.text),
+            embedding: Set(embedding),
+            chunking: Set(signature.clone()),
+            embedding_model: Set(job.embedding_model.clone()),
+            page: Set(chunk.provenance.page),
+            heading: Set(chunk.provenance.heading),
+            start_offset: Set(chunk.provenance.start_offset),
+            end_offset: Set(chunk.provenance.end_offset),
+            created_at: Set(Utc::now()),
+        })
+        .collect();
+
+    Ok(Some(rows))
+}
+
+#[derive(FromQueryResult)]
+struct StagedDocument {
+    owner_id: Uuid,
+    file_hash: String,
+}
+
+/// Replaces the cached embeddings of every rebuilt document with its staged
+/// chunks in a single transaction, so searches see either the old index or
+/// the new one and never a mix.
+async fn swap_in_staged(
+    db: &DatabaseConnection,
+    job_id: Uuid,
+    rag_store: &dyn VectorStore,
+) -> Result<(), AppError> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    let statements = [
+        // documents deleted while the job ran must not come back
+        "DELETE FROM staged_embeddings s WHERE s.job_id = $1 AND NOT EXISTS ( \
+             SELECT 1 FROM documents d WHERE d.owner_id = s.owner_id AND d.file_hash = s.file_hash \
+         )",
+        "DELETE FROM cached_embeddings c \
+         USING (SELECT DISTINCT owner_id, file_hash FROM staged_embeddings WHERE job_id = $1) s \
+         WHERE c.owner_id = s.owner_id AND c.file_hash = s.file_hash",
+        "INSERT INTO cached_embeddings \
+             (id, owner_id, file_hash, chunk, embedding, chunking, embedding_model, \
+              page, heading, start_offset, end_offset, created_at) \
+         SELECT id, owner_id, file_hash, chunk, embedding, chunking, embedding_model, \
+                page, heading, start_offset, end_offset, now() \
+         FROM staged_embeddings WHERE job_id = $1",
+        "UPDATE documents d \
+         SET chunk_count = s.chunks, status = 'ready', error = NULL, updated_at = now() \
+         FROM (SELECT owner_id, file_hash, count(*)::int AS chunks \
+               FROM staged_embeddings WHERE job_id = $1 GROUP BY owner_id, file_hash) s \
+         WHERE d.owner_id = s.owner_id AND d.file_hash = s.file_hash",
+    ];
+
+    for sql in statements {
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            [job_id.into()],
+        ))
+        .await
+        .map_err(|e| {
+            AppError::InternalServerError(format!("Failed to swap in embeddings: {}", e))
+        })?;
+    }
+
+    let swapped = StagedDocument::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT DISTINCT owner_id, file_hash FROM staged_embeddings WHERE job_id = $1",
+        [job_id.into()],
+    ))
+    .all(&txn)
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    StagedEmbedding::delete_many()
+        .filter(StagedColumn::JobId.eq(job_id))
+        .exec(&txn)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    let job = ReindexJobActiveModel {
+        id: Set(job_id),
+        status: Set(ReindexStatus::Completed),
+        updated_at: Set(Utc::now()),
+        completed_at: Set(Some(Utc::now())),
+        ..Default::default()
+    };
+    job.update(&txn)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    txn.commit()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    // this replica's index still holds the old vectors; drop them now and
+    // snapshot before a restart can replay them. Other replicas see the newer
+    // rows and reload the documents the next time they are used
+    for document in &swapped {
+        rag_store
+            .evict(document.owner_id, &document.file_hash)
+            .await;
+    }
+    rag_store.persist().await?;
+
+    println!(
+        "Reindex job {} swapped in {} documents",
+        job_id,
+        swapped.len()
+    );
+
+    Ok(())
+}
+
+async fn fail_job(db: &DatabaseConnection, job: &ReindexJobModel, error: &str) {
+    let cleanup = StagedEmbedding::delete_many()
+        .filter(StagedColumn::JobId.eq(job.id))
+        .exec(db)
+        .await;
+    if let Err(e) = cleanup {
+        eprintln!(
+            "Failed to discard staged embeddings of job {}: {}",
+            job.id, e
+        );
+    }
+
+    let mut active = job.clone().into_active_model();
+    active.status = Set(ReindexStatus::Failed);
+    active.error = Set(Some(error.to_string()));
+    active.updated_at = Set(Utc::now());
+
+    if let Err(e) = active.update(db).await {
+        eprintln!("Failed to update reindex job {} status: {}", job.id, e);
+    }
+}
@@ -0,0 +1,238 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::utils::errors::AppError;
+
+/// The first retry waits around this long, every further retry twice as long.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound for the backoff between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Attempts per call, including the first.
+    pub max_attempts: u32,
+    /// Time a call may take over all of its attempts and waits.
+    pub deadline: Duration,
+    /// Failed calls in a row that open the circuit.
+    pub breaker_threshold: u32,
+    /// How long an open circuit rejects calls before trying one again.
+    pub breaker_cooldown: Duration,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// HTTP client for one upstream service.
+///
+/// Network errors, timeouts, 429 and 5xx responses are retried with jittered
+/// exponential backoff, or after the server's Retry-After, for as long as
+/// the deadline allows. Once `breaker_threshold` calls in a row have failed
+/// that way the circuit opens, and calls are rejected with 503 without
+/// reaching the service until the cooldown has passed. A single trial call is
+/// then let through; its success closes the circuit again. Requests sent with
+/// [`send_to`](Self::send_to) get a circuit of their own per target, so one
+/// failing model does not cut off the others served by the same API.
+pub struct ResilientClient {
+    name: String,
+    http: reqwest::Client,
+    policy: RetryPolicy,
+    breakers: Mutex<HashMap<String, Breaker>>,
+}
+
+impl ResilientClient {
+    pub fn new(name: &str, policy: RetryPolicy) -> Self {
+        Self {
+            name: name.to_string(),
+            http: reqwest::Client::new(),
+            policy,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.http.post(url)
+    }
+
+    /// Sends a request built with this client and returns the first
+    /// successful response. The deadline also covers reading the body, so a
+    /// stalled stream fails instead of hanging.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, AppError> {
+        self.send_to("", request).await
+    }
+
+    /// Like [`send`](Self::send), but failures only count against the circuit
+    /// of `target`, e.g. the model a request is for.
+    pub async fn send_to(
+        &self,
+        target: &str,
+        request: RequestBuilder,
+    ) -> Result<Response, AppError> {
+        let name = match target {
+            "" => self.name.clone(),
+            target => format!("{} ({})", self.name, target),
+        };
+        self.admit(target, &name)?;
+
+        let started = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            let remaining = self.policy.deadline.saturating_sub(started.elapsed());
+            let Some(request) = request.try_clone() else {
+                return Err(AppError::InternalServerError(format!(
+                    "{} request cannot be retried",
+                    name
+                )));
+            };
+
+            let (message, retry_after) = match request.timeout(remaining).send().await {
+                Ok(response) if response.status().is_success() => {
+                    self.record(target, &name, true);
+                    return Ok(response);
+                }
+                Ok(response) if is_transient(response.status()) => {
+                    let status = response.status();
+                    let retry_after = retry_after(&response);
+                    let body = response.text().await.unwrap_or_default();
+                    (format!("status {}: {}", status, body), retry_after)
+                }
+                Ok(response) => {
+                    // the service is up, it rejected this particular request
+                    self.record(target, &name, true);
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(AppError::InternalServerError(format!(
+                        "{} request failed with status {}: {}",
+                        name, status, body
+                    )));
+                }
+                Err(e) => (e.to_string(), None),
+            };
+
+            let delay = retry_after.unwrap_or_else(|| backoff(attempt));
+            if attempt >= self.policy.max_attempts
+                || started.elapsed() + delay >= self.policy.deadline
+            {
+                self.record(target, &name, false);
+                return Err(AppError::ServiceUnavailable(format!(
+                    "{} is unavailable: {}",
+                    name, message
+                )));
+            }
+
+            eprintln!(
+                "{} attempt {} failed, retrying in {:?}: {}",
+                name, attempt, delay, message
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn admit(&self, target: &str, name: &str) -> Result<(), AppError> {
+        let mut breakers = self.breakers.lock().unwrap();
+        let Some(breaker) = breakers.get_mut(target) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+
+        match breaker.open_until {
+            Some(until) if now < until => Err(AppError::ServiceUnavailable(format!(
+                "{} is temporarily unavailable, try again in {} seconds",
+                name,
+                (until - now).as_secs().max(1)
+            ))),
+            Some(_) => {
+                // this call is the trial, keep the circuit open for the others
+                // until it has finished
+                breaker.open_until = Some(now + self.policy.deadline);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn record(&self, target: &str, name: &str, success: bool) {
+        let mut breakers = self.breakers.lock().unwrap();
+
+        if success {
+            breakers.remove(target);
+            return;
+        }
+
+        let breaker = breakers.entry(target.to_string()).or_default();
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures >= self.policy.breaker_threshold {
+            eprintln!(
+                "{} failed {} times in a row, opening circuit for {:?}",
+                name, breaker.consecutive_failures, self.policy.breaker_cooldown
+            );
+            breaker.open_until = Some(Instant::now() + self.policy.breaker_cooldown);
+        }
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Half the exponential delay plus a random share of the other half, so
+/// clients that failed together do not retry together.
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_DELAY);
+    let half = delay.as_millis() as u64 / 2;
+
+    Duration::from_millis(half + rand::thread_rng().gen_range(0..=half))
+}
+
+/// Retry-After as either delay seconds or an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_open_circuit_only_rejects_its_own_target() {
+        let client = ResilientClient::new(
+            "LLM",
+            RetryPolicy {
+                max_attempts: 1,
+                deadline: Duration::from_secs(1),
+                breaker_threshold: 2,
+                breaker_cooldown: Duration::from_secs(60),
+            },
+        );
+
+        client.record("primary", "LLM (primary)", false);
+        assert!(client.admit("primary", "LLM (primary)").is_ok());
+        client.record("primary", "LLM (primary)", false);
+
+        assert!(client.admit("primary", "LLM (primary)").is_err());
+        assert!(client.admit("fallback", "LLM (fallback)").is_ok());
+
+        client.record("primary", "LLM (primary)", true);
+        assert!(client.admit("primary", "LLM (primary)").is_ok());
+    }
+}
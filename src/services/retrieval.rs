@@ -0,0 +1,202 @@
+use fastembed::TextRerank;
+use sea_orm::{DatabaseConnection, DbBackend, FromQueryResult, Statement, Value};
+use std::{collections::HashMap, sync::Arc};
+use tokio::task;
+
+use crate::{
+    models::features::RetrievalStrategy,
+    services::{
+        rag_generate::{ChunkProvenance, DocumentChunk},
+        vector_store::SearchScope,
+    },
+    utils::errors::AppError,
+};
+
+/// Strategy and models used to retrieve context for one request.
+#[derive(Clone)]
+pub struct RetrievalOptions {
+    pub strategy: RetrievalStrategy,
+    pub reranker: Option<Arc<TextRerank>>,
+}
+
+impl RetrievalOptions {
+    pub fn new(
+        strategy: RetrievalStrategy,
+        reranker: Option<Arc<TextRerank>>,
+    ) -> Result<Self, AppError> {
+        if strategy == RetrievalStrategy::HybridRerank && reranker.is_none() {
+            return Err(AppError::BadRequest(
+                "Reranking is not enabled on this server".to_string(),
+            ));
+        }
+
+        Ok(Self { strategy, reranker })
+    }
+}
+
+/// Constant from the original RRF paper; dampens the weight of top ranks so
+/// neither retriever dominates.
+const RRF_K: f32 = 60.0;
+
+#[derive(FromQueryResult)]
+struct LexicalMatch {
+    chunk: String,
+    page: Option<i32>,
+    heading: Option<String>,
+    start_offset: Option<i64>,
+    end_offset: Option<i64>,
+}
+
+/// Full-text search over stored chunks, best match first. Any query term may
+/// match, so exact tokens such as formulas or syllabus codes are found even
+/// when the rest of the query is phrased differently. Like the vector search,
+/// it only sees chunks embedded by `embedding_model`.
+pub async fn lexical_search(
+    db: &DatabaseConnection,
+    query: &str,
+    count: usize,
+    scope: &SearchScope<'_>,
+    embedding_model: &str,
+) -> Result<Vec<DocumentChunk>, AppError> {
+    let terms = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .collect::<Vec<_>>();
+
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let values: Vec<Value> = vec![
+        terms.join(" ").into(),
+        scope.owner_id.into(),
+        scope.file_hashes.to_vec().into(),
+        (count as i64).into(),
+        embedding_model.into(),
+    ];
+
+    // plainto_tsquery normalizes and drops stop words; swapping its AND
+    // operators for OR turns it into a ranked any-term match
+    let rows = LexicalMatch::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "WITH q AS ( \
+             SELECT NULLIF(replace(plainto_tsquery('english', $1)::text, '&', '|'), '')::tsquery AS query \
+         ) \
+         SELECT chunk, page, heading, start_offset, end_offset FROM cached_embeddings, q \
+         WHERE owner_id = $2 AND file_hash = ANY($3) AND embedding_model = $5 \
+           AND chunk_tsv @@ q.query \
+         ORDER BY ts_rank_cd(chunk_tsv, q.query) DESC \
+         LIMIT $4",
+        values,
+    ))
+    .all(db)
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Full-text search failed: {}", e)))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| DocumentChunk {
+            text: row.chunk,
+            provenance: ChunkProvenance {
+                page: row.page,
+                heading: row.heading,
+                start_offset: row.start_offset,
+                end_offset: row.end_offset,
+            },
+        })
+        .collect())
+}
+
+/// Merges ranked lists with reciprocal rank fusion. Chunks ranked highly by
+/// several retrievers rise to the top; ties keep first-seen order.
+pub fn reciprocal_rank_fusion(rankings: Vec<Vec<DocumentChunk>>) -> Vec<DocumentChunk> {
+    let mut scores: HashMap<String, f32> = HashMap::new();
+    let mut order: Vec<DocumentChunk> = Vec::new();
+
+    for ranking in rankings {
+        for (rank, chunk) in ranking.into_iter().enumerate() {
+            let score = scores.entry(chunk.text.clone()).or_insert_with(|| {
+                order.push(chunk);
+                0.0
+            });
+            *score += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+
+    order.sort_by(|a, b| scores[&b.text].total_cmp(&scores[&a.text]));
+    order
+}
+
+/// Reorders candidates by cross-encoder relevance to the query.
+pub async fn rerank(
+    reranker: Arc<TextRerank>,
+    query: &str,
+    candidates: Vec<DocumentChunk>,
+) -> Result<Vec<DocumentChunk>, AppError> {
+    if candidates.len() < 2 {
+        return Ok(candidates);
+    }
+
+    let query = query.to_string();
+    let results = task::spawn_blocking(move || {
+        let documents = candidates
+            .iter()
+            .map(|chunk| chunk.text.as_str())
+            .collect::<Vec<_>>();
+        reranker
+            .rerank(query.as_str(), documents, false, None)
+            .map(|results| {
+                results
+                    .into_iter()
+                    .map(|result| candidates[result.index].clone())
+                    .collect::<Vec<_>>()
+            })
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Rerank task failed: {}", e)))?
+    .map_err(|e| AppError::InternalServerError(format!("Failed to rerank chunks: {}", e)))?;
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::lexical_search;
+    use crate::{
+        services::{
+            rag_generate::{ChunkProvenance, DocumentChunk},
+            vector_store::{cache_embeddings, SearchScope},
+        },
+        utils::test_db::test_db,
+    };
+
+    #[tokio::test]
+    async fn lexical_search_skips_chunks_of_other_models() {
+        let Some(db) = test_db().await else { return };
+        let owner_id = Uuid::new_v4();
+        // a document named after each model, as if embedded before and after a switch
+        for model in ["old-model", "new-model"] {
+            let chunk = DocumentChunk {
+                text: format!("Photosynthesis as embedded by {}", model),
+                provenance: ChunkProvenance::default(),
+            };
+            cache_embeddings(&db, owner_id, model, &[chunk], &[vec![1.0]], "test", model)
+                .await
+                .unwrap();
+        }
+
+        let file_hashes = ["old-model".to_string(), "new-model".to_string()];
+        let scope = SearchScope {
+            owner_id,
+            file_hashes: &file_hashes,
+        };
+        let hits = lexical_search(&db, "photosynthesis", 10, &scope, "new-model")
+            .await
+            .unwrap();
+
+        let texts: Vec<&str> = hits.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(texts, ["Photosynthesis as embedded by new-model"]);
+    }
+}
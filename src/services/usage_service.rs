@@ -0,0 +1,138 @@
+use chrono::{Duration, NaiveDate, Utc};
+use sea_orm::{
+    sea_query::OnConflict, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, Set,
+    Statement, Value,
+};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        features::GeneratedResponse,
+        generation_jobs::GenerationKind,
+        llm_usage::{
+            ActiveModel as LlmUsageActiveModel, Column as LlmUsageColumn, Entity as LlmUsage,
+            UsageGrouping, UsageReport, UsageRow,
+        },
+    },
+    services::llm_service::ModelConfig,
+    utils::errors::AppError,
+};
+
+/// Days covered by a report when the query gives no `from` date.
+const DEFAULT_REPORT_DAYS: i64 = 30;
+
+/// Charges the tokens of a finished LLM call to `owner_id`. A provider that
+/// reports no usage is logged and skipped; a failed insert never fails the
+/// generation it belongs to. Calls made for a job are keyed by the job and
+/// `call_index`, so a retried job replaces the usage of its earlier attempts.
+pub async fn record_llm_usage(
+    db: &DatabaseConnection,
+    models: &ModelConfig,
+    owner_id: Uuid,
+    kind: GenerationKind,
+    job_id: Option<Uuid>,
+    call_index: i32,
+    response: &GeneratedResponse,
+) {
+    let (Some(model), Some(usage)) = (&response.model, &response.usage) else {
+        println!("No token usage reported for {:?} generation", kind);
+        return;
+    };
+
+    let usage = LlmUsageActiveModel {
+        id: Set(Uuid::new_v4()),
+        owner_id: Set(owner_id),
+        kind: Set(kind),
+        model: Set(model.clone()),
+        prompt_tokens: Set(usage.prompt_tokens),
+        completion_tokens: Set(usage.completion_tokens),
+        estimated_cost: Set(models.estimate_cost(model, usage)),
+        job_id: Set(job_id),
+        call_index: Set(call_index),
+        created_at: Set(Utc::now()),
+    };
+
+    let result = LlmUsage::insert(usage)
+        .on_conflict(
+            OnConflict::columns([LlmUsageColumn::JobId, LlmUsageColumn::CallIndex])
+                .update_columns([
+                    LlmUsageColumn::Model,
+                    LlmUsageColumn::PromptTokens,
+                    LlmUsageColumn::CompletionTokens,
+                    LlmUsageColumn::EstimatedCost,
+                    LlmUsageColumn::CreatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await;
+
+    if let Err(e) = result {
+        eprintln!("Failed to record LLM usage of user {}: {}", owner_id, e);
+    }
+}
+
+/// Sums usage between two inclusive dates, for one user or, with no
+/// `owner_id`, for everyone.
+pub async fn usage_report_service(
+    db: &DatabaseConnection,
+    owner_id: Option<Uuid>,
+    group_by: UsageGrouping,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<UsageReport, AppError> {
+    let to = to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = from.unwrap_or(to - Duration::days(DEFAULT_REPORT_DAYS - 1));
+    if from > to {
+        return Err(AppError::BadRequest(
+            "from must not be after to".to_string(),
+        ));
+    }
+
+    let key = match group_by {
+        UsageGrouping::Day => "to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD')",
+        UsageGrouping::Feature => "kind",
+        UsageGrouping::Model => "model",
+    };
+
+    let start = from.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end = (to + Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    let mut values: Vec<Value> = vec![start.into(), end.into()];
+
+    let owner_filter = match owner_id {
+        Some(owner_id) => {
+            values.push(owner_id.into());
+            "AND owner_id = $3"
+        }
+        None => "",
+    };
+
+    let rows = UsageRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!(
+            "SELECT {} AS key, COUNT(*) AS calls, \
+                    SUM(prompt_tokens)::bigint AS prompt_tokens, \
+                    SUM(completion_tokens)::bigint AS completion_tokens, \
+                    SUM(estimated_cost) AS estimated_cost \
+             FROM llm_usage \
+             WHERE created_at >= $1 AND created_at < $2 {} \
+             GROUP BY 1 \
+             ORDER BY 1",
+            key, owner_filter
+        ),
+        values,
+    ))
+    .all(db)
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(UsageReport {
+        group_by,
+        from,
+        to,
+        rows,
+    })
+}
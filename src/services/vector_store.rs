@@ -0,0 +1,434 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use fastembed::TextEmbedding;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    PaginatorTrait, QueryFilter, Set, Statement, TransactionTrait,
+};
+use std::{collections::HashMap, sync::Arc};
+use tokio::task;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        embeddings::{
+            ActiveModel as EmbeddingActiveModel, Column, Entity as Embedding,
+            Model as EmbeddingModel,
+        },
+        features::RetrievalStrategy,
+    },
+    services::{
+        citations::citation_id,
+        rag_generate::{implement_rag, ChunkProvenance, ChunkingConfig, DocumentChunk},
+        retrieval::{lexical_search, reciprocal_rank_fusion, rerank, RetrievalOptions},
+    },
+    utils::errors::AppError,
+};
+
+/// Restricts retrieval to the documents of a single user.
+pub struct SearchScope<'a> {
+    pub owner_id: Uuid,
+    pub file_hashes: &'a [String],
+}
+
+impl SearchScope<'_> {
+    pub fn allows(&self, owner_id: Uuid, file_hash: &str) -> bool {
+        owner_id == self.owner_id && self.file_hashes.iter().any(|h| h == file_hash)
+    }
+}
+
+/// Storage and similarity search over document chunk embeddings.
+///
+/// Every chunk belongs to an owner and a document (identified by its file
+/// hash); implementations must never return chunks outside the given scope.
+/// Methods take `&self` so concurrent requests can search in parallel; each
+/// implementation handles its own synchronization.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Code of the embedding model this store's vectors come from. Chunks
+    /// embedded by any other model are never loaded or searched.
+    fn embedding_model(&self) -> &str;
+
+    /// Makes a previously embedded document searchable. Returns false when the
+    /// document has never been embedded for this owner with this store's model.
+    async fn prepare(&self, owner_id: Uuid, file_hash: &str) -> Result<bool, AppError>;
+
+    /// Persists and indexes a document's chunks, tagged with the signature of
+    /// the chunking settings that produced them. Adding a document that is
+    /// already indexed is a no-op. Returns the number of chunks added.
+    async fn add(
+        &self,
+        owner_id: Uuid,
+        file_hash: &str,
+        chunks: Vec<DocumentChunk>,
+        embeddings: Vec<Vec<f32>>,
+        chunking: &str,
+    ) -> Result<usize, AppError>;
+
+    /// Returns the closest chunks with their cosine distance, nearest first.
+    async fn search(
+        &self,
+        query_embedding: &[f32],
+        count: usize,
+        scope: &SearchScope<'_>,
+    ) -> Result<Vec<(DocumentChunk, f32)>, AppError>;
+
+    /// Removes every chunk of a document. Returns the number of chunks removed.
+    async fn delete_by_hash(&self, owner_id: Uuid, file_hash: &str) -> Result<usize, AppError>;
+
+    /// Drops any in-memory copy of a document so the next `prepare` reloads it
+    /// from `cached_embeddings`. Backends that keep nothing in memory ignore it.
+    async fn evict(&self, _owner_id: Uuid, _file_hash: &str) {}
+
+    /// Flushes in-memory state to durable storage, if the backend keeps any.
+    async fn persist(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+/// Writes a document's chunks to `cached_embeddings`, the source of truth
+/// shared by every backend, tagged with the model that embedded them. Returns false without writing anything when the
+/// document was already cached, e.g. by a concurrent upload of the same file.
+pub async fn cache_embeddings(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    file_hash: &str,
+    chunks: &[DocumentChunk],
+    embeddings: &[Vec<f32>],
+    chunking: &str,
+    embedding_model: &str,
+) -> Result<bool, AppError> {
+    if embeddings.len() != chunks.len() {
+        return Err(AppError::InternalServerError(
+            "Chunks and embeddings must have equal dimensions".to_string(),
+        ));
+    }
+
+    if embeddings.is_empty() {
+        return Err(AppError::InternalServerError(
+            "Cannot create ragstore with empty embeddings".to_string(),
+        ));
+    }
+
+    let dimension = embeddings[0].len();
+
+    if embeddings.iter().any(|emb| emb.len() != dimension) {
+        return Err(AppError::InternalServerError(
+            "All embeddings must have the same dimension".to_string(),
+        ));
+    }
+
+    let values = chunks
+        .iter()
+        .zip(embeddings.iter())
+        .map(|(chunk, embedding)| EmbeddingActiveModel {
+            id: Set(Uuid::new_v4()),
+            owner_id: Set(Some(owner_id)),
+            file_hash: Set(file_hash.to_string()),
+            chunk: Set(chunk.text.clone()),
+            embedding: Set(embedding.clone()),
+            chunking: Set(Some(chunking.to_string())),
+            embedding_model: Set(Some(embedding_model.to_string())),
+            page: Set(chunk.provenance.page),
+            heading: Set(chunk.provenance.heading.clone()),
+            start_offset: Set(chunk.provenance.start_offset),
+            end_offset: Set(chunk.provenance.end_offset),
+            created_at: Set(Utc::now()),
+        })
+        .collect::<Vec<_>>();
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    // serialize writers of the same document across requests and replicas
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_advisory_xact_lock(hashtext($1))",
+        [format!("{}:{}", owner_id, file_hash).into()],
+    ))
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    let existing = Embedding::find()
+        .filter(Column::OwnerId.eq(owner_id))
+        .filter(Column::FileHash.eq(file_hash))
+        .count(&txn)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    if existing > 0 {
+        return Ok(false);
+    }
+
+    Embedding::insert_many(values)
+        .exec(&txn)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Failed to cache embeddings: {}", e)))?;
+
+    txn.commit()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(true)
+}
+
+impl From<EmbeddingModel> for DocumentChunk {
+    fn from(row: EmbeddingModel) -> Self {
+        Self {
+            text: row.chunk,
+            provenance: ChunkProvenance {
+                page: row.page,
+                heading: row.heading,
+                start_offset: row.start_offset,
+                end_offset: row.end_offset,
+            },
+        }
+    }
+}
+
+/// Returns true when some of the document's cached chunks were produced with
+/// different chunking settings or another embedding model, or predate the
+/// columns recording them.
+pub async fn has_stale_chunks(
+    db: &DatabaseConnection,
+    owner_id: Uuid,
+    file_hash: &str,
+    signature: &str,
+    embedding_model: &str,
+) -> Result<bool, AppError> {
+    let stale = Embedding::find()
+        .filter(Column::OwnerId.eq(owner_id))
+        .filter(Column::FileHash.eq(file_hash))
+        .filter(
+            Condition::any()
+                .add(Column::Chunking.is_null())
+                .add(Column::Chunking.ne(signature))
+                .add(Column::EmbeddingModel.is_null())
+                .add(Column::EmbeddingModel.ne(embedding_model)),
+        )
+        .count(db)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database error: {}", e)))?;
+
+    Ok(stale > 0)
+}
+
+/// Makes sure a user's uploaded document is searchable, reusing cached
+/// embeddings when the same file has been seen before with the same chunking
+/// settings and embedding model.
+pub async fn ensure_document_loaded(
+    db: &DatabaseConnection,
+    store: &dyn VectorStore,
+    model: Arc<TextEmbedding>,
+    owner_id: Uuid,
+    file_hash: &str,
+    content: &str,
+    chunking: &ChunkingConfig,
+) -> Result<(), AppError> {
+    let signature = chunking.signature();
+
+    if has_stale_chunks(db, owner_id, file_hash, &signature, store.embedding_model()).await? {
+        println!(
+            "Chunking settings or embedding model changed for file hash: {}, rebuilding embeddings",
+            file_hash
+        );
+        store.delete_by_hash(owner_id, file_hash).await?;
+    }
+
+    if store.prepare(owner_id, file_hash).await? {
+        println!("Using cached embeddings for file hash: {}", file_hash);
+        return Ok(());
+    }
+
+    println!("No cached embeddings found for file hash: {}", file_hash);
+
+    // chunking and embedding are CPU bound, keep them off the async workers
+    let content = content.to_string();
+    let config = chunking.clone();
+    let (chunks, embeddings) =
+        task::spawn_blocking(move || implement_rag(&content, &model, &config))
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Embedding task failed: {}", e)))?
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Failed to implement RAG: {}", e))
+            })?;
+
+    if chunks.is_empty() || embeddings.is_empty() {
+        return Err(AppError::BadRequest(
+            "No chunks or embeddings generated.".to_string(),
+        ));
+    }
+
+    println!("Adding {} chunks and embeddings to the store", chunks.len());
+
+    store
+        .add(owner_id, file_hash, chunks, embeddings, &signature)
+        .await
+        .map_err(|e| {
+            AppError::InternalServerError(format!("Failed to add chunks and embeddings: {:?}", e))
+        })?;
+
+    Ok(())
+}
+
+/// A document retrieval draws context from, labelled for the prompt.
+#[derive(Debug, Clone)]
+pub struct ContextSource {
+    pub name: String,
+    pub file_hash: String,
+}
+
+/// A chunk chosen as context for a request.
+#[derive(Debug, Clone)]
+pub struct RetrievedChunk {
+    pub chunk: DocumentChunk,
+    /// Cosine similarity to the query, if the chunk came from vector search.
+    pub similarity: Option<f32>,
+}
+
+/// The chunks retrieved from one source, nearest first.
+#[derive(Debug)]
+pub struct SourceContext {
+    pub source: ContextSource,
+    pub chunks: Vec<RetrievedChunk>,
+}
+
+/// Chunks retrieved per request, shared between its sources.
+const CONTEXT_CHUNK_BUDGET: usize = 12;
+/// Every source contributes at least this many chunks.
+const MIN_CHUNKS_PER_SOURCE: usize = 2;
+
+/// Hybrid strategies fetch this many candidates per chunk kept, giving fusion
+/// and reranking something to choose from.
+const CANDIDATE_MULTIPLIER: usize = 3;
+
+pub async fn retrieve_relevant_chunks(
+    db: &DatabaseConnection,
+    query: &str,
+    store: &dyn VectorStore,
+    model: Arc<TextEmbedding>,
+    owner_id: Uuid,
+    sources: &[ContextSource],
+    options: &RetrievalOptions,
+) -> Result<Vec<SourceContext>, AppError> {
+    if sources.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let embed_query = query.to_string();
+    let query_embedding = task::spawn_blocking(move || model.embed(vec![embed_query], None))
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Embedding task failed: {}", e)))?
+        .map_err(|e| AppError::InternalServerError(format!("Failed to embed query: {}", e)))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::InternalServerError("Failed to embed query".to_string()))?;
+
+    let per_source = (CONTEXT_CHUNK_BUDGET / sources.len()).max(MIN_CHUNKS_PER_SOURCE);
+
+    // search each document on its own so a long textbook cannot crowd out
+    // shorter notes
+    let mut contexts = Vec::with_capacity(sources.len());
+    for source in sources {
+        let file_hashes = [source.file_hash.clone()];
+        let scope = SearchScope {
+            owner_id,
+            file_hashes: &file_hashes,
+        };
+
+        let candidates = match options.strategy {
+            RetrievalStrategy::Vector => per_source,
+            _ => per_source * CANDIDATE_MULTIPLIER,
+        };
+
+        let mut similarities = HashMap::new();
+        let vector_hits = store
+            .search(&query_embedding, candidates, &scope)
+            .await?
+            .into_iter()
+            .map(|(chunk, distance)| {
+                similarities.insert(chunk.text.clone(), 1.0 - distance);
+                chunk
+            })
+            .collect::<Vec<_>>();
+
+        let mut chunks = match options.strategy {
+            RetrievalStrategy::Vector => vector_hits,
+            RetrievalStrategy::Hybrid | RetrievalStrategy::HybridRerank => {
+                let lexical_hits =
+                    lexical_search(db, query, candidates, &scope, store.embedding_model()).await?;
+                reciprocal_rank_fusion(vec![vector_hits, lexical_hits])
+            }
+        };
+
+        if let (RetrievalStrategy::HybridRerank, Some(reranker)) =
+            (options.strategy, options.reranker.clone())
+        {
+            chunks = rerank(reranker, query, chunks).await?;
+        }
+        chunks.truncate(per_source);
+
+        let chunks = chunks
+            .into_iter()
+            .map(|chunk| RetrievedChunk {
+                similarity: similarities.get(&chunk.text).copied(),
+                chunk,
+            })
+            .collect();
+
+        contexts.push(SourceContext {
+            source: source.clone(),
+            chunks,
+        });
+    }
+
+    Ok(contexts)
+}
+
+/// Human readable location of a chunk, e.g. `page 4, "Photosynthesis"`.
+fn describe_location(provenance: &ChunkProvenance) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(page) = provenance.page {
+        parts.push(format!("page {}", page));
+    }
+    if let Some(heading) = &provenance.heading {
+        parts.push(format!("\"{}\"", heading));
+    }
+
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// Renders retrieved chunks grouped under the name of the document they came
+/// from. Each chunk is labelled with its citation id and location so the
+/// model can tell the sources apart and cite them.
+pub fn format_context(contexts: &[SourceContext]) -> String {
+    let mut index = 0;
+
+    contexts
+        .iter()
+        .filter(|context| !context.chunks.is_empty())
+        .map(|context| {
+            let chunks = context
+                .chunks
+                .iter()
+                .map(|retrieved| {
+                    let id = citation_id(index);
+                    index += 1;
+
+                    match describe_location(&retrieved.chunk.provenance) {
+                        Some(location) => {
+                            format!("[{}] ({})\n{}", id, location, retrieved.chunk.text)
+                        }
+                        None => format!("[{}]\n{}", id, retrieved.chunk.text),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            format!("[Source: {}]\n{}", context.source.name, chunks)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
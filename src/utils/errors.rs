@@ -12,6 +12,23 @@ pub enum AppError {
     Conflict(String),
     InternalServerError(String),
     NotFound(String),
+    PayloadTooLarge(String),
+    ServiceUnavailable(String),
+}
+
+impl AppError {
+    /// The message without the variant, for reporting errors outside a response.
+    pub fn into_message(self) -> String {
+        match self {
+            Self::BadRequest(msg)
+            | Self::Unauthorized(msg)
+            | Self::Conflict(msg)
+            | Self::InternalServerError(msg)
+            | Self::NotFound(msg)
+            | Self::PayloadTooLarge(msg)
+            | Self::ServiceUnavailable(msg) => msg,
+        }
+    }
 }
 
 impl IntoResponse for AppError {
@@ -22,6 +39,8 @@ impl IntoResponse for AppError {
             Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
             Self::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            Self::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
+            Self::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
         };
 
         (status, Json(error_response(&message))).into_response()
@@ -1,2 +1,4 @@
 pub mod errors;
 pub mod response;
+#[cfg(test)]
+pub mod test_db;
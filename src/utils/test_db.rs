@@ -0,0 +1,23 @@
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{Database, DatabaseConnection};
+use tokio::sync::OnceCell;
+
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+/// A connection to the database in `TEST_DATABASE_URL`, migrated once per
+/// test run. Tests needing a database skip themselves when it is not set.
+pub async fn test_db() -> Option<DatabaseConnection> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL is not set, skipping database test");
+        return None;
+    };
+
+    MIGRATED
+        .get_or_init(|| async {
+            let db = Database::connect(&url).await.unwrap();
+            Migrator::up(&db, None).await.unwrap();
+        })
+        .await;
+
+    Some(Database::connect(&url).await.unwrap())
+}